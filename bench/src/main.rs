@@ -0,0 +1,319 @@
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::iter;
+use std::process;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use ws::util::Token;
+use ws::{Builder, Handler, Handshake, Message, Result, Sender, Settings};
+
+use protocol::{solve_pow, FindSource, WsMessage, WsMessageType, ID_MAX_LEN};
+
+const SUBMIT: Token = Token(1);
+const STOP: Token = Token(2);
+
+const USAGE: &str = "usage: cargo run -p bench --release -- [--url URL] [--watchers N] \
+                     [--bruteforcers M] [--rate FINDS_PER_SEC] [--duration SECS] [--pid SERVER_PID]";
+
+struct Options {
+    url: String,
+    watchers: usize,
+    bruteforcers: usize,
+    rate: f64,
+    duration: u64,
+    pid: Option<u32>,
+}
+
+impl Options {
+    fn parse() -> std::result::Result<Options, String> {
+        let mut options = Options {
+            url: "ws://127.0.0.1:8002/ws".to_owned(),
+            watchers: 100,
+            bruteforcers: 10,
+            rate: 0.1,
+            duration: 60,
+            pid: None,
+        };
+
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(USAGE)?;
+
+            match arg.as_str() {
+                "--url" => options.url = value,
+                "--watchers" => options.watchers = parse(&arg, &value)?,
+                "--bruteforcers" => options.bruteforcers = parse(&arg, &value)?,
+                "--rate" => options.rate = parse(&arg, &value)?,
+                "--duration" => options.duration = parse(&arg, &value)?,
+                "--pid" => options.pid = Some(parse(&arg, &value)?),
+                _ => return Err(USAGE.to_owned()),
+            }
+        }
+
+        if options.rate <= 0.0 {
+            return Err("--rate must be positive".to_owned());
+        }
+
+        Ok(options)
+    }
+
+    fn submit_interval_ms(&self) -> u64 {
+        (1000.0 / self.rate) as u64
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: &str) -> std::result::Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", arg, value))
+}
+
+/// Everything measured during a run, shared by every connection of the
+/// single event loop.
+#[derive(Default)]
+struct Report {
+    pid: Option<u32>,
+    memory_before: Option<u64>,
+    memory_at_stop: Option<u64>,
+    watchers_connected: usize,
+    sent_at: HashMap<String, Instant>,
+    acked: u64,
+    rejected: u64,
+    broadcast: HashSet<String>,
+    latencies: Vec<Duration>,
+}
+
+struct Connection {
+    out: Sender,
+    report: Rc<RefCell<Report>>,
+    is_bruteforcer: bool,
+    /// Set on one connection, which ends the run for everyone.
+    stop_after_ms: Option<u64>,
+    submit_interval_ms: u64,
+    challenge: String,
+    difficulty: u32,
+    next_client_msg_id: u64,
+}
+
+impl Connection {
+    fn submit(&mut self) -> Result<()> {
+        if self.difficulty > 0 && self.challenge.is_empty() {
+            return Ok(());
+        }
+
+        // Seven characters with a letter, so the server takes it for a
+        // plausible imgur ID instead of quarantining it.
+        let id = iter::once('a')
+            .chain(iter::repeat(()).map(|()| thread_rng().sample(Alphanumeric)))
+            .take(ID_MAX_LEN)
+            .collect::<String>();
+        let nonce = solve_pow(&self.challenge, &id, self.difficulty);
+
+        self.next_client_msg_id += 1;
+        self.report
+            .borrow_mut()
+            .sent_at
+            .insert(id.clone(), Instant::now());
+
+        let ws_message = WsMessage {
+            text: Some(id),
+            number: Some(nonce),
+            client_msg_id: Some(self.next_client_msg_id),
+            source: Some(FindSource::ServerBot),
+            ..WsMessage::new(WsMessageType::New)
+        };
+
+        match serde_json::to_string(&ws_message) {
+            Ok(text) => self.out.send(Message::text(text)),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Handler for Connection {
+    fn on_open(&mut self, _: Handshake) -> Result<()> {
+        if let Some(stop_after_ms) = self.stop_after_ms {
+            self.out.timeout(stop_after_ms, STOP)?;
+        }
+
+        if self.is_bruteforcer {
+            // Spread submissions over the interval instead of sending them
+            // all at once.
+            let delay = thread_rng().gen_range(0, self.submit_interval_ms.max(1));
+            self.out.timeout(delay, SUBMIT)
+        } else {
+            self.report.borrow_mut().watchers_connected += 1;
+            Ok(())
+        }
+    }
+
+    fn on_message(&mut self, msg: Message) -> Result<()> {
+        let received_at = Instant::now();
+        let ws_message = match msg
+            .as_text()
+            .ok()
+            .and_then(|text| serde_json::from_str::<WsMessage>(text).ok())
+        {
+            Some(ws_message) => ws_message,
+            None => return Ok(()),
+        };
+
+        let mut report = self.report.borrow_mut();
+
+        match ws_message.msg_type {
+            WsMessageType::Challenge => {
+                self.challenge = ws_message.text.unwrap_or_default();
+                self.difficulty = ws_message.number.unwrap_or_default() as u32;
+            }
+            WsMessageType::Ack if self.is_bruteforcer => report.acked += 1,
+            WsMessageType::Rejected if self.is_bruteforcer => report.rejected += 1,
+            WsMessageType::New if !self.is_bruteforcer => {
+                if let Some(id) = ws_message.text {
+                    if let Some(sent_at) = report.sent_at.get(&id).copied() {
+                        report.latencies.push(received_at - sent_at);
+                        report.broadcast.insert(id);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn on_timeout(&mut self, event: Token) -> Result<()> {
+        match event {
+            SUBMIT => {
+                self.submit()?;
+                self.out.timeout(self.submit_interval_ms, SUBMIT)
+            }
+            STOP => {
+                let mut report = self.report.borrow_mut();
+                report.memory_at_stop = report.pid.and_then(resident_kib);
+
+                self.out.shutdown()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resident memory of process `pid` in KiB, from `/proc/{pid}/status`.
+fn resident_kib(pid: u32) -> Option<u64> {
+    fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with("VmRSS:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        Duration::default()
+    } else {
+        sorted[(sorted.len() - 1) * percent / 100]
+    }
+}
+
+fn run(options: &Options) -> std::result::Result<(), String> {
+    let url = url::Url::parse(&options.url).map_err(|err| err.to_string())?;
+    let connections = options.watchers + options.bruteforcers;
+    let report = Rc::new(RefCell::new(Report {
+        pid: options.pid,
+        memory_before: options.pid.and_then(resident_kib),
+        ..Report::default()
+    }));
+
+    let factory_report = report.clone();
+    let mut created = 0;
+    let submit_interval_ms = options.submit_interval_ms();
+    let duration_ms = options.duration * 1000;
+
+    let mut ws = Builder::new()
+        .with_settings(Settings {
+            max_connections: connections,
+            ..Settings::default()
+        })
+        .build(move |out: Sender| {
+            created += 1;
+
+            Connection {
+                out,
+                report: factory_report.clone(),
+                is_bruteforcer: created <= options.bruteforcers,
+                stop_after_ms: Some(duration_ms).filter(|_| created == 1),
+                submit_interval_ms,
+                challenge: String::new(),
+                difficulty: 0,
+                next_client_msg_id: 0,
+            }
+        })
+        .map_err(|err| err.to_string())?;
+
+    for _ in 0..connections {
+        ws.connect(url.clone()).map_err(|err| err.to_string())?;
+    }
+
+    ws.run().map_err(|err| err.to_string())?;
+
+    let report = report.borrow();
+    let mut latencies = report.latencies.clone();
+    latencies.sort();
+
+    println!(
+        "{} watchers ({} connected), {} bruteforcers at {} finds/s each, {} s",
+        options.watchers,
+        report.watchers_connected,
+        options.bruteforcers,
+        options.rate,
+        options.duration
+    );
+    println!(
+        "finds: {} sent, {} acked, {} rejected, {} broadcast",
+        report.sent_at.len(),
+        report.acked,
+        report.rejected,
+        report.broadcast.len()
+    );
+    println!(
+        "deliveries: {} of {} expected",
+        latencies.len(),
+        report.broadcast.len() * report.watchers_connected
+    );
+    println!(
+        "latency: p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        percentile(&latencies, 50),
+        percentile(&latencies, 90),
+        percentile(&latencies, 99),
+        latencies.last().copied().unwrap_or_default()
+    );
+
+    if let (Some(before), Some(after)) = (report.memory_before, report.memory_at_stop) {
+        println!(
+            "server memory: {} KiB before connecting, {} KiB at the end, {} KiB per watcher",
+            before,
+            after,
+            after.saturating_sub(before) / report.watchers_connected.max(1) as u64
+        );
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let result = Options::parse().and_then(|options| run(&options));
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,123 @@
+use std::env;
+use std::process;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use client_core::generator::Generator;
+use client_core::{original_url, Bruteforcer, Connection, Event, State};
+use protocol::FindSource;
+
+mod tui;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+const USAGE: &str =
+    "usage: cargo run -p cli --release -- [--url URL] [--threads N] [--seed N] [--tui]";
+
+pub struct Options {
+    pub url: String,
+    pub threads: usize,
+    pub seed: Option<u64>,
+    pub tui: bool,
+}
+
+impl Options {
+    fn parse() -> Result<Options, String> {
+        let mut options = Options {
+            url: "ws://127.0.0.1:8002/ws".to_owned(),
+            threads: 64,
+            seed: None,
+            tui: false,
+        };
+
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            if arg == "--tui" {
+                options.tui = true;
+                continue;
+            }
+
+            let value = args.next().ok_or(USAGE)?;
+
+            match arg.as_str() {
+                "--url" => options.url = value,
+                "--threads" => options.threads = parse(&arg, &value)?,
+                "--seed" => options.seed = Some(parse(&arg, &value)?),
+                _ => return Err(USAGE.to_owned()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn bruteforcer(&self) -> Bruteforcer {
+        let mut bruteforcer = Bruteforcer::default();
+
+        if let Some(seed) = self.seed {
+            bruteforcer.generator = Generator::seeded(seed);
+        }
+
+        bruteforcer
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", arg, value))
+}
+
+/// Bruteforces as soon as connected and prints the URL of every image
+/// anyone finds, one per line.
+fn run(options: &Options, connection: &Connection, events: Receiver<Event>) {
+    let mut bruteforcer = options.bruteforcer();
+
+    loop {
+        match events.recv_timeout(POLL_INTERVAL) {
+            Ok(Event::State(State::Connected)) => {
+                eprintln!("connected to {}", options.url);
+
+                if bruteforcer.is_started() {
+                    bruteforcer.resume(connection);
+                } else {
+                    bruteforcer.start(connection, options.threads, PROBE_INTERVAL);
+                }
+            }
+            Ok(Event::State(State::Disconnected)) => eprintln!("disconnected, reconnecting"),
+            Ok(Event::New(id)) => println!("{}", original_url(&id)),
+            Ok(Event::Rejected(_, reason)) => eprintln!("find rejected: {}", reason),
+            Ok(Event::Announce(announcement)) => eprintln!("announcement: {}", announcement),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        for id in bruteforcer.poll(connection) {
+            eprintln!("you found {}", id);
+        }
+    }
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let (event_sender, events) = mpsc::channel();
+    let connection = Connection::open(options.url.clone(), FindSource::Cli, event_sender);
+
+    if options.tui {
+        if let Err(err) = tui::run(&options, &connection, events) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    } else {
+        run(&options, &connection, events);
+    }
+}
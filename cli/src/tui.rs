@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use std::io::{self, stdout};
+use std::sync::mpsc::Receiver;
+
+use crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use client_core::wall::push_evicting;
+use client_core::{now_ms, original_url, Bruteforcer, Connection, Event, State};
+use protocol::Counts;
+
+use crate::{Options, POLL_INTERVAL, PROBE_INTERVAL};
+
+const MAX_FINDS: usize = 200;
+
+struct Viewer {
+    state: State,
+    counts: Counts,
+    rejected: u64,
+    announcement: Option<String>,
+    /// URLs of the images everyone found, newest first.
+    finds: VecDeque<String>,
+}
+
+/// Shows live statistics and the images found as they come, with `s` to
+/// start or stop bruteforcing and `q` to quit.
+pub fn run(options: &Options, connection: &Connection, events: Receiver<Event>) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+
+    let result = Terminal::new(CrosstermBackend::new(stdout()))
+        .and_then(|mut terminal| run_viewer(&mut terminal, options, connection, events));
+
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+
+    result
+}
+
+fn run_viewer(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    options: &Options,
+    connection: &Connection,
+    events: Receiver<Event>,
+) -> io::Result<()> {
+    let mut bruteforcer = options.bruteforcer();
+    let mut viewer = Viewer {
+        state: State::Connecting,
+        counts: Counts::default(),
+        rejected: 0,
+        announcement: None,
+        finds: VecDeque::new(),
+    };
+
+    loop {
+        while let Ok(event) = events.try_recv() {
+            match event {
+                Event::State(state) => {
+                    if state == State::Connected {
+                        bruteforcer.resume(connection);
+                    }
+
+                    viewer.state = state;
+                }
+                Event::New(id) => {
+                    push_evicting(&mut viewer.finds, original_url(&id), MAX_FINDS, false);
+                }
+                Event::Counts(counts) => viewer.counts = counts,
+                Event::Ack(_) => {}
+                Event::Rejected(_, _) => viewer.rejected += 1,
+                Event::Announce(announcement) => viewer.announcement = Some(announcement),
+            }
+        }
+
+        bruteforcer.poll(connection);
+        terminal.draw(|frame| draw(frame, options, &viewer, &bruteforcer))?;
+
+        if event::poll(POLL_INTERVAL)? {
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => break,
+                        KeyCode::Char('s') if bruteforcer.is_started() => {
+                            bruteforcer.stop(connection)
+                        }
+                        KeyCode::Char('s') => {
+                            bruteforcer.start(connection, options.threads, PROBE_INTERVAL)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    bruteforcer.stop(connection);
+
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, options: &Options, viewer: &Viewer, bruteforcer: &Bruteforcer) {
+    let areas = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .split(frame.size());
+
+    let summary = bruteforcer.session.summary(now_ms());
+    let bruteforcing = if bruteforcer.is_started() {
+        format!(
+            "Bruteforcing with {} threads: {} requests ({:.0}/s), {} found, {} rejected, {} failed",
+            options.threads,
+            summary.attempts,
+            bruteforcer.requests_per_second(),
+            summary.finds,
+            viewer.rejected,
+            bruteforcer.failed
+        )
+    } else {
+        "Not bruteforcing.".to_owned()
+    };
+
+    let stats = Paragraph::new(vec![
+        Line::from(format!(
+            "{:?} to {}, {} watching, {} bruteforcing",
+            viewer.state, options.url, viewer.counts.watching, viewer.counts.bruteforcing
+        )),
+        Line::from(bruteforcing),
+        Line::from(viewer.announcement.clone().unwrap_or_default()),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Random imgur wall"),
+    );
+
+    let finds = List::new(viewer.finds.iter().map(String::as_str))
+        .block(Block::default().borders(Borders::ALL).title("Finds"));
+
+    frame.render_widget(stats, areas[0]);
+    frame.render_widget(finds, areas[1]);
+    frame.render_widget(
+        Paragraph::new("s: start or stop bruteforcing, q: quit"),
+        areas[2],
+    );
+}
@@ -0,0 +1,128 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use protocol::{WsMessage, WsMessageType, RATE_REPORT_SECS};
+
+use crate::connection::Connection;
+use crate::generator::Generator;
+use crate::now_ms;
+use crate::probe::{Probe, Prober};
+use crate::session::Session;
+
+const RATE_REPORT_INTERVAL: Duration = Duration::from_secs(RATE_REPORT_SECS);
+
+/// Bruteforcing on behalf of a connection: runs the prober, submits what
+/// it finds and reports the request rate, like the web client does.
+pub struct Bruteforcer {
+    probes: Receiver<Probe>,
+    probe_sender: Sender<Probe>,
+    prober: Option<Prober>,
+    /// Replace with a seeded one to probe the same IDs every run.
+    pub generator: Generator,
+    pub session: Session,
+    pub failed: u64,
+    reported_attempts: u64,
+    last_rate_report: Instant,
+}
+
+impl Default for Bruteforcer {
+    fn default() -> Self {
+        let (probe_sender, probes) = mpsc::channel();
+
+        Bruteforcer {
+            probes,
+            probe_sender,
+            prober: None,
+            generator: Generator::default(),
+            session: Session::new(now_ms()),
+            failed: 0,
+            reported_attempts: 0,
+            last_rate_report: Instant::now(),
+        }
+    }
+}
+
+impl Bruteforcer {
+    pub fn is_started(&self) -> bool {
+        self.prober.is_some()
+    }
+
+    /// Starts a new session with `threads` probing threads.
+    pub fn start(&mut self, connection: &Connection, threads: usize, interval: Duration) {
+        if self.prober.is_some() {
+            return;
+        }
+
+        self.session = Session::new(now_ms());
+        self.failed = 0;
+        self.reported_attempts = 0;
+        self.last_rate_report = Instant::now();
+        self.prober = Some(Prober::start(
+            threads,
+            interval,
+            &mut self.generator,
+            self.probe_sender.clone(),
+        ));
+        connection.send(&WsMessage::new(WsMessageType::Start));
+    }
+
+    /// Tells the server again that we are bruteforcing, after reconnecting.
+    pub fn resume(&self, connection: &Connection) {
+        if self.is_started() {
+            connection.send(&WsMessage::new(WsMessageType::Start));
+        }
+    }
+
+    pub fn stop(&mut self, connection: &Connection) {
+        if let Some(prober) = self.prober.take() {
+            // Joining waits for the requests in flight, at most the request
+            // timeout.
+            thread::spawn(move || prober.stop());
+            connection.send(&WsMessage::new(WsMessageType::Stop));
+        }
+    }
+
+    /// Records the probes done since the last call, submitting the finds,
+    /// and sends a `Rate` report when one is due. Returns the IDs found.
+    pub fn poll(&mut self, connection: &Connection) -> Vec<String> {
+        let mut found = Vec::new();
+
+        while let Ok(probe) = self.probes.try_recv() {
+            match probe {
+                Probe::Found(id) => {
+                    connection.submit(&id);
+                    self.session.record(true);
+                    found.push(id);
+                }
+                Probe::NotFound => self.session.record(false),
+                Probe::Failed => {
+                    self.session.record(false);
+                    self.failed += 1;
+                }
+            }
+        }
+
+        if self.is_started() && self.last_rate_report.elapsed() >= RATE_REPORT_INTERVAL {
+            self.last_rate_report = Instant::now();
+
+            let attempts = self.session.summary(now_ms()).attempts;
+            let requests = attempts - self.reported_attempts;
+            self.reported_attempts = attempts;
+
+            connection.send(&WsMessage {
+                number: Some(requests / RATE_REPORT_SECS),
+                ..WsMessage::new(WsMessageType::Rate)
+            });
+        }
+
+        found
+    }
+
+    /// Average requests per second of the session.
+    pub fn requests_per_second(&self) -> f64 {
+        let summary = self.session.summary(now_ms());
+
+        summary.attempts as f64 / summary.duration_secs.max(1) as f64
+    }
+}
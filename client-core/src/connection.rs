@@ -0,0 +1,191 @@
+use std::cmp;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use ws::{CloseCode, Handler, Handshake, Message, Result};
+
+use protocol::{normalize_id, Counts, FindSource, WsMessage, WsMessageType};
+
+use crate::finds::{find_message, Challenge};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// What the server told us, decoded.
+pub enum Event {
+    State(State),
+    New(String),
+    Counts(Counts),
+    Ack(u64),
+    Rejected(u64, String),
+    Announce(String),
+}
+
+#[derive(Default)]
+struct Shared {
+    out: Option<ws::Sender>,
+    challenge: Option<Challenge>,
+    next_client_msg_id: u64,
+    resume_token: Option<String>,
+}
+
+/// A connection to a wall that reconnects, with a growing delay, whenever
+/// it is lost.
+pub struct Connection {
+    shared: Arc<Mutex<Shared>>,
+    source: FindSource,
+}
+
+impl Connection {
+    /// Connects to `url`, submitting finds as coming from `source`.
+    pub fn open(url: String, source: FindSource, events: Sender<Event>) -> Connection {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let connection_shared = shared.clone();
+
+        thread::spawn(move || {
+            let mut delay = RECONNECT_DELAY;
+
+            loop {
+                if events.send(Event::State(State::Connecting)).is_err() {
+                    break;
+                }
+
+                // Picks up where the lost connection left off, if the server
+                // still has its session.
+                let resume_url = match &connection_shared.lock().unwrap().resume_token {
+                    Some(token) => {
+                        let separator = if url.contains('?') { '&' } else { '?' };
+                        format!("{}{}resume={}", url, separator, token)
+                    }
+                    None => url.clone(),
+                };
+
+                let result = ws::connect(resume_url, |out| ConnectionHandler {
+                    out,
+                    shared: connection_shared.clone(),
+                    events: events.clone(),
+                });
+
+                let was_connected = connection_shared.lock().unwrap().out.take().is_some();
+
+                if result.is_err() || events.send(Event::State(State::Disconnected)).is_err() {
+                    break;
+                }
+
+                if was_connected {
+                    delay = RECONNECT_DELAY;
+                }
+
+                thread::sleep(delay);
+                delay = cmp::min(delay * 2, MAX_RECONNECT_DELAY);
+            }
+        });
+
+        Connection { shared, source }
+    }
+
+    pub fn send(&self, message: &WsMessage) -> bool {
+        let shared = self.shared.lock().unwrap();
+
+        match (&shared.out, serde_json::to_string(message)) {
+            (Some(out), Ok(text)) => out.send(Message::text(text)).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Submits a find with the proof of work of the current challenge and
+    /// returns the `client_msg_id` the server acknowledges it with.
+    pub fn submit(&self, id: &str) -> Option<u64> {
+        let id = normalize_id(id)?;
+        let message = {
+            let mut shared = self.shared.lock().unwrap();
+            shared.next_client_msg_id += 1;
+
+            find_message(
+                shared.challenge.as_ref(),
+                id,
+                shared.next_client_msg_id,
+                self.source,
+            )
+        };
+
+        if self.send(&message) {
+            message.client_msg_id
+        } else {
+            None
+        }
+    }
+}
+
+struct ConnectionHandler {
+    out: ws::Sender,
+    shared: Arc<Mutex<Shared>>,
+    events: Sender<Event>,
+}
+
+impl ConnectionHandler {
+    fn event(&self, event: Event) -> Result<()> {
+        match self.events.send(event) {
+            Ok(()) => Ok(()),
+            // Nobody listens anymore.
+            Err(_) => self.out.close(CloseCode::Away),
+        }
+    }
+}
+
+impl Handler for ConnectionHandler {
+    fn on_open(&mut self, _: Handshake) -> Result<()> {
+        self.shared.lock().unwrap().out = Some(self.out.clone());
+        self.event(Event::State(State::Connected))
+    }
+
+    fn on_message(&mut self, msg: Message) -> Result<()> {
+        let ws_message = match msg
+            .as_text()
+            .ok()
+            .and_then(|text| serde_json::from_str::<WsMessage>(text).ok())
+        {
+            Some(ws_message) => ws_message,
+            None => return Ok(()),
+        };
+
+        let event = match ws_message.msg_type {
+            WsMessageType::Challenge => {
+                self.shared.lock().unwrap().challenge = Challenge::from_message(&ws_message);
+                return Ok(());
+            }
+            WsMessageType::Resume => {
+                self.shared.lock().unwrap().resume_token = ws_message.text;
+                return Ok(());
+            }
+            WsMessageType::New => ws_message
+                .text
+                .and_then(|id| normalize_id(&id))
+                .map(Event::New),
+            WsMessageType::Counts => ws_message
+                .text
+                .and_then(|text| serde_json::from_str(&text).ok())
+                .map(Event::Counts),
+            WsMessageType::Ack => ws_message.client_msg_id.map(Event::Ack),
+            WsMessageType::Rejected => ws_message.client_msg_id.map(|client_msg_id| {
+                Event::Rejected(client_msg_id, ws_message.reason.unwrap_or_default())
+            }),
+            WsMessageType::Announce => ws_message.text.map(Event::Announce),
+            _ => None,
+        };
+
+        match event {
+            Some(event) => self.event(event),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use protocol::{solve_pow, FindSource, WsMessage, WsMessageType};
+
+/// The proof of work the server asks finds to carry.
+#[derive(Clone)]
+pub struct Challenge {
+    pub text: String,
+    pub difficulty: u32,
+}
+
+impl Challenge {
+    /// Reads the text and difficulty of a `Challenge` message.
+    pub fn from_message(message: &WsMessage) -> Option<Challenge> {
+        match (&message.text, message.number) {
+            (Some(text), Some(difficulty)) => Some(Challenge {
+                text: text.clone(),
+                difficulty: difficulty as u32,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A `New` message submitting `id` from `source`, with a proof of work once
+/// the server sent a challenge.
+pub fn find_message(
+    challenge: Option<&Challenge>,
+    id: String,
+    client_msg_id: u64,
+    source: FindSource,
+) -> WsMessage {
+    WsMessage {
+        number: challenge.map(|challenge| solve_pow(&challenge.text, &id, challenge.difficulty)),
+        text: Some(id),
+        client_msg_id: Some(client_msg_id),
+        source: Some(source),
+        ..WsMessage::new(WsMessageType::New)
+    }
+}
@@ -0,0 +1,54 @@
+use std::iter;
+
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+use protocol::ID_MAX_LEN;
+
+/// The source of every random ID and event of a client. Seeded, it gives
+/// the exact same sequence every time, to reproduce a bug or a test run.
+pub struct Generator {
+    rng: StdRng,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl Generator {
+    pub fn seeded(seed: u64) -> Self {
+        Generator {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// A generator of its own, for another thread or component, seeded
+    /// from this one so that seeding this one seeds it too.
+    pub fn fork(&mut self) -> Self {
+        Generator::seeded(self.rng.next_u64())
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn alphanumeric(&mut self, len: usize) -> String {
+        let rng = &mut self.rng;
+
+        iter::repeat(())
+            .map(|()| rng.sample(Alphanumeric))
+            .take(len)
+            .collect::<String>()
+    }
+
+    /// An ID to probe. Only seven character IDs are tried, imgur stopped
+    /// handing out shorter ones long ago.
+    pub fn id(&mut self) -> String {
+        self.alphanumeric(ID_MAX_LEN)
+    }
+}
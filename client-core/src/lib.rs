@@ -0,0 +1,59 @@
+//! What a wall client does besides drawing it, shared by the web, desktop
+//! and other frontends. The websocket connection and the prober need the
+//! `native` feature, the rest also builds for the browser.
+
+#[cfg(feature = "native")]
+pub mod bruteforce;
+#[cfg(feature = "native")]
+pub mod connection;
+pub mod finds;
+pub mod generator;
+pub mod lifecycle;
+pub mod pacing;
+#[cfg(feature = "native")]
+pub mod probe;
+pub mod seq;
+pub mod session;
+pub mod wall;
+
+#[cfg(feature = "native")]
+pub use bruteforce::Bruteforcer;
+#[cfg(feature = "native")]
+pub use connection::{Connection, Event, State};
+pub use finds::{find_message, Challenge};
+#[cfg(feature = "native")]
+pub use probe::{Probe, Prober};
+pub use seq::{SeqCheck, SeqTracker};
+pub use session::{Session, SessionSummary};
+
+/// Full size URL of image `id`.
+pub fn original_url(id: &str) -> String {
+    format!("https://i.imgur.com/{}.png", id)
+}
+
+/// Imgur's request form, pre-filled to report image `id`. The page and the
+/// file are both linked, the staff reviewing it may need either.
+pub fn report_url(id: &str) -> String {
+    format!(
+        "https://help.imgur.com/hc/en-us/requests/new?tf_subject=Reporting%20image%20{id}&tf_description=I%20am%20reporting%20https%3A%2F%2Fimgur.com%2F{id}%20(https%3A%2F%2Fi.imgur.com%2F{id}.png)%20because%20",
+        id = id
+    )
+}
+
+/// URL of the imgur page showing image `id`, with its context and a way to
+/// report it.
+pub fn page_url(id: &str) -> String {
+    format!("https://imgur.com/{}", id)
+}
+
+/// Milliseconds since the unix epoch, the clock `Session` expects. The
+/// browser has `Date::now()` instead.
+#[cfg(feature = "native")]
+pub fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
@@ -0,0 +1,349 @@
+//! Where a client is between connecting, watching and bruteforcing, and
+//! what the connection coming and going makes it do. The frontend carries
+//! that out behind `Link`.
+
+use std::time::Duration;
+
+use protocol::{WsMessage, WsMessageType};
+
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Where the app is between connecting, watching and bruteforcing.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    /// Not connected yet.
+    Idle,
+    Watching,
+    Bruteforcing,
+    /// Bruteforcing, but quiet hours hold the probes back.
+    Paused,
+    /// The connection dropped, to go back to the given state once it is up
+    /// again.
+    Reconnecting(Resume),
+}
+
+/// The connected states a reconnect goes back to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Resume {
+    Watching,
+    Bruteforcing,
+    Paused,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Connected,
+    Lost,
+    Start,
+    Stop,
+    Pause,
+    Resume,
+}
+
+/// What the connection makes the client do.
+pub trait Link {
+    fn send(&mut self, message: WsMessage);
+
+    /// Connects again after `delay`.
+    fn reconnect_after(&mut self, delay: Duration);
+
+    /// Starts bruteforcing, as if the user asked to.
+    fn start(&mut self);
+}
+
+/// What the client tells a server it connects to.
+pub struct Hello<'a> {
+    pub client_id: &'a str,
+    pub now_ms: u64,
+    /// Where to replay broadcasts from, after a reconnection.
+    pub resync_from: Option<u64>,
+    pub watch_only: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::Idle
+    }
+}
+
+impl From<Resume> for State {
+    fn from(resume: Resume) -> Self {
+        match resume {
+            Resume::Watching => State::Watching,
+            Resume::Bruteforcing => State::Bruteforcing,
+            Resume::Paused => State::Paused,
+        }
+    }
+}
+
+impl State {
+    /// The state `event` leads to, or `None` if it doesn't apply here.
+    pub fn next(self, event: Event) -> Option<State> {
+        match (self, event) {
+            (State::Idle, Event::Connected) => Some(State::Watching),
+            (State::Watching, Event::Start) => Some(State::Bruteforcing),
+            (State::Bruteforcing, Event::Stop) | (State::Paused, Event::Stop) => {
+                Some(State::Watching)
+            }
+            (State::Bruteforcing, Event::Pause) => Some(State::Paused),
+            (State::Paused, Event::Resume) => Some(State::Bruteforcing),
+            (State::Reconnecting(resume), Event::Connected) => Some(resume.into()),
+            // Starting, stopping and quiet hours still apply while
+            // reconnecting, to the state that is resumed.
+            (State::Reconnecting(resume), event) => State::from(resume)
+                .next(event)
+                .and_then(State::resume)
+                .map(State::Reconnecting),
+            (state, Event::Lost) => state.resume().map(State::Reconnecting),
+            _ => None,
+        }
+    }
+
+    /// Greets the server once connected and picks up where the client left
+    /// off. Returns whether it asked for a replay.
+    pub fn connected(&mut self, link: &mut impl Link, hello: &Hello) -> bool {
+        let previous = *self;
+        *self = self.next(Event::Connected).unwrap_or(*self);
+
+        link.send(WsMessage {
+            text: Some(hello.client_id.to_owned()),
+            ..WsMessage::new(WsMessageType::Hello)
+        });
+        link.send(WsMessage {
+            number: Some(hello.now_ms),
+            ..WsMessage::new(WsMessageType::Time)
+        });
+
+        if let Some(from_seq) = hello.resync_from {
+            link.send(WsMessage {
+                number: Some(from_seq),
+                ..WsMessage::new(WsMessageType::Resync)
+            });
+        }
+
+        match previous {
+            State::Idle if !hello.watch_only => link.start(),
+            State::Reconnecting(_) if self.is_bruteforcing() => {
+                link.send(WsMessage::new(WsMessageType::Start))
+            }
+            _ => {}
+        }
+
+        hello.resync_from.is_some()
+    }
+
+    /// Waits to be resumed once the connection dropped, and reconnects.
+    pub fn lost(&mut self, link: &mut impl Link) {
+        *self = self.next(Event::Lost).unwrap_or(*self);
+        link.reconnect_after(RECONNECT_DELAY);
+    }
+
+    /// Whether the user asked to bruteforce, even if probes are on hold.
+    pub fn is_bruteforcing(self) -> bool {
+        match self {
+            State::Bruteforcing | State::Paused => true,
+            State::Reconnecting(resume) => resume != Resume::Watching,
+            State::Idle | State::Watching => false,
+        }
+    }
+
+    fn resume(self) -> Option<Resume> {
+        match self {
+            State::Watching => Some(Resume::Watching),
+            State::Bruteforcing => Some(Resume::Bruteforcing),
+            State::Paused => Some(Resume::Paused),
+            State::Idle | State::Reconnecting(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeLink {
+        /// The types of the messages sent, as on the wire.
+        sent: Vec<String>,
+        reconnect_after: Option<Duration>,
+        started: bool,
+    }
+
+    impl Link for FakeLink {
+        fn send(&mut self, message: WsMessage) {
+            self.sent.push(
+                serde_json::to_value(message.msg_type)
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_owned(),
+            );
+        }
+
+        fn reconnect_after(&mut self, delay: Duration) {
+            self.reconnect_after = Some(delay);
+        }
+
+        fn start(&mut self) {
+            self.started = true;
+        }
+    }
+
+    #[test]
+    fn every_event_from_every_state() {
+        use self::Resume as R;
+        use self::State::*;
+
+        let events = [
+            Event::Connected,
+            Event::Lost,
+            Event::Start,
+            Event::Stop,
+            Event::Pause,
+            Event::Resume,
+        ];
+        // What each state goes to on each of `events`, in order.
+        let table = [
+            (Idle, [Some(Watching), None, None, None, None, None]),
+            (
+                Watching,
+                [
+                    None,
+                    Some(Reconnecting(R::Watching)),
+                    Some(Bruteforcing),
+                    None,
+                    None,
+                    None,
+                ],
+            ),
+            (
+                Bruteforcing,
+                [
+                    None,
+                    Some(Reconnecting(R::Bruteforcing)),
+                    None,
+                    Some(Watching),
+                    Some(Paused),
+                    None,
+                ],
+            ),
+            (
+                Paused,
+                [
+                    None,
+                    Some(Reconnecting(R::Paused)),
+                    None,
+                    Some(Watching),
+                    None,
+                    Some(Bruteforcing),
+                ],
+            ),
+            (
+                Reconnecting(R::Watching),
+                [
+                    Some(Watching),
+                    None,
+                    Some(Reconnecting(R::Bruteforcing)),
+                    None,
+                    None,
+                    None,
+                ],
+            ),
+            (
+                Reconnecting(R::Bruteforcing),
+                [
+                    Some(Bruteforcing),
+                    None,
+                    None,
+                    Some(Reconnecting(R::Watching)),
+                    Some(Reconnecting(R::Paused)),
+                    None,
+                ],
+            ),
+            (
+                Reconnecting(R::Paused),
+                [
+                    Some(Paused),
+                    None,
+                    None,
+                    Some(Reconnecting(R::Watching)),
+                    None,
+                    Some(Reconnecting(R::Bruteforcing)),
+                ],
+            ),
+        ];
+
+        for (state, nexts) in &table {
+            for (event, next) in events.iter().zip(nexts) {
+                assert_eq!(state.next(*event), *next, "{:?} on {:?}", state, event);
+            }
+        }
+    }
+
+    #[test]
+    fn bruteforcing_includes_paused_and_reconnecting_to_it() {
+        assert!(!State::Idle.is_bruteforcing());
+        assert!(!State::Watching.is_bruteforcing());
+        assert!(State::Bruteforcing.is_bruteforcing());
+        assert!(State::Paused.is_bruteforcing());
+        assert!(!State::Reconnecting(Resume::Watching).is_bruteforcing());
+        assert!(State::Reconnecting(Resume::Bruteforcing).is_bruteforcing());
+        assert!(State::Reconnecting(Resume::Paused).is_bruteforcing());
+    }
+
+    fn hello(resync_from: Option<u64>, watch_only: bool) -> Hello<'static> {
+        Hello {
+            client_id: "client",
+            now_ms: 0,
+            resync_from,
+            watch_only,
+        }
+    }
+
+    #[test]
+    fn connecting_greets_the_server_and_starts() {
+        let mut link = FakeLink::default();
+        let mut state = State::Idle;
+
+        assert!(!state.connected(&mut link, &hello(None, false)));
+        assert_eq!(state, State::Watching);
+        assert_eq!(link.sent, ["Hello", "Time"]);
+        assert!(link.started);
+    }
+
+    #[test]
+    fn watch_only_clients_do_not_start() {
+        let mut link = FakeLink::default();
+        let mut state = State::Idle;
+
+        state.connected(&mut link, &hello(None, true));
+        assert!(!link.started);
+    }
+
+    #[test]
+    fn reconnecting_resyncs_and_resumes_bruteforcing() {
+        let mut link = FakeLink::default();
+        let mut state = State::Bruteforcing;
+
+        state.lost(&mut link);
+        assert_eq!(state, State::Reconnecting(Resume::Bruteforcing));
+        assert_eq!(link.reconnect_after, Some(RECONNECT_DELAY));
+
+        assert!(state.connected(&mut link, &hello(Some(42), false)));
+        assert_eq!(state, State::Bruteforcing);
+        assert_eq!(link.sent, ["Hello", "Time", "Resync", "Start"]);
+        assert!(!link.started);
+    }
+
+    #[test]
+    fn reconnecting_while_watching_does_not_start() {
+        let mut link = FakeLink::default();
+        let mut state = State::Watching;
+
+        state.lost(&mut link);
+        state.connected(&mut link, &hello(Some(1), false));
+        assert_eq!(state, State::Watching);
+        assert!(!link.sent.iter().any(|sent| sent == "Start"));
+        assert!(!link.started);
+    }
+}
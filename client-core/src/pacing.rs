@@ -0,0 +1,266 @@
+//! How often a client probes imgur and shows the finds broadcast to it. The
+//! timers that drive it belong to the frontend, behind `Schedule`.
+
+use std::time::Duration;
+
+pub const DATA_SAVER_MIN_INTERVAL: Duration = Duration::from_millis(1000);
+/// How many times slower clients probe while the server says imgur is
+/// throttling them.
+pub const SWARM_BACKOFF_SLOWDOWN: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Tick {
+    /// Time for the next probe.
+    Probe,
+    /// Time the wall can show another find.
+    ResetRateLimit,
+}
+
+/// The repeating timers pacing runs on.
+pub trait Schedule {
+    /// Makes `tick` happen every `every`, in place of what it did before.
+    fn every(&mut self, tick: Tick, every: Duration);
+
+    fn cancel(&mut self, tick: Tick);
+}
+
+/// The lowest settings the deployment and the operator allow.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Minimums {
+    pub interval_ms: u64,
+    pub rate_limit: u64,
+}
+
+pub struct Pacing {
+    interval: Duration,
+    /// Whether `interval` had to be raised to the minimum, so the settings
+    /// can say why.
+    interval_clamped: bool,
+    /// Seconds between finds shown on the wall.
+    rate_limit: u64,
+    minimums: Minimums,
+    /// Whether the wall, and the pane of the user's own finds in the split
+    /// layout, showed a find since the last `ResetRateLimit`.
+    is_rate_limited: bool,
+    own_rate_limited: bool,
+}
+
+impl Pacing {
+    pub fn new(interval: Duration, rate_limit: u64) -> Pacing {
+        Pacing {
+            interval,
+            interval_clamped: false,
+            rate_limit,
+            minimums: Minimums::default(),
+            is_rate_limited: true,
+            own_rate_limited: true,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn is_interval_clamped(&self) -> bool {
+        self.interval_clamped
+    }
+
+    pub fn rate_limit(&self) -> u64 {
+        self.rate_limit
+    }
+
+    /// The lowest interval allowed by both the deployment and the operator.
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_millis(self.minimums.interval_ms)
+    }
+
+    pub fn effective_rate_limit(&self) -> u64 {
+        self.rate_limit.max(self.minimums.rate_limit)
+    }
+
+    /// Sets the interval between probes, raised to the minimum. Probes
+    /// already scheduled keep their interval until `schedule_probes`.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+        self.clamp_interval();
+    }
+
+    pub fn set_minimums(&mut self, minimums: Minimums) {
+        self.minimums = minimums;
+        self.clamp_interval();
+    }
+
+    pub fn set_rate_limit(&mut self, schedule: &mut impl Schedule, rate_limit: u64) {
+        self.rate_limit = rate_limit;
+        self.schedule_rate_limit(schedule);
+    }
+
+    /// Probes at the interval, slowed down for data saving and while the
+    /// swarm backs off.
+    pub fn schedule_probes(
+        &self,
+        schedule: &mut impl Schedule,
+        is_data_saver: bool,
+        is_backing_off: bool,
+    ) {
+        let interval = if is_data_saver {
+            self.interval.max(DATA_SAVER_MIN_INTERVAL)
+        } else {
+            self.interval
+        };
+        let interval = interval.max(self.min_interval());
+        let interval = if is_backing_off {
+            interval * SWARM_BACKOFF_SLOWDOWN
+        } else {
+            interval
+        };
+
+        schedule.every(Tick::Probe, interval);
+    }
+
+    /// Lets a find through every `effective_rate_limit` seconds, or all of
+    /// them when it is 0.
+    pub fn schedule_rate_limit(&mut self, schedule: &mut impl Schedule) {
+        if self.effective_rate_limit() == 0 {
+            schedule.cancel(Tick::ResetRateLimit);
+            self.reset_rate_limit();
+        } else {
+            schedule.every(
+                Tick::ResetRateLimit,
+                Duration::from_secs(self.effective_rate_limit()),
+            );
+        }
+    }
+
+    pub fn reset_rate_limit(&mut self) {
+        self.is_rate_limited = false;
+        self.own_rate_limited = false;
+    }
+
+    /// Whether a find can be shown now, in the user's own pane or on the
+    /// wall, counting it against the rate limit if so.
+    pub fn try_show(&mut self, in_own_pane: bool) -> bool {
+        let is_unlimited = self.effective_rate_limit() == 0;
+        let is_rate_limited = if in_own_pane {
+            &mut self.own_rate_limited
+        } else {
+            &mut self.is_rate_limited
+        };
+
+        if *is_rate_limited && !is_unlimited {
+            return false;
+        }
+
+        *is_rate_limited = true;
+
+        true
+    }
+
+    fn clamp_interval(&mut self) {
+        self.interval_clamped = self.interval < self.min_interval();
+
+        if self.interval_clamped {
+            self.interval = self.min_interval();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeSchedule {
+        probe: Option<Duration>,
+        reset_rate_limit: Option<Duration>,
+    }
+
+    impl FakeSchedule {
+        fn tick(&mut self, tick: Tick) -> &mut Option<Duration> {
+            match tick {
+                Tick::Probe => &mut self.probe,
+                Tick::ResetRateLimit => &mut self.reset_rate_limit,
+            }
+        }
+    }
+
+    impl Schedule for FakeSchedule {
+        fn every(&mut self, tick: Tick, every: Duration) {
+            *self.tick(tick) = Some(every);
+        }
+
+        fn cancel(&mut self, tick: Tick) {
+            *self.tick(tick) = None;
+        }
+    }
+
+    fn minimums(interval_ms: u64, rate_limit: u64) -> Minimums {
+        Minimums {
+            interval_ms,
+            rate_limit,
+        }
+    }
+
+    #[test]
+    fn the_interval_is_raised_to_the_minimum() {
+        let mut pacing = Pacing::new(Duration::from_millis(100), 0);
+
+        pacing.set_minimums(minimums(250, 0));
+        assert_eq!(pacing.interval(), Duration::from_millis(250));
+        assert!(pacing.is_interval_clamped());
+
+        pacing.set_interval(Duration::from_millis(300));
+        assert_eq!(pacing.interval(), Duration::from_millis(300));
+        assert!(!pacing.is_interval_clamped());
+    }
+
+    #[test]
+    fn probes_slow_down_for_data_saving_and_backoff() {
+        let mut schedule = FakeSchedule::default();
+        let pacing = Pacing::new(Duration::from_millis(100), 0);
+
+        pacing.schedule_probes(&mut schedule, false, false);
+        assert_eq!(schedule.probe, Some(Duration::from_millis(100)));
+
+        pacing.schedule_probes(&mut schedule, true, false);
+        assert_eq!(schedule.probe, Some(DATA_SAVER_MIN_INTERVAL));
+
+        pacing.schedule_probes(&mut schedule, false, true);
+        assert_eq!(
+            schedule.probe,
+            Some(Duration::from_millis(100) * SWARM_BACKOFF_SLOWDOWN)
+        );
+    }
+
+    #[test]
+    fn one_find_is_shown_per_rate_limit_in_each_pane() {
+        let mut schedule = FakeSchedule::default();
+        let mut pacing = Pacing::new(Duration::from_millis(100), 3);
+
+        pacing.schedule_rate_limit(&mut schedule);
+        assert_eq!(schedule.reset_rate_limit, Some(Duration::from_secs(3)));
+        assert!(!pacing.try_show(false));
+
+        pacing.reset_rate_limit();
+        assert!(pacing.try_show(false));
+        assert!(!pacing.try_show(false));
+        assert!(pacing.try_show(true));
+        assert!(!pacing.try_show(true));
+    }
+
+    #[test]
+    fn the_operator_minimum_rate_limit_applies() {
+        let mut schedule = FakeSchedule::default();
+        let mut pacing = Pacing::new(Duration::from_millis(100), 0);
+
+        pacing.set_rate_limit(&mut schedule, 0);
+        assert_eq!(schedule.reset_rate_limit, None);
+        assert!(pacing.try_show(false));
+        assert!(pacing.try_show(false));
+
+        pacing.set_minimums(minimums(0, 5));
+        pacing.schedule_rate_limit(&mut schedule);
+        assert_eq!(schedule.reset_rate_limit, Some(Duration::from_secs(5)));
+        assert!(!pacing.try_show(false));
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+
+use crate::generator::Generator;
+use crate::original_url;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The outcome of requesting one random ID.
+pub enum Probe {
+    Found(String),
+    NotFound,
+    Failed,
+}
+
+/// Bruteforces imgur from a pool of threads, each sending a `HEAD`
+/// request for a random ID every `interval` and reporting how it went.
+pub struct Prober {
+    stopped: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Prober {
+    pub fn start(
+        threads: usize,
+        interval: Duration,
+        generator: &mut Generator,
+        results: Sender<Probe>,
+    ) -> Prober {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let client = Client::builder()
+            .redirect(Policy::none())
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        let workers = (0..threads)
+            .map(|_| {
+                let stopped = stopped.clone();
+                let client = client.clone();
+                let results = results.clone();
+                let mut generator = generator.fork();
+
+                thread::spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        if results.send(probe(&client, &generator.id())).is_err() {
+                            break;
+                        }
+
+                        thread::sleep(interval);
+                    }
+                })
+            })
+            .collect();
+
+        Prober { stopped, workers }
+    }
+
+    /// Waits for the requests in flight to finish.
+    pub fn stop(self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        for worker in self.workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Imgur answers an ID that does not exist with a redirect to its
+/// "removed" placeholder, so only a plain success is a find.
+fn probe(client: &Client, id: &str) -> Probe {
+    match client.head(original_url(id)).send() {
+        Ok(response) if response.status() == StatusCode::OK => Probe::Found(id.to_owned()),
+        Ok(_) => Probe::NotFound,
+        Err(_) => Probe::Failed,
+    }
+}
@@ -0,0 +1,41 @@
+/// What to do with a broadcast, from its sequence number.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SeqCheck {
+    Fresh,
+    /// Already seen, to drop.
+    Duplicate,
+    /// Some were missed, to replay from the sequence number carried.
+    Gap(u64),
+}
+
+/// The sequence number of the last broadcast handled.
+#[derive(Default)]
+pub struct SeqTracker {
+    last: Option<u64>,
+}
+
+impl SeqTracker {
+    /// Messages without a sequence number are never broadcasts, so always
+    /// fresh.
+    pub fn check(&mut self, seq: Option<u64>) -> SeqCheck {
+        match (seq, self.last) {
+            (Some(seq), Some(last)) if seq <= last => SeqCheck::Duplicate,
+            (Some(seq), Some(last)) if seq > last + 1 => SeqCheck::Gap(last + 1),
+            (Some(seq), _) => {
+                self.last = Some(seq);
+                SeqCheck::Fresh
+            }
+            (None, _) => SeqCheck::Fresh,
+        }
+    }
+
+    /// Where to resume after a reconnection.
+    pub fn next(&self) -> Option<u64> {
+        self.last.map(|last| last + 1)
+    }
+
+    /// Starts over from the replay the server is about to send.
+    pub fn replay_from(&mut self, start: u64) {
+        self.last = start.checked_sub(1);
+    }
+}
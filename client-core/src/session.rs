@@ -0,0 +1,111 @@
+use serde::Serialize;
+
+const PROBE_BYTES_ESTIMATE: u64 = 800;
+const FOUND_BYTES_ESTIMATE: u64 = 150_000;
+
+pub struct Session {
+    started_at: f64,
+    attempts: u64,
+    finds: u64,
+    streak: u64,
+    best_streak: u64,
+}
+
+#[derive(Serialize)]
+pub struct SessionSummary {
+    pub started_at: f64,
+    pub duration_secs: u64,
+    pub attempts: u64,
+    pub finds: u64,
+    pub hit_rate: f64,
+    pub bandwidth_bytes: u64,
+    pub best_streak: u64,
+}
+
+impl Session {
+    pub fn new(started_at: f64) -> Self {
+        Session {
+            started_at,
+            attempts: 0,
+            finds: 0,
+            streak: 0,
+            best_streak: 0,
+        }
+    }
+
+    pub fn record(&mut self, found: bool) {
+        self.attempts += 1;
+
+        if found {
+            self.finds += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+
+    pub fn streak(&self) -> u64 {
+        self.streak
+    }
+
+    pub fn elapsed_secs(&self, now: f64) -> f64 {
+        ((now - self.started_at) / 1000.0).max(0.0)
+    }
+
+    pub fn summary(&self, now: f64) -> SessionSummary {
+        SessionSummary {
+            started_at: self.started_at,
+            duration_secs: self.elapsed_secs(now) as u64,
+            attempts: self.attempts,
+            finds: self.finds,
+            hit_rate: if self.attempts == 0 {
+                0.0
+            } else {
+                self.finds as f64 / self.attempts as f64
+            },
+            bandwidth_bytes: self.attempts * PROBE_BYTES_ESTIMATE
+                + self.finds * FOUND_BYTES_ESTIMATE,
+            best_streak: self.best_streak,
+        }
+    }
+}
+
+impl SessionSummary {
+    pub fn share_text(&self) -> String {
+        format!(
+            "I found {} random imgur images in {} tries ({:.3}% hit rate) over {} on Random Imgur Wall!",
+            self.finds,
+            self.attempts,
+            self.hit_rate * 100.0,
+            format_duration(self.duration_secs)
+        )
+    }
+}
+
+pub fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!(
+            "{}h {:02}m {:02}s",
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60
+        )
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1 << 30 {
+        format!("{:.2} GiB", bytes as f64 / (1u64 << 30) as f64)
+    } else if bytes >= 1 << 20 {
+        format!("{:.2} MiB", bytes as f64 / (1u64 << 20) as f64)
+    } else if bytes >= 1 << 10 {
+        format!("{:.2} KiB", bytes as f64 / (1u64 << 10) as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+
+/// Adds `item` to a wall holding at most `capacity` items, removing the
+/// oldest ones to make room. New items go at the back when `newest_last`,
+/// at the front otherwise. Returns how many items were removed.
+pub fn push_evicting<T>(
+    items: &mut VecDeque<T>,
+    item: T,
+    capacity: usize,
+    newest_last: bool,
+) -> u64 {
+    let mut evicted = 0;
+
+    while !items.is_empty() && items.len() >= capacity {
+        if newest_last {
+            items.pop_front();
+        } else {
+            items.pop_back();
+        }
+
+        evicted += 1;
+    }
+
+    if newest_last {
+        items.push_back(item);
+    } else {
+        items.push_front(item);
+    }
+
+    evicted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Pushes numbered items onto walls of random capacities, which change
+    /// along the way like the wall size setting can, checking every
+    /// invariant after each push.
+    fn check_random_walls(newest_last: bool) {
+        let mut rng = StdRng::seed_from_u64(962);
+
+        for _ in 0..500 {
+            let mut items = VecDeque::new();
+            let mut capacity = rng.gen_range(0, 20);
+            let mut evicted = 0;
+            let pushes = rng.gen_range(0, 100);
+
+            for item in 0..pushes {
+                if rng.gen_ratio(1, 10) {
+                    capacity = rng.gen_range(0, 20);
+                }
+
+                let before = items.len();
+                let just_evicted = push_evicting(&mut items, item, capacity, newest_last);
+                evicted += just_evicted;
+
+                assert!(items.len() <= capacity.max(1));
+                assert_eq!(before as u64 + 1 - just_evicted, items.len() as u64);
+                assert_eq!(evicted + items.len() as u64, item + 1);
+
+                let newest = if newest_last {
+                    items.back()
+                } else {
+                    items.front()
+                };
+                assert_eq!(newest, Some(&item));
+
+                // Only ever the oldest go, so what is left is the latest
+                // pushes, in order.
+                let mut expected = (item + 1 - items.len() as u64..=item).collect::<Vec<_>>();
+
+                if !newest_last {
+                    expected.reverse();
+                }
+
+                assert!(items.iter().eq(expected.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn newest_last_walls_keep_the_latest_in_order() {
+        check_random_walls(true);
+    }
+
+    #[test]
+    fn newest_first_walls_keep_the_latest_in_order() {
+        check_random_walls(false);
+    }
+
+    #[test]
+    fn nothing_is_evicted_below_capacity() {
+        let mut items = VecDeque::new();
+
+        for item in 0..5 {
+            assert_eq!(push_evicting(&mut items, item, 5, true), 0);
+        }
+
+        assert_eq!(push_evicting(&mut items, 5, 5, true), 1);
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::env;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use iced::widget::{button, column, image, row, scrollable, text, text_input};
+use iced::{executor, time, Application, Command, Element, Length, Settings, Subscription, Theme};
+
+use client_core::wall::push_evicting;
+use client_core::{now_ms, original_url, Bruteforcer, Connection, Event, State};
+use protocol::{Counts, FindSource};
+
+const DEFAULT_URL: &str = "ws://127.0.0.1:8002/ws";
+const DEFAULT_THREADS: usize = 64;
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_IMAGES: usize = 100;
+const IMAGES_PER_ROW: usize = 4;
+
+#[derive(Clone, Debug)]
+enum Message {
+    Poll,
+    Start,
+    Stop,
+    ThreadsChanged(String),
+}
+
+struct Desktop {
+    url: String,
+    connection: Connection,
+    events: Receiver<Event>,
+    state: State,
+    counts: Counts,
+    bruteforcer: Bruteforcer,
+    threads: usize,
+    rejected: u64,
+    images: VecDeque<(String, Option<image::Handle>)>,
+    downloads: Receiver<(String, Vec<u8>)>,
+    download_sender: Sender<(String, Vec<u8>)>,
+    announcement: Option<String>,
+}
+
+impl Application for Desktop {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Theme = Theme;
+    type Flags = String;
+
+    fn new(url: String) -> (Desktop, Command<Message>) {
+        let (event_sender, events) = mpsc::channel();
+        let (download_sender, downloads) = mpsc::channel();
+
+        let desktop = Desktop {
+            connection: Connection::open(url.clone(), FindSource::Desktop, event_sender),
+            url,
+            events,
+            state: State::Connecting,
+            counts: Counts::default(),
+            bruteforcer: Bruteforcer::default(),
+            threads: DEFAULT_THREADS,
+            rejected: 0,
+            images: VecDeque::new(),
+            downloads,
+            download_sender,
+            announcement: None,
+        };
+
+        (desktop, Command::none())
+    }
+
+    fn title(&self) -> String {
+        format!("Random imgur wall - {}", self.url)
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::Poll => self.poll(),
+            Message::Start => {
+                self.bruteforcer
+                    .start(&self.connection, self.threads, PROBE_INTERVAL)
+            }
+            Message::Stop => self.bruteforcer.stop(&self.connection),
+            Message::ThreadsChanged(value) => {
+                if let Ok(threads) = value.parse::<usize>() {
+                    self.threads = threads.max(1);
+                }
+            }
+        }
+
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let controls = row![
+            text("Threads"),
+            text_input("threads", &self.threads.to_string())
+                .on_input(Message::ThreadsChanged)
+                .width(Length::Fixed(80.0)),
+            if self.bruteforcer.is_started() {
+                button("Stop").on_press(Message::Stop)
+            } else {
+                button("Start").on_press(Message::Start)
+            },
+        ]
+        .spacing(10);
+
+        let summary = self.bruteforcer.session.summary(now_ms());
+        let stats = column![
+            text(format!(
+                "{:?}, {} watching, {} bruteforcing",
+                self.state, self.counts.watching, self.counts.bruteforcing
+            )),
+            text(format!(
+                "{} requests ({:.0}/s), {} found, {} rejected, {} failed",
+                summary.attempts,
+                self.bruteforcer.requests_per_second(),
+                summary.finds,
+                self.rejected,
+                self.bruteforcer.failed
+            )),
+            text(self.announcement.clone().unwrap_or_default()),
+        ];
+
+        let images = self.images.iter().collect::<Vec<_>>();
+        let wall = images
+            .chunks(IMAGES_PER_ROW)
+            .fold(column![].spacing(5), |wall, chunk| {
+                wall.push(chunk.iter().fold(row![].spacing(5), |row, (id, handle)| {
+                    let tile: Element<Message> = match handle {
+                        Some(handle) => image(handle.clone()).width(Length::FillPortion(1)).into(),
+                        None => text(id).width(Length::FillPortion(1)).into(),
+                    };
+
+                    row.push(tile)
+                }))
+            });
+
+        column![controls, stats, scrollable(wall)]
+            .spacing(10)
+            .padding(10)
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        time::every(POLL_INTERVAL).map(|_| Message::Poll)
+    }
+}
+
+impl Desktop {
+    /// Takes in everything the connection, the probes and the downloads
+    /// sent since the last poll.
+    fn poll(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::State(state) => {
+                    if state == State::Connected {
+                        self.bruteforcer.resume(&self.connection);
+                    }
+
+                    self.state = state;
+                }
+                Event::New(id) => self.show_image(id),
+                Event::Counts(counts) => self.counts = counts,
+                Event::Ack(_) => {}
+                Event::Rejected(_, _) => self.rejected += 1,
+                Event::Announce(announcement) => self.announcement = Some(announcement),
+            }
+        }
+
+        self.bruteforcer.poll(&self.connection);
+
+        while let Ok((id, bytes)) = self.downloads.try_recv() {
+            if let Some(image) = self.images.iter_mut().find(|image| image.0 == id) {
+                image.1 = Some(image::Handle::from_memory(bytes));
+            }
+        }
+    }
+
+    fn show_image(&mut self, id: String) {
+        let downloads = self.download_sender.clone();
+        let download_id = id.clone();
+        thread::spawn(move || {
+            let bytes = reqwest::blocking::get(original_url(&download_id))
+                .and_then(|response| response.bytes());
+
+            if let Ok(bytes) = bytes {
+                let _ = downloads.send((download_id, bytes.to_vec()));
+            }
+        });
+
+        push_evicting(&mut self.images, (id, None), MAX_IMAGES, false);
+    }
+}
+
+fn main() -> iced::Result {
+    let url = env::args().nth(1).unwrap_or_else(|| DEFAULT_URL.to_owned());
+
+    Desktop::run(Settings::with_flags(url))
+}
@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::{Function, JSON};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, WebSocket};
+
+use protocol::{FindSource, WsMessage, WsMessageType};
+
+fn to_js_error(err: serde_json::Error) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+/// Parses the text of a websocket frame into a message object, failing on
+/// anything the server would not send.
+#[wasm_bindgen]
+pub fn decode(text: &str) -> Result<JsValue, JsValue> {
+    let message = serde_json::from_str::<WsMessage>(text).map_err(to_js_error)?;
+
+    JSON::parse(&serde_json::to_string(&message).map_err(to_js_error)?)
+}
+
+/// Turns a message object into the text of a websocket frame, failing on
+/// anything the server would refuse.
+#[wasm_bindgen]
+pub fn encode(message: &JsValue) -> Result<String, JsValue> {
+    let text = String::from(JSON::stringify(message)?);
+    let message = serde_json::from_str::<WsMessage>(&text).map_err(to_js_error)?;
+
+    serde_json::to_string(&message).map_err(to_js_error)
+}
+
+#[wasm_bindgen(js_name = normalizeId)]
+pub fn normalize_id(text: &str) -> Option<String> {
+    protocol::normalize_id(text)
+}
+
+#[wasm_bindgen(js_name = isValidId)]
+pub fn is_valid_id(id: &str) -> bool {
+    protocol::is_valid_id(id)
+}
+
+/// Like `protocol::solve_pow`, as a plain number since nonces stay far
+/// below 2^53.
+#[wasm_bindgen(js_name = solvePow)]
+pub fn solve_pow(challenge: &str, id: &str, difficulty: u32) -> f64 {
+    protocol::solve_pow(challenge, id, difficulty) as f64
+}
+
+/// The proof of work the server currently asks for.
+#[derive(Default)]
+struct Challenge {
+    text: String,
+    difficulty: u32,
+}
+
+/// A connection to a wall. Every message received is decoded and passed to
+/// `on_message`, challenges are also kept to sign submissions.
+#[wasm_bindgen]
+pub struct Client {
+    socket: WebSocket,
+    challenge: Rc<RefCell<Challenge>>,
+    next_client_msg_id: u64,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+#[wasm_bindgen]
+impl Client {
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str, on_message: Function) -> Result<Client, JsValue> {
+        let socket = WebSocket::new(url)?;
+        let challenge = Rc::new(RefCell::new(Challenge::default()));
+
+        let received = challenge.clone();
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let text = match event.data().as_string() {
+                Some(text) => text,
+                None => return,
+            };
+
+            let message = match serde_json::from_str::<WsMessage>(&text) {
+                Ok(message) => message,
+                Err(_) => return,
+            };
+
+            if let WsMessageType::Challenge = message.msg_type {
+                *received.borrow_mut() = Challenge {
+                    text: message.text.clone().unwrap_or_default(),
+                    difficulty: message.number.unwrap_or_default() as u32,
+                };
+            }
+
+            if let Ok(value) = decode(&text) {
+                let _ = on_message.call1(&JsValue::NULL, &value);
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Ok(Client {
+            socket,
+            challenge,
+            next_client_msg_id: 0,
+            _on_message: on_message,
+        })
+    }
+
+    /// Sends a message object, checked like [`encode`].
+    pub fn send(&self, message: &JsValue) -> Result<(), JsValue> {
+        self.socket.send_with_str(&encode(message)?)
+    }
+
+    /// Submits an image found elsewhere, solving the current challenge.
+    /// Returns the `client_msg_id` the server acknowledges it with.
+    pub fn submit(&mut self, id: &str) -> Result<f64, JsValue> {
+        let id =
+            protocol::normalize_id(id).ok_or_else(|| JsValue::from_str("not an imgur image ID"))?;
+        let challenge = self.challenge.borrow();
+
+        self.next_client_msg_id += 1;
+
+        let message = WsMessage {
+            number: Some(protocol::solve_pow(
+                &challenge.text,
+                &id,
+                challenge.difficulty,
+            )),
+            text: Some(id),
+            client_msg_id: Some(self.next_client_msg_id),
+            source: Some(FindSource::Extension),
+            ..WsMessage::new(WsMessageType::New)
+        };
+
+        self.socket
+            .send_with_str(&serde_json::to_string(&message).map_err(to_js_error)?)?;
+
+        Ok(self.next_client_msg_id as f64)
+    }
+
+    pub fn close(&self) -> Result<(), JsValue> {
+        self.socket.close()
+    }
+}
@@ -0,0 +1,49 @@
+// Generated by `cargo xtask schema`, do not edit.
+
+export type WsMessageType =
+  | "UsersBruteforcing"
+  | "UsersWatching"
+  | "Start"
+  | "Stop"
+  | "New"
+  | "Milestone"
+  | "Hello"
+  | "Register"
+  | "Registered"
+  | "Error"
+  | "Authenticated"
+  | "SyncSettings"
+  | "Settings"
+  | "Challenge"
+  | "Rejected"
+  | "Resync"
+  | "Ack"
+  | "ConfigUpdate"
+  | "BruteforceDisabled"
+  | "Rate"
+  | "Announce"
+  | "Counts"
+  | "Time"
+  | "Resume"
+  | "Regions"
+  | "Activity"
+  | "Throttled"
+  | "Federate"
+  | "Seen"
+;
+
+export interface WsMessage {
+  msg_type: WsMessageType;
+  text?: string | null;
+  number?: number | null;
+  url?: string | null;
+  reason?: string | null;
+  seq?: number | null;
+  client_msg_id?: number | null;
+  token?: string | null;
+  at?: number | null;
+  source?: "web" | "cli" | "desktop" | "extension" | "server-bot" | "archive-replay" | null;
+  origin?: string | null;
+  signature?: string | null;
+  labels?: string[] | null;
+}
@@ -0,0 +1,431 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const ID_MIN_LEN: usize = 5;
+pub const ID_MAX_LEN: usize = 7;
+/// How often bruteforcing clients send a `Rate` message.
+pub const RATE_REPORT_SECS: u64 = 10;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const SECS_PER_DAY: u32 = MINUTES_PER_DAY * 60;
+
+#[derive(Serialize, Deserialize)]
+pub enum WsMessageType {
+    /// Replaced by `Counts`, only sent to old clients with `LEGACY_COUNTS`.
+    UsersBruteforcing,
+    /// Replaced by `Counts`, only sent to old clients with `LEGACY_COUNTS`.
+    UsersWatching,
+    Start,
+    Stop,
+    New,
+    Milestone,
+    Hello,
+    Register,
+    Registered,
+    Error,
+    Authenticated,
+    SyncSettings,
+    Settings,
+    Challenge,
+    Rejected,
+    Resync,
+    Ack,
+    ConfigUpdate,
+    BruteforceDisabled,
+    Rate,
+    Announce,
+    Counts,
+    Time,
+    Resume,
+    Regions,
+    Activity,
+    /// Sent by bruteforcing clients after a `Rate` with how many of those
+    /// probes imgur answered with a 429, and by the server with how many
+    /// seconds every client should probe slower for, 0 once it is over.
+    Throttled,
+    /// Between federated servers: first with the shared `token` and the
+    /// name of the server connecting, then with each find it accepted.
+    Federate,
+    /// Between federated servers: the finds the sender accepted lately,
+    /// space-separated, so they are not pushed to it.
+    Seen,
+}
+
+impl WsMessageType {
+    /// Every message type, in declaration order, for the schema generator.
+    pub const ALL: [WsMessageType; 29] = [
+        WsMessageType::UsersBruteforcing,
+        WsMessageType::UsersWatching,
+        WsMessageType::Start,
+        WsMessageType::Stop,
+        WsMessageType::New,
+        WsMessageType::Milestone,
+        WsMessageType::Hello,
+        WsMessageType::Register,
+        WsMessageType::Registered,
+        WsMessageType::Error,
+        WsMessageType::Authenticated,
+        WsMessageType::SyncSettings,
+        WsMessageType::Settings,
+        WsMessageType::Challenge,
+        WsMessageType::Rejected,
+        WsMessageType::Resync,
+        WsMessageType::Ack,
+        WsMessageType::ConfigUpdate,
+        WsMessageType::BruteforceDisabled,
+        WsMessageType::Rate,
+        WsMessageType::Announce,
+        WsMessageType::Counts,
+        WsMessageType::Time,
+        WsMessageType::Resume,
+        WsMessageType::Regions,
+        WsMessageType::Activity,
+        WsMessageType::Throttled,
+        WsMessageType::Federate,
+        WsMessageType::Seen,
+    ];
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WsMessage {
+    pub msg_type: WsMessageType,
+    pub text: Option<String>,
+    pub number: Option<u64>,
+    pub url: Option<String>,
+    pub reason: Option<String>,
+    pub seq: Option<u64>,
+    pub client_msg_id: Option<u64>,
+    /// Admin token authorizing a privileged message, like `Announce`.
+    pub token: Option<String>,
+    /// A server timestamp in milliseconds since the unix epoch: when it
+    /// received a `New` find, or its clock when it answered a `Time`.
+    pub at: Option<u64>,
+    /// What submitted a `New` find, as the client declared it.
+    pub source: Option<FindSource>,
+    /// The federated server a `New` find was found on, if not this one.
+    pub origin: Option<String>,
+    /// Base64 ed25519 signature of a message between federated servers.
+    pub signature: Option<String>,
+    /// Notes the server's content policies attached to a `New` find.
+    pub labels: Option<Vec<String>>,
+}
+
+impl WsMessage {
+    pub fn new(msg_type: WsMessageType) -> Self {
+        WsMessage {
+            msg_type,
+            text: None,
+            number: None,
+            url: None,
+            reason: None,
+            seq: None,
+            client_msg_id: None,
+            token: None,
+            at: None,
+            source: None,
+            origin: None,
+            signature: None,
+            labels: None,
+        }
+    }
+}
+
+/// Kinds of clients finds come from, shown as badges on the wall.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FindSource {
+    Web,
+    Cli,
+    Desktop,
+    /// Browser extensions and other scripts built on `protocol-js`.
+    Extension,
+    /// Programs submitting on their own, like `bench`.
+    ServerBot,
+    /// Finds of a recording replayed by the server, or of the archive.
+    ArchiveReplay,
+}
+
+impl FindSource {
+    pub const ALL: [FindSource; 6] = [
+        FindSource::Web,
+        FindSource::Cli,
+        FindSource::Desktop,
+        FindSource::Extension,
+        FindSource::ServerBot,
+        FindSource::ArchiveReplay,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FindSource::Web => "web",
+            FindSource::Cli => "cli",
+            FindSource::Desktop => "desktop",
+            FindSource::Extension => "extension",
+            FindSource::ServerBot => "bot",
+            FindSource::ArchiveReplay => "replay",
+        }
+    }
+}
+
+/// Client-facing parameters the operator can change while the server runs,
+/// sent as the JSON text of a `ConfigUpdate`.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub min_interval_ms: u64,
+    pub min_rate_limit: u64,
+    pub bruteforce_disabled: bool,
+    pub bruteforce_disabled_reason: Option<String>,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// How many users are connected and how many of them are bruteforcing, sent
+/// as the JSON text of a `Counts`.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Counts {
+    pub watching: u64,
+    pub bruteforcing: u64,
+}
+
+/// Users watching by continent code, with `other` for those elsewhere or in
+/// regions too small to show, sent as the JSON text of a `Regions`.
+pub type RegionCounts = BTreeMap<String, u64>;
+
+/// A daily window during which bruteforcing pauses, in minutes since
+/// midnight. It wraps around midnight when `end` comes before `start`.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl QuietHours {
+    pub fn is_valid(&self) -> bool {
+        self.start < MINUTES_PER_DAY && self.end < MINUTES_PER_DAY
+    }
+
+    pub fn contains(&self, secs_of_day: u32) -> bool {
+        let minute = secs_of_day / 60;
+
+        if self.start <= self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    /// Seconds from `secs_of_day` until the window next starts or ends.
+    pub fn secs_until_change(&self, secs_of_day: u32) -> u32 {
+        let change = if self.contains(secs_of_day) {
+            self.end
+        } else {
+            self.start
+        } * 60;
+
+        (change + SECS_PER_DAY - secs_of_day % SECS_PER_DAY) % SECS_PER_DAY
+    }
+}
+
+/// Checks that `name` is usable as a contributor name, `[A-Za-z0-9_-]{3,20}`.
+pub fn is_valid_name(name: &str) -> bool {
+    name.len() >= 3
+        && name.len() <= 20
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// Checks that `id` is a bare imgur image ID, `[A-Za-z0-9]{5,7}`.
+pub fn is_valid_id(id: &str) -> bool {
+    id.len() >= ID_MIN_LEN
+        && id.len() <= ID_MAX_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+fn bare_id(text: &str) -> &str {
+    let text = text.trim();
+    // The query and fragment, then the host and path of a link, then the
+    // extension.
+    let text = &text[..text.find(['?', '#']).unwrap_or(text.len())];
+    let text = &text[text.rfind('/').map_or(0, |slash| slash + 1)..];
+
+    &text[..text.find('.').unwrap_or(text.len())]
+}
+
+/// Turns whatever a peer sent (`abc1234`, `abc1234.png`, ` abc1234.jpg?1 `,
+/// `https://i.imgur.com/abc1234.png`) into a bare image ID, or `None` if it
+/// does not look like one.
+pub fn normalize_id(text: &str) -> Option<String> {
+    let id = bare_id(text);
+
+    if is_valid_id(id) {
+        Some(id.to_owned())
+    } else {
+        None
+    }
+}
+
+/// A valid image ID held inline, zero padded, instead of in a heap
+/// allocated `String`. Serialized as a plain string.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ImageId([u8; 8]);
+
+impl ImageId {
+    pub fn new(id: &str) -> Option<ImageId> {
+        if !is_valid_id(id) {
+            return None;
+        }
+
+        let mut bytes = [0; 8];
+        bytes[..id.len()].copy_from_slice(id.as_bytes());
+
+        Some(ImageId(bytes))
+    }
+
+    /// Like [`normalize_id`].
+    pub fn normalize(text: &str) -> Option<ImageId> {
+        ImageId::new(bare_id(text))
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(self.0.len());
+
+        std::str::from_utf8(&self.0[..len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ImageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for ImageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ImageId({:?})", self.as_str())
+    }
+}
+
+impl Serialize for ImageId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ImageId, D::Error> {
+        let id = String::deserialize(deserializer)?;
+
+        ImageId::new(&id).ok_or_else(|| D::Error::custom(format!("invalid image ID {:?}", id)))
+    }
+}
+
+fn pow_hash(challenge: &str, id: &str, nonce: u64) -> Vec<u8> {
+    Sha256::digest(format!("{}:{}:{}", challenge, id, nonce).as_bytes()).to_vec()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+
+    bits
+}
+
+/// Checks a proof of work attached to a `New` submission: the SHA-256 of
+/// `{challenge}:{id}:{nonce}` must start with `difficulty` zero bits.
+pub fn is_valid_pow(challenge: &str, id: &str, nonce: u64, difficulty: u32) -> bool {
+    leading_zero_bits(&pow_hash(challenge, id, nonce)) >= difficulty
+}
+
+/// Finds the first nonce satisfying [`is_valid_pow`].
+pub fn solve_pow(challenge: &str, id: &str, difficulty: u32) -> u64 {
+    (0..)
+        .find(|&nonce| is_valid_pow(challenge, id, nonce, difficulty))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_five_to_seven_ascii_alphanumerics() {
+        assert!(is_valid_id("abc12"));
+        assert!(is_valid_id("aBc1234"));
+        assert!(!is_valid_id(""));
+        assert!(!is_valid_id("abc1"));
+        assert!(!is_valid_id("abc12345"));
+        assert!(!is_valid_id("abc-123"));
+        assert!(!is_valid_id("abc123\u{e9}"));
+        // Six bytes, but not six ASCII characters.
+        assert!(!is_valid_id("ab\u{e9}cd"));
+        assert!(!is_valid_id("\u{661}\u{662}\u{663}\u{664}\u{665}"));
+    }
+
+    #[test]
+    fn extensions_queries_and_fragments_are_stripped() {
+        assert_eq!(normalize_id("abc1234"), Some("abc1234".to_owned()));
+        assert_eq!(normalize_id(" abc1234.png "), Some("abc1234".to_owned()));
+        assert_eq!(normalize_id("abc1234.jpg?1"), Some("abc1234".to_owned()));
+        assert_eq!(
+            normalize_id("abc1234?maxwidth=100"),
+            Some("abc1234".to_owned())
+        );
+        assert_eq!(normalize_id("abc1234#top"), Some("abc1234".to_owned()));
+        assert_eq!(normalize_id("abc1234?a.b#c"), Some("abc1234".to_owned()));
+    }
+
+    #[test]
+    fn anything_else_is_not_an_id() {
+        assert_eq!(normalize_id(""), None);
+        assert_eq!(normalize_id("   "), None);
+        assert_eq!(normalize_id(".png"), None);
+        assert_eq!(normalize_id("?abc1234"), None);
+        assert_eq!(normalize_id("abc12345678.png"), None);
+        assert_eq!(normalize_id("\u{e9}\u{e9}\u{e9}.png"), None);
+        assert_eq!(normalize_id("abc\u{e9}1.png"), None);
+        assert_eq!(normalize_id("https://imgur.com/"), None);
+        assert_eq!(normalize_id("https://imgur.com/gallery/"), None);
+    }
+
+    #[test]
+    fn links_give_the_id_they_point_to() {
+        for link in &[
+            "https://i.imgur.com/abc1234.png",
+            "http://i.imgur.com/abc1234.jpg?maxwidth=100#x",
+            "https://imgur.com/abc1234",
+            "imgur.com/abc1234",
+            "i.imgur.com/abc1234.png?a/b",
+        ] {
+            assert_eq!(normalize_id(link), Some("abc1234".to_owned()), "{}", link);
+        }
+    }
+
+    #[test]
+    fn image_ids_parse_like_normalize_id() {
+        for text in &[
+            "abc1234",
+            "abc12.png?x#y",
+            "",
+            "abc12345",
+            "https://imgur.com/abc1234",
+        ] {
+            assert_eq!(
+                ImageId::normalize(text).map(|id| id.to_string()),
+                normalize_id(text)
+            );
+        }
+    }
+}
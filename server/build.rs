@@ -0,0 +1,53 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Embeds the files of `WEB_ASSETS_DIR`, when set, so the server can serve
+/// the frontend itself.
+fn main() {
+    println!("cargo:rerun-if-env-changed=WEB_ASSETS_DIR");
+
+    let mut assets = Vec::new();
+
+    if let Ok(dir) = env::var("WEB_ASSETS_DIR") {
+        let dir = PathBuf::from(dir);
+        println!("cargo:rerun-if-changed={}", dir.display());
+        collect(&dir, &dir, &mut assets);
+    }
+
+    let entries = assets
+        .iter()
+        .map(|(path, file)| format!("    ({:?}, include_bytes!({:?})),\n", path, file))
+        .collect::<String>();
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("assets.rs");
+    fs::write(
+        out,
+        format!("pub static ASSETS: &[(&str, &[u8])] = &[\n{}];\n", entries),
+    )
+    .unwrap();
+}
+
+fn collect(root: &Path, dir: &Path, assets: &mut Vec<(String, String)>) {
+    let entries =
+        fs::read_dir(dir).unwrap_or_else(|err| panic!("could not read {}: {}", dir.display(), err));
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect(root, &path, assets);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let url_path = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            assets.push((
+                format!("/{}", url_path),
+                path.canonicalize().unwrap().display().to_string(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Public,
+    Contributor,
+    Admin,
+}
+
+/// Who may read what, as read from `ACCESS_FILE`. Logged in users are
+/// contributors, API keys carry their own role.
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessPolicy {
+    pub api_keys: HashMap<String, Role>,
+    /// `/api/archive` and `/api/images/random`.
+    pub archive: Role,
+    /// `/api/stats/export` and `/stats`.
+    pub stats: Role,
+    /// Replays of more than `public_history` broadcasts.
+    pub history: Role,
+    pub public_history: usize,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        AccessPolicy {
+            api_keys: HashMap::new(),
+            archive: Role::Public,
+            stats: Role::Public,
+            history: Role::Public,
+            public_history: 100,
+        }
+    }
+}
+
+impl AccessPolicy {
+    pub fn load(path: &str) -> Result<AccessPolicy, String> {
+        let data = fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+        serde_json::from_str(&data).map_err(|err| err.to_string())
+    }
+
+    pub fn key_role(&self, key: &str) -> Option<Role> {
+        self.api_keys.get(key).copied()
+    }
+
+    /// The lowest role allowed to request `path`.
+    pub fn required_role(&self, path: &str) -> Role {
+        match path {
+            "/api/archive" | "/api/images/random" => self.archive,
+            "/api/stats/export" | "/stats" => self.stats,
+            _ if path.starts_with("/api/admin/") => Role::Admin,
+            _ => Role::Public,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use crate::store::{self, StoreError};
+
+const SETTINGS_MAX_LEN: usize = 4096;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Account {
+    pub settings: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Accounts {
+    by_key: HashMap<String, Account>,
+}
+
+impl Accounts {
+    pub fn load(path: &str) -> Result<Accounts, StoreError> {
+        store::load(path)
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.by_key.remove(key).is_some()
+    }
+
+    pub fn settings(&self, key: &str) -> Option<&String> {
+        self.by_key
+            .get(key)
+            .and_then(|account| account.settings.as_ref())
+    }
+
+    /// Stores the opaque settings blob synced by the frontend.
+    pub fn set_settings(&mut self, key: &str, settings: String) -> Result<(), &'static str> {
+        if settings.len() > SETTINGS_MAX_LEN {
+            return Err("settings are too large");
+        }
+
+        if serde_json::from_str::<serde_json::Value>(&settings).is_err() {
+            return Err("settings must be JSON");
+        }
+
+        self.by_key.entry(key.to_owned()).or_default().settings = Some(settings);
+
+        Ok(())
+    }
+}
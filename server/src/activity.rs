@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How often the ticker gets a new item at most, so it stays readable.
+pub const ACTIVITY_INTERVAL: Duration = Duration::from_secs(4);
+/// How often the number of users online is mentioned at most.
+const USERS_INTERVAL: Duration = Duration::from_secs(60);
+/// Items waiting beyond this are dropped, oldest first, to stay current.
+const MAX_QUEUED: usize = 10;
+
+/// Community events waiting to be shown in the clients' ticker.
+#[derive(Default)]
+pub struct Activity {
+    /// Finds since the last item by contributor name, `None` for anonymous
+    /// ones, in the order they first found something.
+    finds: Vec<(Option<String>, u64)>,
+    queue: VecDeque<String>,
+    users_mentioned: Option<(Instant, u64)>,
+}
+
+impl Activity {
+    pub fn record_find(&mut self, contributor: Option<String>) {
+        match self.finds.iter_mut().find(|(name, _)| *name == contributor) {
+            Some((_, finds)) => *finds += 1,
+            None => self.finds.push((contributor, 1)),
+        }
+    }
+
+    pub fn record_milestone(&mut self, milestone: &str) {
+        self.push(format!("Milestone: {}", milestone));
+    }
+
+    pub fn record_users(&mut self, watching: u64, now: Instant) {
+        let is_due = self.users_mentioned.map_or(true, |(at, mentioned)| {
+            mentioned != watching && now.duration_since(at) >= USERS_INTERVAL
+        });
+
+        if is_due {
+            self.users_mentioned = Some((now, watching));
+            self.push(format!(
+                "{} {} online",
+                watching,
+                if watching == 1 { "user" } else { "users" }
+            ));
+        }
+    }
+
+    /// The next item to send, one per `ACTIVITY_INTERVAL`.
+    pub fn next(&mut self) -> Option<String> {
+        for (contributor, finds) in std::mem::take(&mut self.finds) {
+            self.push(format!(
+                "{} found {} {}",
+                contributor.as_deref().unwrap_or("Someone"),
+                finds,
+                if finds == 1 { "image" } else { "images" }
+            ));
+        }
+
+        self.queue.pop_front()
+    }
+
+    fn push(&mut self, item: String) {
+        if self.queue.len() == MAX_QUEUED {
+            self.queue.pop_front();
+        }
+
+        self.queue.push_back(item);
+    }
+}
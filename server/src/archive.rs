@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+
+use protocol::ImageId;
+use rand::{thread_rng, Rng};
+
+use crate::dates;
+use crate::store::StoreError;
+
+/// Days kept without `RETENTION_DAYS`.
+const DEFAULT_RETENTION_DAYS: usize = 30;
+const MAX_FINDS_PER_DAY: usize = 50_000;
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedFind {
+    pub at: u64,
+    pub id: ImageId,
+}
+
+/// Every find of the last days with its timestamp, kept as JSON lines so
+/// recording one is a cheap append.
+pub struct Archive {
+    by_date: BTreeMap<String, Vec<ArchivedFind>>,
+    retention_days: usize,
+}
+
+impl Default for Archive {
+    /// Keeping as many days as the other stores.
+    fn default() -> Archive {
+        Archive {
+            by_date: BTreeMap::new(),
+            retention_days: crate::retention_days()
+                .map_or(DEFAULT_RETENTION_DAYS, |days| days as usize),
+        }
+    }
+}
+
+impl Archive {
+    /// Loads the finds at `path`, skipping lines cut short by a crash.
+    pub fn load(path: &str) -> Result<Archive, StoreError> {
+        let mut archive = Archive::default();
+
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(archive),
+            Err(source) => {
+                return Err(StoreError::Read {
+                    path: path.to_owned(),
+                    source,
+                })
+            }
+        };
+
+        for find in data
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ArchivedFind>(line).ok())
+        {
+            archive.insert(find);
+        }
+
+        archive.compact(path);
+
+        Ok(archive)
+    }
+
+    /// Rewrites the file without the days that fell out of retention.
+    fn compact(&self, path: &str) {
+        if let Err(err) = fs::write(path, self.to_jsonl()) {
+            eprintln!("could not compact archive {}: {}", path, err);
+        }
+    }
+
+    /// The content of the file, one find per line.
+    pub fn to_jsonl(&self) -> String {
+        self.by_date
+            .values()
+            .flatten()
+            .filter_map(|find| serde_json::to_string(find).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    fn insert(&mut self, find: ArchivedFind) -> bool {
+        let finds = self.by_date.entry(dates::date(find.at)).or_default();
+
+        if finds.len() >= MAX_FINDS_PER_DAY {
+            return false;
+        }
+
+        finds.push(find);
+
+        while self.by_date.len() > self.retention_days {
+            let oldest = self.by_date.keys().next().cloned().unwrap_or_default();
+            self.by_date.remove(&oldest);
+        }
+
+        true
+    }
+
+    /// Records a find, returning the line to append to the file if it was
+    /// kept.
+    pub fn record(&mut self, at: u64, id: ImageId) -> Option<String> {
+        let find = ArchivedFind { at, id };
+        let line = serde_json::to_string(&find).ok()?;
+
+        if self.insert(find) {
+            Some(line)
+        } else {
+            None
+        }
+    }
+
+    /// Drops the days before `date`, returning whether any was dropped.
+    pub fn purge_before(&mut self, date: &str) -> bool {
+        let days = self.by_date.len();
+        self.by_date = self.by_date.split_off(date);
+
+        self.by_date.len() != days
+    }
+
+    /// The finds of `date` in the order they were made.
+    pub fn day(&self, date: &str) -> &[ArchivedFind] {
+        self.by_date.get(date).map_or(&[], |finds| finds.as_slice())
+    }
+
+    /// Picks up to `n` distinct finds at random, every day getting the same
+    /// chance so that busy days do not crowd out the quieter ones.
+    pub fn sample(&self, n: usize) -> Vec<&ArchivedFind> {
+        let days = self
+            .by_date
+            .values()
+            .filter(|finds| !finds.is_empty())
+            .collect::<Vec<_>>();
+        let n = n.min(days.iter().map(|finds| finds.len()).sum());
+
+        let mut rng = thread_rng();
+        let mut picked = HashSet::new();
+        let mut sample = Vec::with_capacity(n);
+
+        while sample.len() < n {
+            let day = rng.gen_range(0, days.len());
+            let index = rng.gen_range(0, days[day].len());
+
+            if picked.insert((day, index)) {
+                sample.push(&days[day][index]);
+            }
+        }
+
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_past_retention_are_dropped() {
+        let mut archive = Archive {
+            by_date: BTreeMap::new(),
+            retention_days: 2,
+        };
+        let id = ImageId::new("abcde").unwrap();
+
+        for day in 0..3 {
+            assert!(archive.record(day * 86_400, id).is_some());
+        }
+
+        assert!(archive.day(&dates::date(0)).is_empty());
+        assert_eq!(archive.day(&dates::date(86_400)).len(), 1);
+        assert_eq!(archive.day(&dates::date(2 * 86_400)).len(), 1);
+    }
+}
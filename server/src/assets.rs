@@ -0,0 +1,21 @@
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// The embedded frontend file served at `path`, with its content type.
+pub fn get(path: &str) -> Option<(&'static [u8], &'static str)> {
+    let path = if path == "/" { "/index.html" } else { path };
+    let (_, contents) = ASSETS.iter().find(|(asset, _)| *asset == path)?;
+
+    let content_type = match path.rsplit('.').next() {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("wasm") => "application/wasm",
+        Some("json") => "application/json",
+        Some("css") => "text/css",
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    };
+
+    Some((contents, content_type))
+}
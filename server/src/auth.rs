@@ -0,0 +1,360 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::Sha256;
+use url::{form_urlencoded, Url};
+use ws::Response;
+
+const SESSION_LIFETIME_SECS: u64 = 30 * 24 * 3600;
+const STATE_LIFETIME_SECS: u64 = 600;
+const ADMIN_TOKEN_LIFETIME_SECS: u64 = 900;
+
+pub struct OAuthProvider {
+    pub name: &'static str,
+    authorize_url: &'static str,
+    token_url: &'static str,
+    user_url: &'static str,
+    scope: &'static str,
+    client_id: String,
+    client_secret: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub sub: String,
+    pub name: String,
+    pub exp: u64,
+}
+
+/// Claims of the short-lived token an admin attaches to privileged
+/// websocket messages.
+#[derive(Serialize, Deserialize)]
+pub struct AdminClaims {
+    pub role: String,
+    pub exp: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LoginState {
+    provider: String,
+    return_to: String,
+    exp: u64,
+}
+
+pub struct Auth {
+    secret: Vec<u8>,
+    public_url: String,
+    web_url: String,
+    providers: Vec<OAuthProvider>,
+}
+
+impl Auth {
+    /// Reads `AUTH_SECRET`, `PUBLIC_URL`, `WEB_URL` and the
+    /// `{GITHUB,GOOGLE}_CLIENT_{ID,SECRET}` variables. Login stays disabled
+    /// unless a secret and a provider are set.
+    pub fn from_env() -> Option<Auth> {
+        let secret = env::var("AUTH_SECRET").ok()?;
+        let public_url = env::var("PUBLIC_URL").ok()?;
+        let web_url = env::var("WEB_URL").unwrap_or_else(|_| public_url.clone());
+
+        let mut providers = Vec::new();
+
+        if let (Ok(client_id), Ok(client_secret)) = (
+            env::var("GITHUB_CLIENT_ID"),
+            env::var("GITHUB_CLIENT_SECRET"),
+        ) {
+            providers.push(OAuthProvider {
+                name: "github",
+                authorize_url: "https://github.com/login/oauth/authorize",
+                token_url: "https://github.com/login/oauth/access_token",
+                user_url: "https://api.github.com/user",
+                scope: "read:user",
+                client_id,
+                client_secret,
+            });
+        }
+
+        if let (Ok(client_id), Ok(client_secret)) = (
+            env::var("GOOGLE_CLIENT_ID"),
+            env::var("GOOGLE_CLIENT_SECRET"),
+        ) {
+            providers.push(OAuthProvider {
+                name: "google",
+                authorize_url: "https://accounts.google.com/o/oauth2/v2/auth",
+                token_url: "https://oauth2.googleapis.com/token",
+                user_url: "https://openidconnect.googleapis.com/v1/userinfo",
+                scope: "openid profile",
+                client_id,
+                client_secret,
+            });
+        }
+
+        if providers.is_empty() {
+            None
+        } else {
+            Some(Auth {
+                secret: secret.into_bytes(),
+                public_url: public_url.trim_end_matches('/').to_owned(),
+                web_url,
+                providers,
+            })
+        }
+    }
+
+    pub fn provider_names(&self) -> Vec<&'static str> {
+        self.providers
+            .iter()
+            .map(|provider| provider.name)
+            .collect()
+    }
+
+    fn provider(&self, name: &str) -> Option<&OAuthProvider> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+
+    fn redirect_uri(&self, provider: &OAuthProvider) -> String {
+        format!("{}/auth/{}/callback", self.public_url, provider.name)
+    }
+
+    /// Handles `/auth/{provider}/login` and `/auth/{provider}/callback`.
+    pub fn handle(&self, path: &str, query: &str) -> Option<Response> {
+        let mut parts = path.trim_start_matches("/auth/").splitn(2, '/');
+        let provider = self.provider(parts.next()?)?;
+        let params = form_urlencoded::parse(query.as_bytes()).collect::<Vec<_>>();
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        };
+
+        match parts.next()? {
+            "login" => {
+                let state = self.sign(&LoginState {
+                    provider: provider.name.to_owned(),
+                    return_to: param("return_to")
+                        .filter(|return_to| is_within(return_to, &self.web_url))
+                        .unwrap_or_else(|| self.web_url.clone()),
+                    exp: now() + STATE_LIFETIME_SECS,
+                });
+
+                let location = format!(
+                    "{}?{}",
+                    provider.authorize_url,
+                    form_urlencoded::Serializer::new(String::new())
+                        .append_pair("client_id", &provider.client_id)
+                        .append_pair("redirect_uri", &self.redirect_uri(provider))
+                        .append_pair("response_type", "code")
+                        .append_pair("scope", provider.scope)
+                        .append_pair("state", &state)
+                        .finish()
+                );
+
+                Some(redirect(&location))
+            }
+            "callback" => {
+                let state = param("state")
+                    .and_then(|state| self.verify::<LoginState>(&state))
+                    .filter(|state| state.provider == provider.name);
+
+                let state = match state {
+                    Some(state) => state,
+                    None => return Some(bad_request("invalid or expired login state")),
+                };
+
+                let identity = match param("code").map(|code| self.exchange(provider, &code)) {
+                    Some(Ok(identity)) => identity,
+                    Some(Err(err)) => {
+                        eprintln!("{} login failed: {}", provider.name, err);
+                        return Some(bad_request("login failed"));
+                    }
+                    None => return Some(bad_request("missing code")),
+                };
+
+                Some(redirect(&format!(
+                    "{}#session={}",
+                    state.return_to,
+                    self.sign(&identity)
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    fn exchange(&self, provider: &OAuthProvider, code: &str) -> Result<Identity, String> {
+        let redirect_uri = self.redirect_uri(provider);
+        let response = ureq::post(provider.token_url)
+            .set("Accept", "application/json")
+            .send_form(&[
+                ("client_id", &provider.client_id),
+                ("client_secret", &provider.client_secret),
+                ("code", code),
+                ("grant_type", "authorization_code"),
+                ("redirect_uri", &redirect_uri),
+            ]);
+
+        if !response.ok() {
+            return Err(format!("token endpoint returned {}", response.status()));
+        }
+
+        let token = response.into_json().map_err(|err| err.to_string())?;
+        let access_token = token["access_token"]
+            .as_str()
+            .ok_or("no access token in response")?;
+
+        let response = ureq::get(provider.user_url)
+            .set("Accept", "application/json")
+            .set("Authorization", &format!("Bearer {}", access_token))
+            .call();
+
+        if !response.ok() {
+            return Err(format!("user endpoint returned {}", response.status()));
+        }
+
+        let user = response.into_json().map_err(|err| err.to_string())?;
+        let (id, name) = match provider.name {
+            "github" => (user["id"].to_string(), user["login"].as_str()),
+            _ => (
+                user["sub"].as_str().unwrap_or_default().to_owned(),
+                user["name"].as_str(),
+            ),
+        };
+
+        if id.is_empty() || id == "null" {
+            return Err("no user ID in response".to_owned());
+        }
+
+        Ok(Identity {
+            sub: format!("{}:{}", provider.name, id),
+            name: name.unwrap_or(provider.name).to_owned(),
+            exp: now() + SESSION_LIFETIME_SECS,
+        })
+    }
+
+    /// Checks a session token presented by a client at handshake.
+    pub fn verify_session(&self, token: &str) -> Option<Identity> {
+        self.verify(token)
+    }
+
+    fn sign<T: Serialize>(&self, payload: &T) -> String {
+        sign(&self.secret, payload)
+    }
+
+    fn verify<T: DeserializeOwned>(&self, token: &str) -> Option<T> {
+        verify(&self.secret, token)
+    }
+}
+
+/// Issues a short-lived admin token, signed with the admin secret itself.
+pub fn sign_admin_token(admin_secret: &str) -> (String, u64) {
+    let claims = AdminClaims {
+        role: "admin".to_owned(),
+        exp: now() + ADMIN_TOKEN_LIFETIME_SECS,
+    };
+
+    (sign(admin_secret.as_bytes(), &claims), claims.exp)
+}
+
+pub fn verify_admin_token(admin_secret: &str, token: &str) -> bool {
+    verify::<AdminClaims>(admin_secret.as_bytes(), token)
+        .map_or(false, |claims| claims.role == "admin")
+}
+
+fn sign<T: Serialize>(secret: &[u8], payload: &T) -> String {
+    let payload = base64::encode_config(
+        &serde_json::to_vec(payload).unwrap_or_default(),
+        base64::URL_SAFE_NO_PAD,
+    );
+
+    let mut mac = Hmac::<Sha256>::new_varkey(secret).expect("HMAC accepts any key size");
+    mac.input(payload.as_bytes());
+
+    format!(
+        "{}.{}",
+        payload,
+        base64::encode_config(&mac.result().code(), base64::URL_SAFE_NO_PAD)
+    )
+}
+
+fn verify<T: DeserializeOwned>(secret: &[u8], token: &str) -> Option<T> {
+    let mut parts = token.splitn(2, '.');
+    let payload = parts.next()?;
+    let signature = base64::decode_config(parts.next()?, base64::URL_SAFE_NO_PAD).ok()?;
+
+    let mut mac = Hmac::<Sha256>::new_varkey(secret).ok()?;
+    mac.input(payload.as_bytes());
+    mac.verify(&signature).ok()?;
+
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let value = serde_json::from_slice::<serde_json::Value>(&payload).ok()?;
+
+    if value["exp"].as_u64().map_or(true, |exp| exp <= now()) {
+        return None;
+    }
+
+    serde_json::from_value(value).ok()
+}
+
+/// Whether `url` is on the same origin as `base` and under its path, so
+/// a session token redirected there stays with the web app.
+fn is_within(url: &str, base: &str) -> bool {
+    match (Url::parse(url), Url::parse(base)) {
+        (Ok(url), Ok(base)) => {
+            url.scheme() == base.scheme()
+                && url.host_str() == base.host_str()
+                && url.port_or_known_default() == base.port_or_known_default()
+                && url.path().starts_with(base.path())
+        }
+        _ => false,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn redirect(location: &str) -> Response {
+    let mut response = Response::new(302, "Found", Vec::new());
+    response
+        .headers_mut()
+        .push(("Location".into(), location.as_bytes().to_vec()));
+    response
+}
+
+fn bad_request(reason: &str) -> Response {
+    Response::new(400, "Bad Request", format!("400 - {}", reason).into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_within;
+
+    #[test]
+    fn return_to_must_be_on_the_web_url_origin() {
+        let web_url = "https://wall.example.com/";
+
+        assert!(is_within("https://wall.example.com/", web_url));
+        assert!(is_within("https://wall.example.com/?feed=own#x", web_url));
+        assert!(is_within("https://wall.example.com:443/", web_url));
+        assert!(!is_within("https://wall.example.com.evil.com/", web_url));
+        assert!(!is_within("https://wall.example.com@evil.com/", web_url));
+        assert!(!is_within("http://wall.example.com/", web_url));
+        assert!(!is_within("https://wall.example.com:8443/", web_url));
+        assert!(!is_within("//evil.com/", web_url));
+        assert!(!is_within("not a url", web_url));
+    }
+
+    #[test]
+    fn return_to_must_be_under_the_web_url_path() {
+        let web_url = "https://example.com/wall/";
+
+        assert!(is_within("https://example.com/wall/?x=1", web_url));
+        assert!(!is_within("https://example.com/other/", web_url));
+    }
+}
@@ -0,0 +1,56 @@
+use std::env;
+use std::time::Duration;
+
+const CLASSIFY_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_NSFW_THRESHOLD: f64 = 0.8;
+
+/// An NSFW classifier run by the operator, asked about every find with
+/// `GET {url}?url={image URL}` and answering `{"score": 0.0 to 1.0}`.
+pub struct Classifier {
+    url: String,
+    threshold: f64,
+}
+
+impl Classifier {
+    pub fn from_env() -> Option<Classifier> {
+        Some(Classifier {
+            url: env::var("NSFW_CLASSIFIER_URL")
+                .ok()
+                .filter(|url| !url.is_empty())?,
+            threshold: env::var("NSFW_THRESHOLD")
+                .ok()
+                .and_then(|threshold| threshold.parse().ok())
+                .unwrap_or(DEFAULT_NSFW_THRESHOLD),
+        })
+    }
+
+    /// Why the image should be reviewed before being shown, if it scores at
+    /// least the threshold.
+    pub fn check(&self, id: &str) -> Result<Option<String>, String> {
+        let response = ureq::get(&self.url)
+            .query("url", &format!("https://i.imgur.com/{}.jpg", id))
+            .timeout(CLASSIFY_TIMEOUT)
+            .call();
+
+        if let Some(err) = response.synthetic_error() {
+            return Err(err.to_string());
+        }
+
+        if !response.ok() {
+            return Err(format!("classifier answered {}", response.status()));
+        }
+
+        let body = response
+            .into_json()
+            .map_err(|err| format!("classifier answered invalid JSON: {}", err))?;
+        let score = body["score"]
+            .as_f64()
+            .ok_or("classifier answered without a score")?;
+
+        Ok(if score >= self.threshold {
+            Some(format!("NSFW score {:.2}", score))
+        } else {
+            None
+        })
+    }
+}
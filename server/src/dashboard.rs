@@ -0,0 +1,390 @@
+use crate::dates;
+use crate::timeline::Timeline;
+
+const DAYS: u64 = 8 * 7;
+const BAR_WIDTH: u64 = 12;
+const CHART_HEIGHT: f64 = 120.0;
+
+/// Draws one bar per day, scaled to the highest value, each with a tooltip
+/// giving its date and value.
+fn bar_chart(bars: &[(String, f64, String)]) -> String {
+    let max = bars.iter().map(|(_, value, _)| *value).fold(0.0, f64::max);
+
+    let rects = bars
+        .iter()
+        .enumerate()
+        .map(|(index, (date, value, label))| {
+            let height = if max > 0.0 {
+                value / max * CHART_HEIGHT
+            } else {
+                0.0
+            };
+
+            format!(
+                r#"<rect x="{x}" y="{y:.1}" width="{width}" height="{height:.1}"><title>{date}: {label}</title></rect>"#,
+                x = index as u64 * BAR_WIDTH,
+                y = CHART_HEIGHT - height,
+                width = BAR_WIDTH - 2,
+                height = height,
+                date = date,
+                label = label
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<svg viewBox="0 0 {width} {height}" preserveAspectRatio="none">{rects}</svg>"#,
+        width = bars.len() as u64 * BAR_WIDTH,
+        height = CHART_HEIGHT,
+        rects = rects
+    )
+}
+
+pub fn render_stats(timeline: &Timeline) -> String {
+    let daily = timeline.daily(dates::now_secs(), DAYS);
+
+    let finds = daily
+        .iter()
+        .map(|day| {
+            (
+                dates::date(day.start),
+                day.finds as f64,
+                format!("{} finds", day.finds),
+            )
+        })
+        .collect::<Vec<_>>();
+    let users = daily
+        .iter()
+        .map(|day| {
+            (
+                dates::date(day.start),
+                day.peak_users as f64,
+                format!("{} users at most", day.peak_users),
+            )
+        })
+        .collect::<Vec<_>>();
+    let hit_rate = daily
+        .iter()
+        .map(|day| {
+            let rate = if day.requests > 0 {
+                day.finds as f64 / day.requests as f64 * 100.0
+            } else {
+                0.0
+            };
+
+            (
+                dates::date(day.start),
+                rate,
+                format!("{:.3}% of {} requests", rate, day.requests),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8" />
+  <meta content="width=device-width, initial-scale=1.0" name="viewport" />
+  <title>Statistics - Random Imgur Wall</title>
+  <style>
+    body {{ font-family: sans-serif; background-color: #e2e2e2; color: #333333; margin: 0; padding: 1em; }}
+    section {{ background-color: #fffaff; margin: 0.5em auto; padding: 0.5em 1em; max-width: 60em; box-shadow: 3px 3px 3px rgba(50, 50, 50, 0.2); }}
+    svg {{ width: 100%; height: 10em; fill: #4a76a8; }}
+    rect:hover {{ fill: #333333; }}
+  </style>
+</head>
+<body>
+  <section>
+    <h1>Statistics</h1>
+    <p>The last {weeks} weeks, from {from} to {to} (UTC). Hover a bar for its value.</p>
+  </section>
+  <section>
+    <h2>Finds per day</h2>
+    {finds}
+  </section>
+  <section>
+    <h2>Most users online at once</h2>
+    {users}
+  </section>
+  <section>
+    <h2>Hit rate</h2>
+    <p>Finds per request, estimated from the rates clients report.</p>
+    {hit_rate}
+  </section>
+</body>
+</html>
+"#,
+        weeks = DAYS / 7,
+        from = dates::date(daily[0].start),
+        to = dates::date(daily[daily.len() - 1].start),
+        finds = bar_chart(&finds),
+        users = bar_chart(&users),
+        hit_rate = bar_chart(&hit_rate)
+    )
+}
+
+/// The operator dashboard. It holds no data itself, it trades the admin
+/// token for one that expires and reads everything from the admin API with
+/// that. Only the expiring token is kept in the browser.
+const ADMIN_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8" />
+  <meta content="width=device-width, initial-scale=1.0" name="viewport" />
+  <title>Dashboard - Random Imgur Wall</title>
+  <style>
+    body { font-family: sans-serif; background-color: #e2e2e2; color: #333333; margin: 0; padding: 1em; }
+    section { background-color: #fffaff; margin: 0.5em auto; padding: 0.5em 1em; max-width: 60em; box-shadow: 3px 3px 3px rgba(50, 50, 50, 0.2); overflow-x: auto; }
+    table { border-collapse: collapse; width: 100%; }
+    td, th { text-align: left; padding: 0.2em 0.5em; border-bottom: 1px solid #e2e2e2; }
+    #error { color: #a83a3a; }
+    .preview { max-height: 6em; max-width: 8em; filter: blur(8px); }
+    .preview:hover { filter: none; }
+  </style>
+</head>
+<body>
+  <section>
+    <h1>Dashboard</h1>
+    <form id="login">
+      <label for="token">Admin token</label>
+      <input id="token" type="password" />
+      <button type="submit">Open</button>
+    </form>
+    <p id="error"></p>
+  </section>
+  <section>
+    <h2>Announcement</h2>
+    <form id="announce">
+      <input id="announcement" type="text" size="60" placeholder="Shown to every user" />
+      <button type="submit">Send</button>
+    </form>
+  </section>
+  <section>
+    <h2>Connections</h2>
+    <table id="connections"></table>
+  </section>
+  <section>
+    <h2>By IP address</h2>
+    <table id="ips"></table>
+  </section>
+  <section>
+    <h2>Quarantine</h2>
+    <p>Previews are blurred until hovered.</p>
+    <button id="release-selected">Release selected</button>
+    <button id="discard-selected">Discard selected</button>
+    <table id="quarantine"></table>
+  </section>
+  <section>
+    <h2>Recent rejections</h2>
+    <table id="rejections"></table>
+  </section>
+  <section>
+    <h2>Bans</h2>
+    <form id="ban">
+      <input id="ban-ip" type="text" placeholder="IP address" />
+      <input id="ban-client" type="text" placeholder="Client ID" />
+      <button type="submit">Ban</button>
+    </form>
+    <table id="bans"></table>
+  </section>
+  <script>
+    // Left there by earlier versions, which kept ADMIN_TOKEN itself.
+    sessionStorage.removeItem("admin-token");
+    var token = sessionStorage.getItem("admin-session") || "";
+    // Quarantined finds ticked for a batch action, kept across refreshes.
+    var selected = {};
+
+    function request(path, method, credential) {
+      return fetch(path, { method: method, headers: { Authorization: "Bearer " + credential } })
+        .then(function (response) {
+          if (response.status === 401) {
+            throw new Error("Invalid admin token.");
+          } else if (!response.ok) {
+            throw new Error(response.status + " " + path);
+          }
+          return response.json();
+        });
+    }
+
+    function api(path, method) {
+      return request(path, method || "GET", token).catch(function (err) {
+        if (err.message === "Invalid admin token.") {
+          token = "";
+          sessionStorage.removeItem("admin-session");
+          err.message = "The session expired, enter the admin token again.";
+        }
+        throw err;
+      });
+    }
+
+    function post(path, params) {
+      return api(path + "?" + new URLSearchParams(params), "POST").then(refresh, showError);
+    }
+
+    function postSelected(path) {
+      var ids = Object.keys(selected);
+      if (ids.length === 0) {
+        return;
+      }
+      selected = {};
+      post(path, ids.map(function (id) { return ["id", id]; }));
+    }
+
+    function button(label, onclick) {
+      var element = document.createElement("button");
+      element.textContent = label;
+      element.onclick = onclick;
+      return element;
+    }
+
+    function fill(id, headers, rows) {
+      var table = document.getElementById(id);
+      table.textContent = "";
+
+      var head = table.insertRow();
+      headers.forEach(function (header) {
+        var cell = document.createElement("th");
+        cell.textContent = header;
+        head.appendChild(cell);
+      });
+
+      rows.forEach(function (values) {
+        var row = table.insertRow();
+        values.forEach(function (value) {
+          var cell = row.insertCell();
+          if (value instanceof Node) {
+            cell.appendChild(value);
+          } else {
+            cell.textContent = value === null || value === undefined ? "" : value;
+          }
+        });
+      });
+    }
+
+    function actions() {
+      var span = document.createElement("span");
+      Array.prototype.forEach.call(arguments, function (element) { span.appendChild(element); });
+      return span;
+    }
+
+    function time(secs) {
+      return new Date(secs * 1000).toISOString().replace("T", " ").slice(0, 19);
+    }
+
+    function showError(err) {
+      document.getElementById("error").textContent = err.message;
+    }
+
+    function refresh() {
+      if (!token) {
+        return;
+      }
+
+      Promise.all([
+        api("/api/admin/connections"),
+        api("/api/admin/ips"),
+        api("/api/admin/quarantine"),
+        api("/api/admin/rejections"),
+        api("/api/admin/bans"),
+      ]).then(function (results) {
+        document.getElementById("error").textContent = "";
+
+        fill("connections", ["Connection", "IP", "Region", "Client ID", "Bruteforcing", ""], results[0].map(function (connection) {
+          return [connection.connection_id, connection.ip, connection.region, connection.client_id,
+            connection.is_bruteforcing ? (connection.is_over_rate ? "over rate" : "yes") : "no",
+            actions(
+              button("Ban IP", function () { post("/api/admin/ban", { ip: connection.ip || "" }); }),
+              button("Ban client", function () { post("/api/admin/ban", { client_id: connection.client_id || "" }); })
+            )];
+        }));
+        fill("ips", ["IP", "Connections", "Bruteforcing", "Recent rejections", ""], results[1].map(function (ip) {
+          return [ip.ip, ip.connections, ip.bruteforcing, ip.rejections,
+            button("Ban", function () { post("/api/admin/ban", { ip: ip.ip }); })];
+        }));
+        var quarantined = {};
+        fill("quarantine", ["", "Found", "Image", "Client ID", "Reason", ""], results[2].map(function (find) {
+          quarantined[find.id] = true;
+          var checkbox = document.createElement("input");
+          checkbox.type = "checkbox";
+          checkbox.checked = !!selected[find.id];
+          checkbox.onchange = function () {
+            if (checkbox.checked) {
+              selected[find.id] = true;
+            } else {
+              delete selected[find.id];
+            }
+          };
+          var preview = document.createElement("img");
+          preview.className = "preview";
+          preview.src = "https://i.imgur.com/" + find.id + "m.jpg";
+          preview.alt = find.id;
+          preview.referrerPolicy = "no-referrer";
+          var link = document.createElement("a");
+          link.href = "https://i.imgur.com/" + find.id + ".jpg";
+          link.target = "_blank";
+          link.rel = "noopener noreferrer";
+          link.appendChild(preview);
+          return [checkbox, time(find.at), link, find.client_id, find.reason,
+            actions(
+              button("Release", function () { post("/api/admin/quarantine/release", { id: find.id }); }),
+              button("Discard", function () { post("/api/admin/quarantine/discard", { id: find.id }); })
+            )];
+        }));
+        Object.keys(selected).forEach(function (id) {
+          if (!quarantined[id]) {
+            delete selected[id];
+          }
+        });
+        fill("rejections", ["At", "Image", "Client ID", "IP", "Reason"], results[3].reverse().map(function (rejection) {
+          return [time(rejection.at), rejection.id, rejection.client_id, rejection.ip, rejection.reason];
+        }));
+        fill("bans", ["Banned", ""], results[4].ips.map(function (ip) {
+          return ["IP " + ip, button("Unban", function () { post("/api/admin/unban", { ip: ip }); })];
+        }).concat(results[4].client_ids.map(function (clientId) {
+          return ["Client " + clientId, button("Unban", function () { post("/api/admin/unban", { client_id: clientId }); })];
+        })));
+      }, showError);
+    }
+
+    document.getElementById("login").onsubmit = function (event) {
+      event.preventDefault();
+      var input = document.getElementById("token");
+      request("/api/admin/token", "POST", input.value).then(function (session) {
+        input.value = "";
+        token = session.token;
+        sessionStorage.setItem("admin-session", token);
+        refresh();
+      }, showError);
+    };
+    document.getElementById("announce").onsubmit = function (event) {
+      event.preventDefault();
+      var input = document.getElementById("announcement");
+      post("/api/admin/announce", { text: input.value }).then(function () { input.value = ""; });
+    };
+    document.getElementById("release-selected").onclick = function () {
+      postSelected("/api/admin/quarantine/release");
+    };
+    document.getElementById("discard-selected").onclick = function () {
+      postSelected("/api/admin/quarantine/discard");
+    };
+    document.getElementById("ban").onsubmit = function (event) {
+      event.preventDefault();
+      var params = {};
+      var ip = document.getElementById("ban-ip").value;
+      var clientId = document.getElementById("ban-client").value;
+      if (ip) { params.ip = ip; }
+      if (clientId) { params.client_id = clientId; }
+      post("/api/admin/ban", params);
+    };
+
+    refresh();
+    setInterval(refresh, 5000);
+  </script>
+</body>
+</html>
+"#;
+
+pub fn render_admin() -> String {
+    ADMIN_PAGE.to_owned()
+}
@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use protocol::ImageId;
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// The last submitted image IDs, so retried or concurrent submissions of the
+/// same find are only broadcast once.
+pub struct RecentIds {
+    ids: HashSet<ImageId>,
+    order: VecDeque<ImageId>,
+    capacity: usize,
+    evictions: u64,
+}
+
+impl RecentIds {
+    pub fn new(capacity: usize) -> RecentIds {
+        RecentIds {
+            ids: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+            evictions: 0,
+        }
+    }
+
+    /// How many IDs were forgotten to stay within capacity.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Returns `false` if `id` was already submitted recently.
+    pub fn insert(&mut self, id: ImageId) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+
+        self.order.push_back(id);
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+                self.evictions += 1;
+            }
+        }
+
+        true
+    }
+
+    /// Forgets a submission that got rejected, so it can be submitted again.
+    pub fn remove(&mut self, id: ImageId) {
+        if self.ids.remove(&id) {
+            self.order.retain(|&other| other != id);
+        }
+    }
+}
+
+/// The federated instances known to have each recent find, from the finds
+/// they pushed, their origins and the seen sets peers gossip, so a find is
+/// never pushed to an instance that has it.
+pub struct PeerKnowledge {
+    peers_by_id: HashMap<ImageId, Vec<String>>,
+    order: VecDeque<ImageId>,
+    capacity: usize,
+}
+
+impl PeerKnowledge {
+    pub fn new(capacity: usize) -> PeerKnowledge {
+        PeerKnowledge {
+            peers_by_id: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, id: ImageId, peer: &str) {
+        let peers = match self.peers_by_id.get_mut(&id) {
+            Some(peers) => peers,
+            None => {
+                self.order.push_back(id);
+
+                while self.order.len() > self.capacity {
+                    if let Some(oldest) = self.order.pop_front() {
+                        self.peers_by_id.remove(&oldest);
+                    }
+                }
+
+                self.peers_by_id.entry(id).or_default()
+            }
+        };
+
+        if !peers.iter().any(|known| known == peer) {
+            peers.push(peer.to_owned());
+        }
+    }
+
+    pub fn knows(&self, id: ImageId, peer: &str) -> bool {
+        self.peers_by_id
+            .get(&id)
+            .map_or(false, |peers| peers.iter().any(|known| known == peer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: usize) -> ImageId {
+        ImageId::new(&format!("id{:03}", n)).unwrap()
+    }
+
+    #[test]
+    fn recent_ids_forget_the_oldest_past_capacity() {
+        let mut recent_ids = RecentIds::new(3);
+
+        for n in 0..5 {
+            assert!(recent_ids.insert(id(n)));
+        }
+
+        assert_eq!(recent_ids.ids.len(), 3);
+        assert_eq!(recent_ids.order.len(), 3);
+        assert_eq!(recent_ids.evictions(), 2);
+        assert!(recent_ids.insert(id(0)));
+        assert!(!recent_ids.insert(id(4)));
+    }
+
+    #[test]
+    fn removed_ids_leave_the_order_too() {
+        let mut recent_ids = RecentIds::new(3);
+
+        recent_ids.insert(id(0));
+        recent_ids.insert(id(1));
+        recent_ids.remove(id(0));
+
+        assert_eq!(recent_ids.order, [id(1)]);
+        assert!(recent_ids.insert(id(0)));
+        assert_eq!(recent_ids.order, [id(1), id(0)]);
+
+        // Removing an unknown ID changes nothing.
+        recent_ids.remove(id(9));
+        assert_eq!(recent_ids.order.len(), 2);
+
+        // The removed ID no longer takes a place, nothing is evicted until
+        // three are held again.
+        recent_ids.insert(id(2));
+        assert_eq!(recent_ids.evictions(), 0);
+        recent_ids.insert(id(3));
+        assert_eq!(recent_ids.evictions(), 1);
+        assert_eq!(recent_ids.order, [id(0), id(2), id(3)]);
+    }
+
+    #[test]
+    fn peer_knowledge_forgets_the_oldest_finds_past_capacity() {
+        let mut knowledge = PeerKnowledge::new(2);
+
+        knowledge.record(id(0), "a");
+        knowledge.record(id(0), "b");
+        knowledge.record(id(0), "a");
+        assert_eq!(knowledge.order.len(), 1);
+        assert_eq!(knowledge.peers_by_id[&id(0)], ["a", "b"]);
+
+        knowledge.record(id(1), "a");
+        knowledge.record(id(2), "a");
+
+        assert_eq!(knowledge.order.len(), 2);
+        assert_eq!(knowledge.peers_by_id.len(), 2);
+        assert!(!knowledge.knows(id(0), "a"));
+        assert!(knowledge.knows(id(1), "a"));
+        assert!(!knowledge.knows(id(1), "b"));
+        assert!(knowledge.knows(id(2), "a"));
+    }
+}
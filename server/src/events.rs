@@ -0,0 +1,307 @@
+//! What happens to clients and finds, published as typed events. The
+//! records, the moderation log, federation and webhooks subscribe to them,
+//! so integrations attach here instead of in the websocket handler.
+
+use std::env;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+use protocol::{FindSource, ImageId, WsMessage, WsMessageType};
+
+use crate::moderation::Rejection;
+use crate::{dates, Shared};
+
+/// Events the webhook can lag behind before it drops some.
+const WEBHOOK_BACKLOG: usize = 256;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serialized as it is posted to the webhook, without IPs.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    ClientJoined {
+        connection_id: u32,
+        client_id: Option<String>,
+        #[serde(skip)]
+        ip: Option<String>,
+    },
+    ClientLeft {
+        connection_id: u32,
+    },
+    /// A find passed every check and is on its way to the wall.
+    FindValidated {
+        id: ImageId,
+        client_id: Option<String>,
+        /// When it was received, in milliseconds since the unix epoch.
+        at: u64,
+        source: Option<FindSource>,
+        origin: Option<String>,
+        #[serde(skip)]
+        signature: Option<String>,
+    },
+    FindQuarantined {
+        id: ImageId,
+        client_id: Option<String>,
+        reason: String,
+    },
+    FindRejected {
+        id: ImageId,
+        client_id: Option<String>,
+        #[serde(skip)]
+        ip: Option<String>,
+        reason: String,
+    },
+    /// A find on a hash list of known abusive images.
+    ImageBlocked {
+        id: ImageId,
+        client_id: Option<String>,
+        #[serde(skip)]
+        ip: Option<String>,
+        origin: Option<String>,
+        list: String,
+    },
+    ClientBanned {
+        #[serde(skip)]
+        ip: Option<String>,
+        client_id: Option<String>,
+    },
+}
+
+/// Called on the thread that published the event, so subscribers that do
+/// slow work hand it off.
+pub trait Subscriber: Send + Sync {
+    fn notify(&self, shared: &Shared, event: &Event);
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn Subscriber>>,
+}
+
+impl EventBus {
+    /// The server's own subscribers, and the webhook if
+    /// `EVENTS_WEBHOOK_URL` is set.
+    pub fn from_env() -> EventBus {
+        let mut events = EventBus::default();
+
+        events.subscribe(Records);
+        events.subscribe(Moderation);
+        events.subscribe(Federate);
+
+        if let Some(url) = env::var("EVENTS_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+        {
+            events.subscribe(Webhook::new(url));
+        }
+
+        events
+    }
+
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    pub fn publish(&self, shared: &Shared, event: &Event) {
+        for subscriber in &self.subscribers {
+            subscriber.notify(shared, event);
+        }
+    }
+}
+
+/// Keeps profiles, activity, the archive, the image of the day and the
+/// timeline up to date.
+struct Records;
+
+impl Subscriber for Records {
+    fn notify(&self, shared: &Shared, event: &Event) {
+        match event {
+            Event::FindValidated { id, client_id, .. } => {
+                let mut contributor = None;
+
+                if let Some(client_id) = client_id {
+                    let mut profiles = shared.profiles.lock().unwrap();
+
+                    if profiles.record_find(client_id, *id) {
+                        shared.save_profiles(&profiles);
+                    }
+
+                    contributor = profiles
+                        .by_client(client_id)
+                        .map(|profile| profile.name.clone());
+                }
+
+                shared.activity.lock().unwrap().record_find(contributor);
+
+                let now = dates::now_secs();
+                let line = shared.archive.lock().unwrap().record(now, *id);
+
+                if let (Some(path), Some(line)) = (&shared.archive_path, line) {
+                    shared.writer.append(path, line);
+                }
+
+                let mut iotd = shared.iotd.lock().unwrap();
+
+                if iotd.record_find(&dates::date(now), *id) {
+                    shared.save_iotd(&iotd);
+                }
+
+                drop(iotd);
+
+                let mut timeline = shared.timeline.lock().unwrap();
+
+                if timeline.record_find(now) {
+                    shared.save_timeline(&timeline);
+                }
+            }
+            Event::FindRejected { .. } => {
+                let mut timeline = shared.timeline.lock().unwrap();
+
+                if timeline.record_rejection(dates::now_secs()) {
+                    shared.save_timeline(&timeline);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps the recent rejections and the screening count for the operator
+/// dashboard.
+struct Moderation;
+
+impl Subscriber for Moderation {
+    fn notify(&self, shared: &Shared, event: &Event) {
+        match event {
+            Event::FindRejected {
+                id,
+                client_id,
+                ip,
+                reason,
+            } => shared.rejections.lock().unwrap().record(Rejection {
+                at: dates::now_secs(),
+                id: id.to_string(),
+                reason: reason.clone(),
+                client_id: client_id.clone(),
+                ip: ip.clone(),
+            }),
+            Event::ImageBlocked { .. } => shared.metrics.lock().unwrap().screen_matches += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Pushes finds accepted here to federated peers.
+struct Federate;
+
+impl Subscriber for Federate {
+    fn notify(&self, shared: &Shared, event: &Event) {
+        if let Event::FindValidated {
+            id,
+            at,
+            source,
+            origin,
+            signature,
+            ..
+        } = event
+        {
+            let federation = &shared.federation;
+
+            federation.forward(
+                *id,
+                &WsMessage {
+                    text: Some(id.to_string()),
+                    at: Some(*at),
+                    source: *source,
+                    origin: Some(origin.clone().unwrap_or_else(|| federation.name.clone())),
+                    signature: signature.clone().or_else(|| federation.sign_find(*id, *at)),
+                    ..WsMessage::new(WsMessageType::Federate)
+                },
+            );
+        }
+    }
+}
+
+/// Posts every event as JSON to `EVENTS_WEBHOOK_URL`, one at a time on its
+/// own thread.
+struct Webhook {
+    sender: SyncSender<String>,
+}
+
+impl Webhook {
+    fn new(url: String) -> Webhook {
+        let (sender, receiver) = mpsc::sync_channel::<String>(WEBHOOK_BACKLOG);
+
+        thread::spawn(move || {
+            for body in receiver {
+                let response = ureq::post(&url)
+                    .set("Content-Type", "application/json")
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .send_string(&body);
+
+                if let Some(err) = response.synthetic_error() {
+                    eprintln!("could not post event to {}: {}", url, err);
+                } else if !response.ok() {
+                    eprintln!("{} answered {} to an event", url, response.status());
+                }
+            }
+        });
+
+        Webhook { sender }
+    }
+}
+
+impl Subscriber for Webhook {
+    fn notify(&self, shared: &Shared, event: &Event) {
+        let body = match serde_json::to_string(event) {
+            Ok(body) => body,
+            Err(_) => return,
+        };
+
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(body) {
+            shared.metrics.lock().unwrap().webhook_drops += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ips_are_not_serialized() {
+        let ip = Some("192.0.2.1".to_owned());
+        let events = [
+            Event::ClientJoined {
+                connection_id: 1,
+                client_id: None,
+                ip: ip.clone(),
+            },
+            Event::FindRejected {
+                id: ImageId::new("abcde").unwrap(),
+                client_id: None,
+                ip: ip.clone(),
+                reason: "refused".to_owned(),
+            },
+            Event::ImageBlocked {
+                id: ImageId::new("abcde").unwrap(),
+                client_id: None,
+                ip: ip.clone(),
+                origin: None,
+                list: "list.txt".to_owned(),
+            },
+            Event::ClientBanned {
+                ip,
+                client_id: Some("client".to_owned()),
+            },
+        ];
+
+        for event in &events {
+            let json = serde_json::to_value(event).unwrap();
+
+            assert!(json.get("ip").is_none(), "{}", json);
+            assert!(!json.to_string().contains("192.0.2.1"), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::{HashMap, VecDeque};
+
+pub const DEFAULT_BROADCASTS_PER_SEC: u32 = 10;
+/// Finds of a single contributor waiting beyond this are dropped, oldest
+/// first. They are still archived, only not shown live.
+const MAX_QUEUED_PER_SOURCE: usize = 100;
+
+/// Finds waiting to be broadcast, queued by who found them and taken in
+/// turn, so one prolific contributor cannot bury everyone else's finds.
+pub struct FairQueue<T> {
+    queues: HashMap<String, VecDeque<T>>,
+    /// Sources with queued finds, in the order they are served.
+    turns: VecDeque<String>,
+}
+
+impl<T> Default for FairQueue<T> {
+    fn default() -> Self {
+        FairQueue {
+            queues: HashMap::new(),
+            turns: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> FairQueue<T> {
+    /// Queues `item` found by `source`, returning the find it drops when
+    /// that source already has too many queued.
+    pub fn push(&mut self, source: String, item: T) -> Option<T> {
+        let queue = match self.queues.get_mut(&source) {
+            Some(queue) => queue,
+            None => {
+                self.turns.push_back(source.clone());
+                self.queues.entry(source).or_default()
+            }
+        };
+
+        let dropped = if queue.len() == MAX_QUEUED_PER_SOURCE {
+            queue.pop_front()
+        } else {
+            None
+        };
+
+        queue.push_back(item);
+
+        dropped
+    }
+
+    /// The next find to broadcast, from the source whose turn it is.
+    pub fn pop(&mut self) -> Option<T> {
+        let source = self.turns.pop_front()?;
+        let queue = self.queues.get_mut(&source)?;
+        let item = queue.pop_front();
+
+        if queue.is_empty() {
+            self.queues.remove(&source);
+        } else {
+            self.turns.push_back(source);
+        }
+
+        item
+    }
+}
@@ -0,0 +1,519 @@
+//! Federation with other instances. Finds accepted here are pushed to every
+//! peer in `FEDERATION_PEERS`, and finds pushed by peers that know the
+//! `FEDERATION_TOKEN` are accepted here like any other, tagged with the
+//! instance they were found on. Peers gossip the finds they have, so finds
+//! are not pushed to instances that already have them.
+//!
+//! Finds are signed with the ed25519 key of the instance they were found
+//! on, and hellos and seen sets with that of the instance sending them, so a
+//! peer cannot pass its own traffic off as another instance's. Hellos and
+//! finds are signed with the time they were sent or found at, and refused
+//! once stale, so captured ones cannot be replayed for long.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use protocol::{ImageId, WsMessage, WsMessageType};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use ws::{CloseCode, Error as WSError, Handler, Handshake, Message, Result, Sender};
+
+use crate::dedup::{self, PeerKnowledge};
+use crate::{dates, Shared};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+const NAME_LEN: usize = 8;
+pub const GOSSIP_INTERVAL: Duration = Duration::from_secs(10);
+/// Finds beyond this between two gossips are not told to peers, which
+/// only costs them a duplicate push.
+const MAX_SEEN_PER_GOSSIP: usize = 1000;
+/// How far clocks of instances can be apart, and so how old a hello can be.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(60);
+/// How long after it was found a find is still accepted from peers, time
+/// for it to go through verification and a few hops.
+const MAX_FIND_AGE: Duration = Duration::from_secs(600);
+
+struct Link {
+    out: Sender,
+    /// The peer's name, once it answered the federation token.
+    name: Option<String>,
+}
+
+pub struct Federation {
+    /// What other instances tag the finds of this one with.
+    pub name: String,
+    token: Option<String>,
+    peers: Vec<String>,
+    /// Open links to peers, by URL.
+    links: Mutex<HashMap<String, Link>>,
+    pub known: Mutex<PeerKnowledge>,
+    /// Finds accepted since peers were last told.
+    seen: Mutex<Vec<ImageId>>,
+    keypair: Option<Keypair>,
+    /// The public keys of other instances, by name.
+    peer_keys: HashMap<String, PublicKey>,
+}
+
+impl Federation {
+    pub fn from_env() -> std::result::Result<Federation, String> {
+        let keypair = match env::var("FEDERATION_KEY") {
+            Ok(key) => {
+                let secret = decode(&key)
+                    .and_then(|key| SecretKey::from_bytes(&key).ok())
+                    .ok_or("FEDERATION_KEY must be a base64 ed25519 secret key")?;
+                let public = PublicKey::from(&secret);
+
+                Some(Keypair { secret, public })
+            }
+            Err(_) => None,
+        };
+        let mut peer_keys = HashMap::new();
+
+        for peer_key in env::var("FEDERATION_PEER_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|peer_key| !peer_key.is_empty())
+        {
+            let mut parts = peer_key.splitn(2, '=');
+            let name = parts.next().unwrap_or_default();
+            let key = parts
+                .next()
+                .and_then(decode)
+                .and_then(|key| PublicKey::from_bytes(&key).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "FEDERATION_PEER_KEYS: \"{}\" is not name=base64 ed25519 public key",
+                        peer_key
+                    )
+                })?;
+
+            peer_keys.insert(name.to_owned(), key);
+        }
+
+        let federation = Federation {
+            name: env::var("INSTANCE_NAME")
+                .ok()
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| {
+                    thread_rng()
+                        .sample_iter(&Alphanumeric)
+                        .take(NAME_LEN)
+                        .collect()
+                }),
+            token: env::var("FEDERATION_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            peers: env::var("FEDERATION_PEERS")
+                .map(|peers| {
+                    peers
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|peer| !peer.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            links: Mutex::new(HashMap::new()),
+            known: Mutex::new(PeerKnowledge::new(dedup::DEFAULT_CAPACITY)),
+            seen: Mutex::new(Vec::new()),
+            keypair,
+            peer_keys,
+        };
+
+        if !federation.peers.is_empty()
+            && (federation.keypair.is_none() || env::var("INSTANCE_NAME").is_err())
+        {
+            return Err("FEDERATION_PEERS needs INSTANCE_NAME and FEDERATION_KEY".to_owned());
+        }
+
+        Ok(federation)
+    }
+
+    /// The key peers list this instance with in `FEDERATION_PEER_KEYS`.
+    pub fn public_key(&self) -> Option<String> {
+        self.keypair
+            .as_ref()
+            .map(|keypair| base64::encode(keypair.public.as_bytes()))
+    }
+
+    /// The `Federate` message an instance opens a link with, naming itself,
+    /// and its peer answers with.
+    pub fn hello(&self) -> WsMessage {
+        let at = dates::now_ms();
+
+        WsMessage {
+            text: Some(self.name.clone()),
+            at: Some(at),
+            signature: self.sign(&hello_payload(&self.name, at)),
+            ..WsMessage::new(WsMessageType::Federate)
+        }
+    }
+
+    /// Checks that a hello was signed by the instance it names, recently,
+    /// and returns that name.
+    pub fn verify_hello<'a>(
+        &self,
+        ws_message: &'a WsMessage,
+    ) -> std::result::Result<&'a str, &'static str> {
+        let name = ws_message.text.as_deref().ok_or("no name")?;
+        let at = ws_message.at.ok_or("no time")?;
+
+        check_recent(at, MAX_CLOCK_SKEW)?;
+        self.verify(
+            name,
+            &hello_payload(name, at),
+            ws_message.signature.as_deref(),
+        )?;
+
+        Ok(name)
+    }
+
+    /// Signs a find accepted here at `at` for peers.
+    pub fn sign_find(&self, id: ImageId, at: u64) -> Option<String> {
+        self.sign(&find_payload(&self.name, id, at))
+    }
+
+    /// Checks that a find pushed by a peer was signed by its origin, and
+    /// found there recently.
+    pub fn verify_find(
+        &self,
+        origin: &str,
+        id: ImageId,
+        at: Option<u64>,
+        signature: Option<&str>,
+    ) -> std::result::Result<(), &'static str> {
+        let at = at.ok_or("no time")?;
+
+        check_recent(at, MAX_FIND_AGE)?;
+        self.verify(origin, &find_payload(origin, id, at), signature)
+    }
+
+    /// Checks that a seen set was signed by the peer telling it.
+    pub fn verify_seen(
+        &self,
+        peer: &str,
+        ids: &str,
+        signature: Option<&str>,
+    ) -> std::result::Result<(), &'static str> {
+        self.verify(peer, &seen_payload(peer, ids), signature)
+    }
+
+    fn sign(&self, payload: &str) -> Option<String> {
+        self.keypair
+            .as_ref()
+            .map(|keypair| base64::encode(&keypair.sign(payload.as_bytes()).to_bytes()[..]))
+    }
+
+    fn verify(
+        &self,
+        signer: &str,
+        payload: &str,
+        signature: Option<&str>,
+    ) -> std::result::Result<(), &'static str> {
+        let signature = signature.ok_or("unsigned")?;
+        let key = self.peer_keys.get(signer).ok_or("no key for its signer")?;
+        let signature = decode(signature)
+            .and_then(|signature| Signature::try_from(&signature[..]).ok())
+            .ok_or("malformed signature")?;
+
+        key.verify(payload.as_bytes(), &signature)
+            .map_err(|_| "bad signature")
+    }
+
+    pub fn is_peer_token(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (Some(expected), Some(token)) => expected == token,
+            _ => false,
+        }
+    }
+
+    /// Pushes a find accepted here to every peer not known to have it.
+    pub fn forward(&self, id: ImageId, ws_message: &WsMessage) {
+        if self.peers.is_empty() {
+            return;
+        }
+
+        let mut seen = self.seen.lock().unwrap();
+
+        if seen.len() < MAX_SEEN_PER_GOSSIP {
+            seen.push(id);
+        }
+
+        drop(seen);
+
+        let text = match serde_json::to_string(ws_message) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+        let mut known = self.known.lock().unwrap();
+
+        for (url, link) in self.links.lock().unwrap().iter() {
+            if let Some(name) = &link.name {
+                if known.knows(id, name) {
+                    continue;
+                }
+            }
+
+            match link.out.send(text.as_str()) {
+                Ok(()) => {
+                    if let Some(name) = &link.name {
+                        known.record(id, name);
+                    }
+                }
+                Err(err) => eprintln!("could not forward find to {}: {}", url, err),
+            }
+        }
+    }
+
+    /// Tells every peer the finds accepted since last time.
+    fn gossip(&self) {
+        let seen = std::mem::take(&mut *self.seen.lock().unwrap());
+
+        if seen.is_empty() {
+            return;
+        }
+
+        let ids = seen
+            .iter()
+            .map(ImageId::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ws_message = WsMessage {
+            signature: self.sign(&seen_payload(&self.name, &ids)),
+            text: Some(ids),
+            ..WsMessage::new(WsMessageType::Seen)
+        };
+        let text = match serde_json::to_string(&ws_message) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+
+        for (url, link) in self.links.lock().unwrap().iter() {
+            if let Err(err) = link.out.send(text.as_str()) {
+                eprintln!("could not gossip to {}: {}", url, err);
+            }
+        }
+    }
+}
+
+/// Keeps a link open to every peer, reconnecting when it drops, and tells
+/// them what this instance has.
+pub fn connect(shared: Arc<Shared>) {
+    if shared.federation.peers.is_empty() {
+        return;
+    }
+
+    let gossip_shared = shared.clone();
+    thread::spawn(move || loop {
+        thread::sleep(GOSSIP_INTERVAL);
+        gossip_shared.federation.gossip();
+    });
+
+    for url in shared.federation.peers.clone() {
+        let shared = shared.clone();
+
+        thread::spawn(move || loop {
+            if let Err(err) = ws::connect(url.as_str(), |out| PeerLink {
+                out,
+                url: url.clone(),
+                shared: shared.clone(),
+            }) {
+                eprintln!("could not connect to peer {}: {}", url, err);
+            }
+
+            shared.federation.links.lock().unwrap().remove(&url);
+            thread::sleep(RECONNECT_DELAY);
+        });
+    }
+}
+
+/// The connection to a peer, used to push finds to it. What the peer
+/// broadcasts on it is ignored, only its name is kept.
+struct PeerLink {
+    out: Sender,
+    url: String,
+    shared: Arc<Shared>,
+}
+
+impl Handler for PeerLink {
+    fn on_open(&mut self, _: Handshake) -> Result<()> {
+        let federation = &self.shared.federation;
+        let hello = WsMessage {
+            token: federation.token.clone(),
+            ..federation.hello()
+        };
+
+        // Not through `Shared::send`, which takes failures for those of a
+        // user with the same connection ID.
+        if let Ok(text) = serde_json::to_string(&hello) {
+            if let Err(err) = self.out.send(text) {
+                eprintln!("could not say hello to peer {}: {}", self.url, err);
+            }
+        }
+
+        federation.links.lock().unwrap().insert(
+            self.url.clone(),
+            Link {
+                out: self.out.clone(),
+                name: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: Message) -> Result<()> {
+        let ws_message = msg
+            .as_text()
+            .ok()
+            .and_then(|text| serde_json::from_str::<WsMessage>(text).ok());
+
+        if let Some(
+            ws_message @ WsMessage {
+                msg_type: WsMessageType::Federate,
+                ..
+            },
+        ) = &ws_message
+        {
+            let federation = &self.shared.federation;
+
+            match federation.verify_hello(ws_message) {
+                Ok(name) => {
+                    if let Some(link) = federation.links.lock().unwrap().get_mut(&self.url) {
+                        link.name = Some(name.to_owned());
+                    }
+                }
+                Err(reason) => eprintln!("refused hello of peer {}: {}", self.url, reason),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        self.shared
+            .federation
+            .links
+            .lock()
+            .unwrap()
+            .remove(&self.url);
+    }
+
+    fn on_error(&mut self, err: WSError) {
+        eprintln!("link to peer {} failed: {}", self.url, err);
+    }
+}
+
+fn hello_payload(name: &str, at: u64) -> String {
+    format!("hello {} {}", name, at)
+}
+
+fn find_payload(origin: &str, id: ImageId, at: u64) -> String {
+    format!("find {} {} {}", origin, id, at)
+}
+
+fn seen_payload(peer: &str, ids: &str) -> String {
+    format!("seen {} {}", peer, ids)
+}
+
+fn decode(base64: &str) -> Option<Vec<u8>> {
+    base64::decode(base64.trim()).ok()
+}
+
+/// Checks that `at`, in milliseconds since the epoch, is at most `max_age`
+/// ago.
+fn check_recent(at: u64, max_age: Duration) -> std::result::Result<(), &'static str> {
+    let now = dates::now_ms();
+
+    if now.saturating_sub(at) > max_age.as_millis() as u64 {
+        Err("too old, replayed")
+    } else if at.saturating_sub(now) > MAX_CLOCK_SKEW.as_millis() as u64 {
+        Err("from the future")
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::OsRng;
+
+    fn federation(name: &str, keypair: Keypair, peer_keys: &[(&str, &Keypair)]) -> Federation {
+        Federation {
+            name: name.to_owned(),
+            token: None,
+            peers: Vec::new(),
+            links: Mutex::new(HashMap::new()),
+            known: Mutex::new(PeerKnowledge::new(dedup::DEFAULT_CAPACITY)),
+            seen: Mutex::new(Vec::new()),
+            keypair: Some(keypair),
+            peer_keys: peer_keys
+                .iter()
+                .map(|(name, keypair)| (name.to_string(), keypair.public))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn hellos_must_be_signed_by_the_instance_they_name() {
+        let a = Keypair::generate(&mut OsRng);
+        let c = Keypair::generate(&mut OsRng);
+        let here = federation("b", Keypair::generate(&mut OsRng), &[("a", &a), ("c", &c)]);
+        let a = federation("a", a, &[]);
+
+        let hello = a.hello();
+        assert_eq!(here.verify_hello(&hello), Ok("a"));
+
+        let impostor = WsMessage {
+            text: Some("c".to_owned()),
+            ..a.hello()
+        };
+        assert_eq!(here.verify_hello(&impostor), Err("bad signature"));
+
+        let at = dates::now_ms() - 2 * MAX_CLOCK_SKEW.as_millis() as u64;
+        let stale = WsMessage {
+            at: Some(at),
+            signature: a.sign(&hello_payload("a", at)),
+            ..a.hello()
+        };
+        assert_eq!(here.verify_hello(&stale), Err("too old, replayed"));
+    }
+
+    #[test]
+    fn finds_must_be_signed_with_the_time_they_were_found_at() {
+        let a = Keypair::generate(&mut OsRng);
+        let here = federation("b", Keypair::generate(&mut OsRng), &[("a", &a)]);
+        let a = federation("a", a, &[]);
+        let id = ImageId::new("abcde").unwrap();
+        let at = dates::now_ms();
+
+        let signature = a.sign_find(id, at);
+        assert_eq!(
+            here.verify_find("a", id, Some(at), signature.as_deref()),
+            Ok(())
+        );
+        assert_eq!(
+            here.verify_find("a", id, Some(at - 1), signature.as_deref()),
+            Err("bad signature")
+        );
+        assert_eq!(
+            here.verify_find("a", id, None, signature.as_deref()),
+            Err("no time")
+        );
+
+        let at = at - 2 * MAX_FIND_AGE.as_millis() as u64;
+        let signature = a.sign_find(id, at);
+        assert_eq!(
+            here.verify_find("a", id, Some(at), signature.as_deref()),
+            Err("too old, replayed")
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use protocol::RegionCounts;
+
+/// Regions with fewer watchers than this are counted as `other`, so nobody
+/// can be told apart.
+const MIN_REGION_WATCHERS: u64 = 3;
+const OTHER: &str = "other";
+
+/// Resolves IP addresses to continents with a GeoLite2 or GeoIP2 database.
+/// Only the continent is kept, never the address.
+pub struct Regions {
+    reader: Reader<Vec<u8>>,
+}
+
+impl Regions {
+    pub fn open(path: &str) -> Result<Regions, MaxMindDBError> {
+        Ok(Regions {
+            reader: Reader::open_readfile(path)?,
+        })
+    }
+
+    pub fn region(&self, ip: IpAddr) -> &'static str {
+        let country = self.reader.lookup::<geoip2::Country>(ip).ok();
+
+        match country
+            .and_then(|country| country.continent)
+            .and_then(|continent| continent.code)
+        {
+            Some("AF") => "AF",
+            Some("AN") => "AN",
+            Some("AS") => "AS",
+            Some("EU") => "EU",
+            Some("NA") => "NA",
+            Some("OC") => "OC",
+            Some("SA") => "SA",
+            _ => OTHER,
+        }
+    }
+}
+
+/// Counts watchers by region, merging the small regions into `other`.
+pub fn breakdown<'a>(regions: impl Iterator<Item = &'a str>) -> RegionCounts {
+    let mut counts = BTreeMap::new();
+
+    for region in regions {
+        *counts.entry(region.to_owned()).or_insert(0) += 1;
+    }
+
+    let small = counts
+        .iter()
+        .filter(|&(region, &count)| region != OTHER && count < MIN_REGION_WATCHERS)
+        .map(|(region, _)| region.clone())
+        .collect::<Vec<_>>();
+
+    for region in small {
+        if let Some(count) = counts.remove(&region) {
+            *counts.entry(OTHER.to_owned()).or_insert(0) += count;
+        }
+    }
+
+    counts
+}
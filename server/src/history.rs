@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+use protocol::WsMessage;
+
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// The last sequenced broadcasts, kept serialized so lagging clients can be
+/// replayed what they missed.
+pub struct History {
+    next_seq: u64,
+    messages: VecDeque<(u64, String)>,
+    capacity: usize,
+    evictions: u64,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            next_seq: 0,
+            messages: VecDeque::with_capacity(capacity),
+            capacity,
+            evictions: 0,
+        }
+    }
+
+    /// How many messages were dropped to stay within capacity.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Stamps `ws_message` with the next sequence number and returns its
+    /// serialized form.
+    pub fn push(&mut self, mut ws_message: WsMessage) -> Option<String> {
+        ws_message.seq = Some(self.next_seq);
+
+        let text = serde_json::to_string(&ws_message).ok()?;
+
+        self.messages.push_back((self.next_seq, text.clone()));
+        self.next_seq += 1;
+
+        while self.messages.len() > self.capacity {
+            self.messages.pop_front();
+            self.evictions += 1;
+        }
+
+        Some(text)
+    }
+
+    /// Returns the sequence number the replay starts at and the messages
+    /// from there on, at most the last `max` ones. Unknown sequence numbers,
+    /// from before the buffer or from before a restart, replay all of them.
+    pub fn replay(&self, from_seq: u64, max: usize) -> (u64, Vec<&str>) {
+        let oldest = self
+            .messages
+            .iter()
+            .rev()
+            .take(max)
+            .last()
+            .map_or(self.next_seq, |(seq, _)| *seq);
+        let start = if from_seq >= oldest && from_seq <= self.next_seq {
+            from_seq
+        } else {
+            oldest
+        };
+
+        (
+            start,
+            self.messages
+                .iter()
+                .filter(|(seq, _)| *seq >= start)
+                .map(|(_, text)| text.as_str())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::WsMessageType;
+
+    fn history(capacity: usize, pushes: usize) -> History {
+        let mut history = History::new(capacity);
+
+        for _ in 0..pushes {
+            history.push(WsMessage::new(WsMessageType::New)).unwrap();
+        }
+
+        history
+    }
+
+    #[test]
+    fn pushing_past_capacity_evicts_the_oldest() {
+        let history = history(3, 5);
+
+        assert_eq!(history.messages.len(), 3);
+        assert_eq!(history.evictions(), 2);
+        assert_eq!(
+            history
+                .messages
+                .iter()
+                .map(|(seq, _)| *seq)
+                .collect::<Vec<_>>(),
+            [2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn replay_starts_where_asked_when_it_can() {
+        let history = history(3, 5);
+
+        assert_eq!(history.replay(3, 10).0, 3);
+        assert_eq!(history.replay(3, 10).1.len(), 2);
+        // Caught up, nothing to replay.
+        assert_eq!(history.replay(5, 10), (5, Vec::new()));
+    }
+
+    #[test]
+    fn replay_of_evicted_or_unknown_seqs_starts_at_the_oldest() {
+        let history = history(3, 5);
+
+        assert_eq!(history.replay(0, 10).0, 2);
+        assert_eq!(history.replay(0, 10).1.len(), 3);
+        // From before a restart.
+        assert_eq!(history.replay(100, 10).0, 2);
+        assert_eq!(history.replay(u64::MAX, 10).1.len(), 3);
+    }
+
+    #[test]
+    fn replay_is_limited_to_the_last_max() {
+        let history = history(3, 5);
+
+        assert_eq!(history.replay(2, 2).0, 3);
+        assert_eq!(history.replay(2, 2).1.len(), 2);
+    }
+
+    #[test]
+    fn replay_of_an_empty_history_is_empty() {
+        let history = history(3, 0);
+
+        assert_eq!(history.replay(0, 10), (0, Vec::new()));
+        assert_eq!(history.replay(7, 10), (0, Vec::new()));
+    }
+}
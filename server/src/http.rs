@@ -0,0 +1,68 @@
+use ws::Response;
+
+pub fn not_found() -> Response {
+    Response::new(404, "Not Found", b"404 - Not Found".to_vec())
+}
+
+pub fn unauthorized() -> Response {
+    Response::new(401, "Unauthorized", b"401 - Unauthorized".to_vec())
+}
+
+pub fn html(body: String) -> Response {
+    with_content_type(
+        Response::new(200, "OK", body.into_bytes()),
+        "text/html; charset=utf-8",
+    )
+}
+
+pub fn json<T: serde::Serialize>(value: &T) -> Response {
+    match serde_json::to_vec(value) {
+        Ok(body) => {
+            let mut response =
+                with_content_type(Response::new(200, "OK", body), "application/json");
+            response
+                .headers_mut()
+                .push(("Access-Control-Allow-Origin".into(), b"*".to_vec()));
+            response
+        }
+        Err(_) => Response::new(
+            500,
+            "Internal Server Error",
+            b"500 - Internal Server Error".to_vec(),
+        ),
+    }
+}
+
+/// A file of the embedded frontend. Fingerprinted files never change, the
+/// others are revalidated.
+pub fn asset(path: &str, contents: &[u8], content_type: &str) -> Response {
+    let is_fingerprinted = path.starts_with("/web.") && path.matches('.').count() == 2;
+    let mut response = with_content_type(Response::new(200, "OK", contents.to_vec()), content_type);
+    response.headers_mut().push((
+        "Cache-Control".into(),
+        if is_fingerprinted {
+            b"public, max-age=31536000, immutable".to_vec()
+        } else {
+            b"no-cache".to_vec()
+        },
+    ));
+    response
+}
+
+pub fn csv(body: String) -> Response {
+    let mut response = with_content_type(
+        Response::new(200, "OK", body.into_bytes()),
+        "text/csv; charset=utf-8",
+    );
+    response
+        .headers_mut()
+        .push(("Access-Control-Allow-Origin".into(), b"*".to_vec()));
+    response
+}
+
+fn with_content_type(mut response: Response, content_type: &str) -> Response {
+    response
+        .headers_mut()
+        .push(("Content-Type".into(), content_type.as_bytes().to_vec()));
+    response
+}
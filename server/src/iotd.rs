@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::mem;
+
+use protocol::ImageId;
+use rand::{thread_rng, Rng};
+
+use crate::store::{self, StoreError};
+
+#[derive(Serialize)]
+pub struct Pick<'a> {
+    pub date: &'a str,
+    pub id: &'a ImageId,
+}
+
+/// Picks one find per day uniformly at random, by reservoir sampling over
+/// the finds of the current day.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ImageOfTheDay {
+    history: BTreeMap<String, ImageId>,
+    day: String,
+    candidate: Option<ImageId>,
+    finds_today: u64,
+}
+
+impl ImageOfTheDay {
+    pub fn load(path: &str) -> Result<ImageOfTheDay, StoreError> {
+        store::load(path)
+    }
+
+    /// Offers a find to today's draw and returns whether the state changed
+    /// in a way worth saving.
+    pub fn record_find(&mut self, today: &str, id: ImageId) -> bool {
+        let rolled_over = self.roll_over(today);
+
+        self.finds_today += 1;
+
+        if thread_rng().gen_range(0, self.finds_today) == 0 {
+            self.candidate = Some(id);
+            true
+        } else {
+            rolled_over
+        }
+    }
+
+    /// Settles the pick of the previous day once the date changes.
+    pub fn roll_over(&mut self, today: &str) -> bool {
+        if self.day == today {
+            return false;
+        }
+
+        let day = mem::replace(&mut self.day, today.to_owned());
+
+        if let Some(candidate) = self.candidate.take() {
+            self.history.insert(day, candidate);
+        }
+
+        self.finds_today = 0;
+
+        true
+    }
+
+    /// Forgets the picks of the days before `date`.
+    pub fn purge_before(&mut self, date: &str) -> bool {
+        let days = self.history.len();
+        self.history = self.history.split_off(date);
+
+        self.history.len() != days
+    }
+
+    pub fn latest(&self) -> Option<Pick<'_>> {
+        self.history
+            .iter()
+            .next_back()
+            .map(|(date, id)| Pick { date, id })
+    }
+
+    pub fn on(&self, date: &str) -> Option<Pick<'_>> {
+        self.history
+            .get_key_value(date)
+            .map(|(date, id)| Pick { date, id })
+    }
+
+    /// Every settled pick, newest first.
+    pub fn history(&self) -> Vec<Pick<'_>> {
+        self.history
+            .iter()
+            .rev()
+            .map(|(date, id)| Pick { date, id })
+            .collect()
+    }
+}
@@ -1,187 +1,1907 @@
-use std::collections::HashMap;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::fs;
+use std::iter;
+use std::process;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use ws::util::Token;
 use ws::{
-    listen, CloseCode, Error as WSError, Handler, Handshake, Message, Request, Response, Result,
-    Sender,
+    Builder, CloseCode, Error as WSError, Handler, Handshake, Message, Request, Response, Result,
+    Sender, Settings,
 };
 
 #[macro_use]
 extern crate serde_derive;
 
-#[derive(Serialize, Deserialize)]
-enum WsMessageType {
-    UsersBruteforcing,
-    UsersWatching,
-    Start,
-    Stop,
-    New,
+mod access;
+mod accounts;
+mod activity;
+mod archive;
+mod assets;
+mod auth;
+mod classify;
+mod dashboard;
+mod dates;
+mod dedup;
+mod events;
+mod fairness;
+mod federation;
+mod geo;
+mod history;
+mod http;
+mod iotd;
+mod metrics;
+mod moderation;
+mod outbound;
+mod policy;
+mod profiles;
+mod quarantine;
+mod replay;
+mod resume;
+mod runtime_config;
+mod screen;
+mod scripts;
+mod spam;
+mod stats;
+mod store;
+mod stream;
+mod swarm;
+mod timeline;
+mod verify;
+mod writer;
+
+use access::{AccessPolicy, Role};
+use accounts::Accounts;
+use activity::Activity;
+use archive::Archive;
+use auth::{Auth, Identity};
+use classify::Classifier;
+use dedup::RecentIds;
+use events::{Event, EventBus};
+use fairness::FairQueue;
+use federation::Federation;
+use geo::Regions;
+use history::History;
+use iotd::ImageOfTheDay;
+use metrics::Metrics;
+use moderation::{Bans, IpStats, Rejections};
+use outbound::Outbound;
+use policy::{Find, Outcome, Policies};
+use profiles::{render_profile, Profiles};
+use protocol::{
+    is_valid_pow, Counts, FindSource, ImageId, RegionCounts, RuntimeConfig, WsMessage,
+    WsMessageType, RATE_REPORT_SECS,
+};
+use quarantine::{Quarantine, QuarantinedFind};
+use replay::Recorder;
+use resume::Suspended;
+use screen::{Match, Screen};
+use spam::SpamFilter;
+use stats::Stats;
+use store::StoreError;
+use stream::{StreamEvent, Streams};
+use swarm::SwarmThrottle;
+use timeline::Timeline;
+use writer::Writer;
+
+const CLIENT_ID_MIN_LEN: usize = 8;
+const CLIENT_ID_MAX_LEN: usize = 64;
+const CHALLENGE_LEN: usize = 16;
+const CHALLENGE_ROTATION_MS: u64 = 10 * 60 * 1000;
+const ROTATE_CHALLENGE: Token = Token(1);
+const DEFAULT_POW_DIFFICULTY: u32 = 10;
+const RATE_TOLERANCE_MS: u64 = 5;
+/// Reported probe rates are capped at this, no client gets near it.
+const MAX_REPORTED_RATE: u64 = 10_000;
+const DEFAULT_RANDOM_IMAGES: usize = 20;
+const MAX_RANDOM_IMAGES: usize = 100;
+const MAX_PENDING_VERIFICATIONS: u64 = 32;
+const IOTD_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+const COUNTS_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_COUNTS_GRACE_SECS: u64 = 5;
+const DEFAULT_MAX_CONNECTIONS: usize = 100;
+const MAX_SEND_FAILURES: u32 = 10;
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:8002";
+const DEFAULT_DATA_DIR: &str = "data";
+
+struct User {
+    is_bruteforcing: bool,
+    is_over_rate: bool,
+    client_id: Option<String>,
+    resume_token: String,
+    region: Option<&'static str>,
+    ip: Option<String>,
+}
+
+struct Submission {
+    id: ImageId,
+    client_id: Option<String>,
+    client_msg_id: Option<u64>,
+    received_at: Instant,
+    source: Option<FindSource>,
+    /// The federated instance it was found on, if not this one.
+    origin: Option<String>,
+    /// The origin's signature, passed on to peers.
+    signature: Option<String>,
+    /// When the origin found it, in milliseconds since the epoch, which its
+    /// signature covers.
+    found_at: Option<u64>,
+    /// Added by content policies.
+    labels: Vec<String>,
+}
+
+impl Submission {
+    fn ack(&self, shared: &Shared, out: &Sender) {
+        if let Some(client_msg_id) = self.client_msg_id {
+            shared.send(
+                out,
+                &WsMessage {
+                    text: Some(self.id.to_string()),
+                    client_msg_id: Some(client_msg_id),
+                    ..WsMessage::new(WsMessageType::Ack)
+                },
+            );
+        }
+    }
+
+    fn reject(&self, shared: &Shared, out: &Sender, reason: &str) {
+        shared.send(
+            out,
+            &WsMessage {
+                text: Some(self.id.to_string()),
+                reason: Some(reason.to_owned()),
+                client_msg_id: self.client_msg_id,
+                ..WsMessage::new(WsMessageType::Rejected)
+            },
+        );
+    }
+}
+
+/// State shared by every connection and by the verification threads.
+pub struct Shared {
+    users: Mutex<HashMap<u32, User>>,
+    stats: Mutex<Stats>,
+    stats_path: Option<String>,
+    profiles: Mutex<Profiles>,
+    profiles_path: Option<String>,
+    accounts: Mutex<Accounts>,
+    accounts_path: Option<String>,
+    iotd: Mutex<ImageOfTheDay>,
+    iotd_path: Option<String>,
+    archive: Mutex<Archive>,
+    archive_path: Option<String>,
+    timeline: Mutex<Timeline>,
+    timeline_path: Option<String>,
+    quarantine: Mutex<Quarantine>,
+    quarantine_path: Option<String>,
+    bans: Mutex<Bans>,
+    bans_path: Option<String>,
+    rejections: Mutex<Rejections>,
+    spam: Mutex<SpamFilter>,
+    history: Mutex<History>,
+    /// `New` messages waiting for their turn, with when their find was
+    /// received.
+    fair_queue: Mutex<FairQueue<(WsMessage, Instant)>>,
+    broadcast_interval: Duration,
+    recent_ids: Mutex<RecentIds>,
+    metrics: Mutex<Metrics>,
+    outbound: Mutex<Outbound>,
+    send_failures: Mutex<HashMap<u32, u32>>,
+    suspended: Mutex<Suspended<User>>,
+    counts_grace: Duration,
+    regions: Option<Regions>,
+    activity: Mutex<Activity>,
+    swarm_throttle: Mutex<SwarmThrottle>,
+    federation: Federation,
+    streams: Streams,
+    recorder: Option<Mutex<Recorder>>,
+    runtime_config: Mutex<RuntimeConfig>,
+    auth: Option<Auth>,
+    admin_token: Option<String>,
+    access: AccessPolicy,
+    pow_difficulty: u32,
+    verify_finds: bool,
+    classifier: Option<Classifier>,
+    screen: Option<Screen>,
+    policies: Policies,
+    events: EventBus,
+    writer: Writer,
+    legacy_counts: bool,
+}
+
+impl Shared {
+    fn save_stats(&self, stats: &Stats) {
+        if let Some(path) = &self.stats_path {
+            self.writer.save(path, stats);
+        }
+    }
+
+    fn save_profiles(&self, profiles: &Profiles) {
+        if let Some(path) = &self.profiles_path {
+            self.writer.save(path, profiles);
+        }
+    }
+
+    fn save_accounts(&self, accounts: &Accounts) {
+        if let Some(path) = &self.accounts_path {
+            self.writer.save(path, accounts);
+        }
+    }
+
+    fn save_iotd(&self, iotd: &ImageOfTheDay) {
+        if let Some(path) = &self.iotd_path {
+            self.writer.save(path, iotd);
+        }
+    }
+
+    fn save_timeline(&self, timeline: &Timeline) {
+        if let Some(path) = &self.timeline_path {
+            self.writer.save(path, timeline);
+        }
+    }
+
+    fn save_quarantine(&self, quarantine: &Quarantine) {
+        if let Some(path) = &self.quarantine_path {
+            self.writer.save(path, quarantine);
+        }
+    }
+
+    fn save_bans(&self, bans: &Bans) {
+        if let Some(path) = &self.bans_path {
+            self.writer.save(path, bans);
+        }
+    }
+
+    /// Holds a suspicious find back for review. The submitter gets an
+    /// acknowledgement as if it went through.
+    fn quarantine_find(&self, out: &Sender, submission: Submission, reason: &str) {
+        submission.ack(self, out);
+        self.publish(Event::FindQuarantined {
+            id: submission.id,
+            client_id: submission.client_id.clone(),
+            reason: reason.to_owned(),
+        });
+
+        let mut quarantine = self.quarantine.lock().unwrap();
+        quarantine.insert(QuarantinedFind {
+            id: submission.id,
+            client_id: submission.client_id,
+            reason: reason.to_owned(),
+            at: dates::now_secs(),
+            source: submission.source,
+            origin: submission.origin,
+            signature: submission.signature,
+            found_at: submission.found_at,
+        });
+        self.save_quarantine(&quarantine);
+    }
+
+    /// Drops the finds, telemetry and quarantined submissions older than
+    /// `retention_days`.
+    fn purge(&self, retention_days: u64) {
+        let cutoff = dates::now_secs().saturating_sub(retention_days * 86_400);
+        let cutoff_date = dates::date(cutoff);
+
+        let mut archive = self.archive.lock().unwrap();
+
+        if archive.purge_before(&cutoff_date) {
+            if let Some(path) = &self.archive_path {
+                self.writer.replace(path, archive.to_jsonl());
+            }
+        }
+
+        drop(archive);
+
+        let mut iotd = self.iotd.lock().unwrap();
+
+        if iotd.purge_before(&cutoff_date) {
+            self.save_iotd(&iotd);
+        }
+
+        drop(iotd);
+
+        let mut timeline = self.timeline.lock().unwrap();
+
+        if timeline.purge_before(cutoff) {
+            self.save_timeline(&timeline);
+        }
+
+        drop(timeline);
+
+        let mut quarantine = self.quarantine.lock().unwrap();
+
+        if quarantine.purge_before(cutoff) {
+            self.save_quarantine(&quarantine);
+        }
+    }
+
+    /// Deletes the profile, settings and quarantined submissions of
+    /// `client_id`, returning whether there was anything to delete.
+    fn delete_client(&self, client_id: &str) -> bool {
+        let mut profiles = self.profiles.lock().unwrap();
+        let had_profile = profiles.remove(client_id);
+
+        if had_profile {
+            self.save_profiles(&profiles);
+        }
+
+        drop(profiles);
+
+        let mut accounts = self.accounts.lock().unwrap();
+        let had_account = accounts.remove(client_id);
+
+        if had_account {
+            self.save_accounts(&accounts);
+        }
+
+        drop(accounts);
+
+        let mut quarantine = self.quarantine.lock().unwrap();
+        let had_quarantined = quarantine.remove_client(client_id);
+
+        if had_quarantined {
+            self.save_quarantine(&quarantine);
+        }
+
+        had_profile || had_account || had_quarantined
+    }
+
+    fn record_users(&self) {
+        let users = self.users.lock().unwrap().len() as u64;
+        let mut timeline = self.timeline.lock().unwrap();
+
+        if timeline.record_users(dates::now_secs(), users) {
+            self.save_timeline(&timeline);
+        }
+    }
+
+    /// The users connected, and those that dropped less than `counts_grace`
+    /// ago, so the numbers don't dip every time one reconnects.
+    fn counts(&self) -> Counts {
+        let users = self.users.lock().unwrap();
+        let suspended = self.suspended.lock().unwrap();
+        let pending = suspended
+            .recent(Instant::now(), self.counts_grace)
+            .collect::<Vec<_>>();
+
+        Counts {
+            watching: (users.len() + pending.len()) as u64,
+            bruteforcing: users
+                .values()
+                .chain(pending)
+                .filter(|user| user.is_bruteforcing)
+                .count() as u64,
+        }
+    }
+
+    /// Connected users by region, if regions are resolved.
+    fn region_counts(&self) -> Option<RegionCounts> {
+        self.regions.as_ref()?;
+
+        let users = self.users.lock().unwrap();
+
+        Some(geo::breakdown(
+            users.values().filter_map(|user| user.region),
+        ))
+    }
+
+    fn send_activity(&self, out: &Sender, text: String) {
+        let ws_message = WsMessage {
+            text: Some(text),
+            ..WsMessage::new(WsMessageType::Activity)
+        };
+
+        if let Ok(text) = serde_json::to_string(&ws_message) {
+            self.deliver(out, text, true);
+        }
+    }
+
+    /// Tells clients to probe slower for `backoff_secs`, or that they can
+    /// resume their pace when it is 0.
+    fn send_throttled(&self, out: &Sender, backoff_secs: u64, broadcast: bool) {
+        let ws_message = WsMessage {
+            number: Some(backoff_secs),
+            text: Some(swarm::REASON.to_owned()).filter(|_| backoff_secs > 0),
+            ..WsMessage::new(WsMessageType::Throttled)
+        };
+
+        if let Ok(text) = serde_json::to_string(&ws_message) {
+            self.deliver(out, text, broadcast);
+        }
+    }
+
+    fn send_regions(&self, out: &Sender, region_counts: &RegionCounts, broadcast: bool) {
+        let ws_message = WsMessage {
+            text: serde_json::to_string(region_counts).ok(),
+            ..WsMessage::new(WsMessageType::Regions)
+        };
+
+        if let Ok(text) = serde_json::to_string(&ws_message) {
+            self.deliver(out, text, broadcast);
+        }
+    }
+
+    /// Sends `counts` through `out`, to every client if `broadcast`, along
+    /// with the messages older clients expect if `LEGACY_COUNTS` is set.
+    fn send_counts(&self, out: &Sender, counts: Counts, broadcast: bool) {
+        let mut messages = vec![WsMessage {
+            text: serde_json::to_string(&counts).ok(),
+            ..WsMessage::new(WsMessageType::Counts)
+        }];
+
+        if self.legacy_counts {
+            messages.push(WsMessage {
+                number: Some(counts.watching),
+                ..WsMessage::new(WsMessageType::UsersWatching)
+            });
+            messages.push(WsMessage {
+                number: Some(counts.bruteforcing),
+                ..WsMessage::new(WsMessageType::UsersBruteforcing)
+            });
+        }
+
+        for ws_message in messages {
+            if let Ok(text) = serde_json::to_string(&ws_message) {
+                self.deliver(out, text, broadcast);
+            }
+        }
+    }
+
+    fn send(&self, out: &Sender, ws_message: &WsMessage) {
+        if let Ok(text) = serde_json::to_string(ws_message) {
+            self.deliver(out, text, false);
+        }
+    }
+
+    /// Queues `text` for the client of `out`, or for every client if
+    /// `broadcast`, counting what ws-rs refuses. A client whose messages
+    /// keep getting refused is dropped rather than left out of sync.
+    fn deliver(&self, out: &Sender, text: String, broadcast: bool) {
+        let message = Message::text(text);
+        let result = if broadcast {
+            out.broadcast(message)
+        } else {
+            out.send(message)
+        };
+
+        let err = match result {
+            Ok(()) => return,
+            Err(err) => err,
+        };
+
+        if broadcast {
+            self.metrics.lock().unwrap().broadcast_errors += 1;
+            eprintln!("could not broadcast message: {}", err);
+            return;
+        }
+
+        self.metrics.lock().unwrap().send_errors += 1;
+
+        let connection_id = out.connection_id();
+        let failures = {
+            let mut send_failures = self.send_failures.lock().unwrap();
+            let failures = send_failures.entry(connection_id).or_insert(0);
+            *failures += 1;
+            *failures
+        };
+
+        eprintln!(
+            "could not send message to connection {}: {}",
+            connection_id, err
+        );
+
+        if failures >= MAX_SEND_FAILURES {
+            self.metrics.lock().unwrap().send_failure_disconnects += 1;
+            self.remove_user(connection_id);
+
+            if let Err(err) = out.close(CloseCode::Away) {
+                eprintln!("could not close connection {}: {}", connection_id, err);
+            }
+        }
+    }
+
+    /// Removes a user, keeping its session for a while in case it resumes
+    /// it by reconnecting.
+    fn remove_user(&self, connection_id: u32) {
+        let user = self.users.lock().unwrap().remove(&connection_id);
+
+        if let Some(user) = user {
+            let token = user.resume_token.clone();
+            self.suspended
+                .lock()
+                .unwrap()
+                .suspend(token, user, Instant::now());
+            self.publish(Event::ClientLeft { connection_id });
+        }
+
+        self.send_failures.lock().unwrap().remove(&connection_id);
+        self.record_users();
+    }
+
+    /// Broadcasts `ws_message` with a sequence number, holding the history
+    /// lock so every client gets broadcasts in sequence order.
+    fn broadcast(&self, out: &Sender, ws_message: WsMessage) {
+        let text = ws_message.text.as_deref().unwrap_or_default();
+
+        match ws_message.msg_type {
+            WsMessageType::New => self.streams.publish(&StreamEvent::Find {
+                id: text,
+                at: ws_message.at,
+            }),
+            WsMessageType::Milestone => self.streams.publish(&StreamEvent::Milestone { text }),
+            _ => {}
+        }
+
+        let mut history = self.history.lock().unwrap();
+
+        if let Some(text) = history.push(ws_message) {
+            if let Some(recorder) = &self.recorder {
+                recorder.lock().unwrap().record(dates::now_ms(), &text);
+            }
+
+            self.deliver(out, text, true);
+        }
+    }
+
+    fn broadcast_milestone(&self, out: &Sender, milestone: Option<String>) {
+        if let Some(milestone) = milestone {
+            self.activity.lock().unwrap().record_milestone(&milestone);
+            self.broadcast(
+                out,
+                WsMessage {
+                    text: Some(milestone),
+                    ..WsMessage::new(WsMessageType::Milestone)
+                },
+            );
+        }
+    }
+
+    fn send_runtime_config(&self, out: &Sender, broadcast: bool) {
+        if let Ok(text) = serde_json::to_string(&*self.runtime_config.lock().unwrap()) {
+            let ws_message = WsMessage {
+                text: Some(text),
+                ..WsMessage::new(WsMessageType::ConfigUpdate)
+            };
+
+            if broadcast {
+                if let Ok(text) = serde_json::to_string(&ws_message) {
+                    self.deliver(out, text, true);
+                }
+            } else {
+                self.send(out, &ws_message);
+            }
+        }
+    }
+
+    fn set_runtime_config(&self, out: &Sender, runtime_config: RuntimeConfig) {
+        let mut current = self.runtime_config.lock().unwrap();
+
+        if *current != runtime_config {
+            let was_disabled = current.bruteforce_disabled;
+            *current = runtime_config;
+            drop(current);
+
+            self.send_runtime_config(out, true);
+
+            if !was_disabled {
+                self.send_bruteforce_disabled(out, true);
+            }
+        }
+    }
+
+    /// Tells clients to stop probing, if the operator disabled bruteforcing.
+    fn send_bruteforce_disabled(&self, out: &Sender, broadcast: bool) {
+        let runtime_config = self.runtime_config.lock().unwrap();
+
+        if !runtime_config.bruteforce_disabled {
+            return;
+        }
+
+        if let Ok(text) = serde_json::to_string(&WsMessage {
+            text: runtime_config.bruteforce_disabled_reason.clone(),
+            ..WsMessage::new(WsMessageType::BruteforceDisabled)
+        }) {
+            self.deliver(out, text, broadcast);
+        }
+    }
+
+    fn is_bruteforce_disabled(&self) -> bool {
+        self.runtime_config.lock().unwrap().bruteforce_disabled
+    }
+
+    /// Why no find is taken right now, if the operator stopped them.
+    fn pause_reason(&self) -> Option<&'static str> {
+        if self.is_bruteforce_disabled() {
+            Some("bruteforcing is disabled by the operator")
+        } else if self.is_quiet_hours() {
+            Some("bruteforcing is paused for quiet hours")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the operator's quiet hours, in UTC, are in effect right now.
+    fn is_quiet_hours(&self) -> bool {
+        self.runtime_config
+            .lock()
+            .unwrap()
+            .quiet_hours
+            .map_or(false, |quiet_hours| {
+                quiet_hours.contains((dates::now_secs() % 86_400) as u32)
+            })
+    }
+
+    /// Replays the broadcasts a client missed, announced by a `Resync`
+    /// telling where the replay starts.
+    fn resync(&self, out: &Sender, from_seq: u64, role: Role) {
+        let max = if role >= self.access.history {
+            usize::MAX
+        } else {
+            self.access.public_history
+        };
+        let history = self.history.lock().unwrap();
+        let (start, messages) = history.replay(from_seq, max);
+
+        self.send(
+            out,
+            &WsMessage {
+                number: Some(start),
+                ..WsMessage::new(WsMessageType::Resync)
+            },
+        );
+
+        for text in messages {
+            self.deliver(out, text.to_owned(), false);
+        }
+    }
+
+    /// Acknowledges a submission, broadcasting and crediting it unless the
+    /// same image was submitted recently.
+    fn submit_find(self: Arc<Self>, out: &Sender, submission: Submission) {
+        if !self.recent_ids.lock().unwrap().insert(submission.id) {
+            submission.ack(&self, out);
+        } else if self.verify_finds
+            || self.classifier.is_some()
+            || self.screen.is_some()
+            || !self.policies.is_empty()
+        {
+            self.verify_find(out.clone(), submission);
+        } else {
+            self.admit_find(out, submission);
+        }
+    }
+
+    fn record_requests(&self, requests: u64) {
+        self.swarm_throttle
+            .lock()
+            .unwrap()
+            .record_requests(requests);
+
+        let mut timeline = self.timeline.lock().unwrap();
+
+        if timeline.record_requests(dates::now_secs(), requests) {
+            self.save_timeline(&timeline);
+        }
+    }
+
+    fn publish(&self, event: Event) {
+        self.events.publish(self, &event);
+    }
+
+    fn reject_submission(&self, out: &Sender, submission: &Submission, reason: &str) {
+        self.publish(Event::FindRejected {
+            id: submission.id,
+            client_id: submission.client_id.clone(),
+            ip: self.ip(out),
+            reason: reason.to_owned(),
+        });
+
+        submission.reject(self, out, reason);
+    }
+
+    fn ip(&self, out: &Sender) -> Option<String> {
+        self.users
+            .lock()
+            .unwrap()
+            .get(&out.connection_id())
+            .and_then(|user| user.ip.clone())
+    }
+
+    fn reject_find(&self, out: &Sender, submission: &Submission, reason: &str) {
+        self.recent_ids.lock().unwrap().remove(submission.id);
+        self.reject_submission(out, submission, reason);
+    }
+
+    /// Runs the content policies on a find that passed every check, then
+    /// accepts, quarantines or refuses it as they decide.
+    fn admit_find(&self, out: &Sender, mut submission: Submission) {
+        if self.policies.is_empty() {
+            return self.accept_find(out, submission);
+        }
+
+        let find = Find {
+            id: submission.id,
+            client_id: submission.client_id.clone(),
+            source: submission.source,
+            origin: submission.origin.clone(),
+            labels: Vec::new(),
+        };
+
+        match self.policies.apply(find) {
+            Outcome::Accept(find) => {
+                // The origin did not sign another image, it becomes a find
+                // of this instance.
+                if find.id != submission.id {
+                    submission.origin = None;
+                    submission.signature = None;
+                }
+
+                submission.id = find.id;
+                submission.source = find.source;
+                submission.labels = find.labels;
+                self.accept_find(out, submission);
+            }
+            Outcome::Quarantine(reason) => self.quarantine_find(out, submission, &reason),
+            Outcome::Block(reason) => self.reject_find(out, &submission, &reason),
+        }
+    }
+
+    /// Broadcasts an accepted find and credits it to whoever submitted it.
+    fn accept_find(&self, out: &Sender, submission: Submission) {
+        submission.ack(self, out);
+
+        let Submission {
+            id,
+            client_id,
+            received_at,
+            source,
+            origin,
+            signature,
+            found_at,
+            labels,
+            ..
+        } = submission;
+        let elapsed = received_at.elapsed();
+        let at =
+            found_at.unwrap_or_else(|| dates::now_ms().saturating_sub(elapsed.as_millis() as u64));
+        let contributor_key = client_id
+            .clone()
+            .or_else(|| origin.as_ref().map(|origin| format!("@{}", origin)))
+            .unwrap_or_else(|| format!("#{}", out.connection_id()));
+
+        self.publish(Event::FindValidated {
+            id,
+            client_id,
+            at,
+            source,
+            origin: origin.clone(),
+            signature,
+        });
+
+        let dropped = self.fair_queue.lock().unwrap().push(
+            contributor_key,
+            (
+                WsMessage {
+                    text: Some(id.to_string()),
+                    at: Some(at),
+                    source,
+                    origin,
+                    labels: Some(labels).filter(|labels| !labels.is_empty()),
+                    ..WsMessage::new(WsMessageType::New)
+                },
+                received_at,
+            ),
+        );
+
+        if dropped.is_some() {
+            self.metrics.lock().unwrap().fair_queue_drops += 1;
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        let milestone = stats.record_find();
+
+        if milestone.is_some() || stats.should_save() {
+            self.save_stats(&stats);
+        }
+
+        drop(stats);
+        self.broadcast_milestone(out, milestone);
+    }
+
+    /// Asks imgur whether `id` exists, within the limits of `outbound`.
+    fn probe(&self, id: &str) -> std::result::Result<Option<String>, String> {
+        self.request_imgur(id, verify::probe)
+    }
+
+    /// Makes `request` about `id` to imgur within the limits of `outbound`,
+    /// returning the content hash it got.
+    fn request_imgur(
+        &self,
+        id: &str,
+        request: impl FnOnce(&str) -> verify::Probe,
+    ) -> std::result::Result<Option<String>, String> {
+        let wait = self
+            .outbound
+            .lock()
+            .unwrap()
+            .permit(id, Instant::now())
+            .map_err(|reason| {
+                self.metrics.lock().unwrap().outbound_refused += 1;
+                reason
+            })?;
+
+        thread::sleep(wait);
+        let probe = request(id);
+
+        if let verify::Probe::Throttled(_) = probe {
+            self.metrics.lock().unwrap().imgur_throttles += 1;
+        }
+
+        self.outbound
+            .lock()
+            .unwrap()
+            .record(id, probe, Instant::now())
+    }
+
+    /// Asks the NSFW classifier whether `id` should be reviewed first,
+    /// letting it through when the classifier cannot be reached.
+    fn classify(&self, id: ImageId) -> Option<String> {
+        match self.classifier.as_ref()?.check(id.as_str()) {
+            Ok(reason) => reason,
+            Err(err) => {
+                eprintln!("could not classify {}: {}", id, err);
+                self.metrics.lock().unwrap().classification_failures += 1;
+                None
+            }
+        }
+    }
+
+    /// Looks a find up in the hash lists, returning whether it is clear.
+    /// A match is reported and its submitter banned and, when `submitter`
+    /// is the connection it came from, refused and disconnected. It is
+    /// stored nowhere, not even among the rejections.
+    fn screen_find(
+        &self,
+        submitter: Option<&Sender>,
+        submission: &Submission,
+        etag: Option<&str>,
+    ) -> std::result::Result<bool, String> {
+        let screen = match &self.screen {
+            Some(screen) => screen,
+            None => return Ok(true),
+        };
+        let id = submission.id;
+        let sha256 = || {
+            self.request_imgur(id.as_str(), screen::download_sha256)?
+                .ok_or_else(|| format!("could not download {} to screen it", id))
+        };
+        let (list, hash) = match screen.check(etag, sha256)? {
+            Some(found) => found,
+            None => return Ok(true),
+        };
+        // Federated finds come from a peer, not from whoever found them.
+        let ip = submitter
+            .filter(|_| submission.origin.is_none())
+            .and_then(|out| self.ip(out));
+
+        screen.report(&Match {
+            at: dates::now_secs(),
+            id: submission.id.to_string(),
+            list: list.clone(),
+            hash,
+            client_id: submission.client_id.clone(),
+            ip: ip.clone(),
+            origin: submission.origin.clone(),
+        });
+        self.publish(Event::ImageBlocked {
+            id: submission.id,
+            client_id: submission.client_id.clone(),
+            ip: ip.clone(),
+            origin: submission.origin.clone(),
+            list,
+        });
+
+        if submission.origin.is_none() {
+            let mut bans = self.bans.lock().unwrap();
+            let changed = bans.set(ip.clone(), submission.client_id.clone(), true);
+
+            if changed {
+                self.save_bans(&bans);
+            }
+
+            drop(bans);
+
+            if changed {
+                self.publish(Event::ClientBanned {
+                    ip,
+                    client_id: submission.client_id.clone(),
+                });
+            }
+
+            if let Some(out) = submitter {
+                submission.reject(self, out, "refused");
+                let _ = out.close(CloseCode::Policy);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Probes `id` with `VERIFY_FINDS`, screens it against the hash lists,
+    /// classifies it with the NSFW classifier and runs the content policies
+    /// on a separate thread, and only accepts it once imgur confirms it
+    /// exists and nothing flags it.
+    fn verify_find(self: Arc<Self>, out: Sender, submission: Submission) {
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+
+            if metrics.verifications_pending >= MAX_PENDING_VERIFICATIONS {
+                drop(metrics);
+                self.reject_find(&out, &submission, "server is busy, try again later");
+                return;
+            }
+
+            metrics.verifications_pending += 1;
+        }
+
+        thread::spawn(move || {
+            let started_at = Instant::now();
+            let result = if self.verify_finds {
+                self.probe(submission.id.as_str())
+            } else {
+                Ok(None)
+            };
+            let result = result.and_then(|content_hash| {
+                if !self.screen_find(Some(&out), &submission, content_hash.as_deref())? {
+                    return Ok(None);
+                }
+
+                Ok(Some(
+                    content_hash
+                        .and_then(|hash| self.spam.lock().unwrap().check_content(&hash))
+                        .map(str::to_owned)
+                        .or_else(|| self.classify(submission.id)),
+                ))
+            });
+
+            self.metrics
+                .lock()
+                .unwrap()
+                .record_verification(started_at.elapsed(), &result);
+
+            match result {
+                Ok(Some(Some(reason))) => self.quarantine_find(&out, submission, &reason),
+                Ok(Some(None)) => self.admit_find(&out, submission),
+                // Screened out, already dealt with.
+                Ok(None) => {}
+                Err(reason) => self.reject_find(&out, &submission, &reason),
+            }
+        });
+    }
+}
+
+struct Server {
+    shared: Arc<Shared>,
+    identity: Option<Identity>,
+    role: Role,
+    challenge: String,
+    previous_challenge: Option<String>,
+    /// Probes in this client's last `Rate`, the most it can report as
+    /// throttled.
+    reported_requests: u64,
+    resume_token: Option<String>,
+    /// The name of the federated instance on the other end, once it sent
+    /// the federation token.
+    peer: Option<String>,
+    out: Sender,
 }
 
-#[derive(Serialize, Deserialize)]
-struct WsMessage {
-    msg_type: WsMessageType,
-    text: Option<String>,
-    number: Option<u64>,
-}
+impl Server {
+    fn send(&self, ws_message: &WsMessage) {
+        self.shared.send(&self.out, ws_message);
+    }
+
+    fn send_error(&self, reason: &str) {
+        self.send(&WsMessage {
+            text: Some(reason.to_owned()),
+            ..WsMessage::new(WsMessageType::Error)
+        });
+    }
+
+    fn client_id(&self) -> Option<String> {
+        self.shared
+            .users
+            .lock()
+            .unwrap()
+            .get(&self.out.connection_id())
+            .and_then(|user| user.client_id.clone())
+    }
+
+    fn is_banned(&self) -> bool {
+        let users = self.shared.users.lock().unwrap();
+        let user = users.get(&self.out.connection_id());
+
+        self.shared.bans.lock().unwrap().is_banned(
+            user.and_then(|user| user.ip.as_deref()),
+            user.and_then(|user| user.client_id.as_deref()),
+        )
+    }
+
+    /// Who to hold accountable for a submission: its connection, its IP
+    /// and the client ID it picked, if any.
+    fn spam_sources(&self) -> Vec<String> {
+        let users = self.shared.users.lock().unwrap();
+        let user = users.get(&self.out.connection_id());
+        let mut sources = vec![format!("connection:{}", self.out.connection_id())];
+
+        sources.extend(
+            user.and_then(|user| user.ip.as_ref())
+                .map(|ip| format!("ip:{}", ip)),
+        );
+        sources.extend(
+            user.and_then(|user| user.client_id.as_ref())
+                .map(|client_id| format!("client:{}", client_id)),
+        );
+
+        sources
+    }
+
+    /// What `req` was sent with, from an `Authorization: Bearer` header or
+    /// from a `key` or `session` query parameter.
+    fn request_credential(req: &Request, query: &str) -> Option<String> {
+        let bearer = req
+            .header("Authorization")
+            .and_then(|authorization| std::str::from_utf8(authorization).ok())
+            .and_then(|authorization| authorization.strip_prefix("Bearer "))
+            .map(str::to_owned);
+
+        bearer.or_else(|| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "key" || key == "session")
+                .map(|(_, credential)| credential.into_owned())
+        })
+    }
+
+    /// The role of whoever sent `req`. `ADMIN_TOKEN` and the tokens it
+    /// issued are admins.
+    fn request_role(&self, req: &Request, query: &str) -> Role {
+        let credential = match Self::request_credential(req, query) {
+            Some(credential) => credential,
+            None => return Role::Public,
+        };
 
-struct User {
-    is_bruteforcing: bool,
-}
+        if let Some(admin_token) = &self.shared.admin_token {
+            if *admin_token == credential || auth::verify_admin_token(admin_token, &credential) {
+                return Role::Admin;
+            }
+        }
 
-struct Server {
-    users: Arc<Mutex<HashMap<u32, User>>>,
-    out: Sender,
+        if let Some(role) = self.shared.access.key_role(&credential) {
+            role
+        } else if self
+            .shared
+            .auth
+            .as_ref()
+            .and_then(|auth| auth.verify_session(&credential))
+            .is_some()
+        {
+            Role::Contributor
+        } else {
+            Role::Public
+        }
+    }
+
+    fn is_over_rate(&self) -> bool {
+        self.shared
+            .users
+            .lock()
+            .unwrap()
+            .get(&self.out.connection_id())
+            .map_or(false, |user| user.is_over_rate)
+    }
+
+    /// Checks the probe rate a client reports against the minimum interval,
+    /// refusing its submissions while it goes faster.
+    fn check_rate(&self, requests_per_second: u64) {
+        let requests_per_second = requests_per_second.min(MAX_REPORTED_RATE);
+        let min_interval_ms = self.shared.runtime_config.lock().unwrap().min_interval_ms;
+        let is_over_rate = min_interval_ms != 0
+            && requests_per_second.saturating_mul(min_interval_ms)
+                > 1000 + RATE_TOLERANCE_MS * requests_per_second;
+
+        let was_over_rate = match self
+            .shared
+            .users
+            .lock()
+            .unwrap()
+            .get_mut(&self.out.connection_id())
+        {
+            Some(user) => std::mem::replace(&mut user.is_over_rate, is_over_rate),
+            None => return,
+        };
+
+        if is_over_rate && !was_over_rate {
+            self.shared.metrics.lock().unwrap().rate_violations += 1;
+            self.shared.send_runtime_config(&self.out, false);
+            self.send_error(&format!(
+                "you are probing faster than the minimum interval of {} ms, your finds are refused until you slow down",
+                min_interval_ms
+            ));
+        }
+    }
+
+    fn rotate_challenge(&mut self) {
+        let challenge = iter::repeat(())
+            .map(|()| thread_rng().sample(Alphanumeric))
+            .take(CHALLENGE_LEN)
+            .collect::<String>();
+
+        self.previous_challenge = Some(std::mem::replace(&mut self.challenge, challenge))
+            .filter(|previous| !previous.is_empty());
+
+        self.send(&WsMessage {
+            text: Some(self.challenge.clone()),
+            number: Some(u64::from(self.shared.pow_difficulty)),
+            ..WsMessage::new(WsMessageType::Challenge)
+        });
+    }
+
+    /// Accepts proofs of work made against the current or the previous
+    /// challenge, so finds in flight during a rotation are not lost.
+    fn check_pow(&self, id: &str, nonce: Option<u64>) -> bool {
+        let difficulty = self.shared.pow_difficulty;
+
+        if difficulty == 0 {
+            return true;
+        }
+
+        match nonce {
+            Some(nonce) => iter::once(&self.challenge)
+                .chain(self.previous_challenge.as_ref())
+                .any(|challenge| is_valid_pow(challenge, id, nonce, difficulty)),
+            None => false,
+        }
+    }
+
+    fn send_registered(&self, profiles: &Profiles, client_id: &str) {
+        if let Some(profile) = profiles.by_client(client_id) {
+            self.send(&WsMessage {
+                text: Some(profile.name.clone()),
+                number: Some(profile.finds),
+                url: profile.avatar.clone(),
+                ..WsMessage::new(WsMessageType::Registered)
+            });
+        }
+    }
 }
 
 impl Handler for Server {
     fn on_request(&mut self, req: &Request) -> Result<(Response)> {
-        match req.resource() {
-            "/ws" => Response::from_request(req),
-            _ => Ok(Response::new(404, "Not Found", b"404 - Not Found".to_vec())),
+        let mut resource = req.resource().splitn(2, '?');
+        let path = resource.next().unwrap_or_default();
+        let query = resource.next().unwrap_or_default();
+
+        if path == "/ws" {
+            if let Some(auth) = &self.shared.auth {
+                self.identity = url::form_urlencoded::parse(query.as_bytes())
+                    .find(|(key, _)| key == "session")
+                    .and_then(|(_, token)| auth.verify_session(&token));
+            }
+
+            self.role = self.request_role(req, query);
+            self.resume_token = url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "resume")
+                .map(|(_, token)| token.into_owned());
+
+            Response::from_request(req)
+        } else if self.request_role(req, query) < self.shared.access.required_role(path) {
+            Ok(http::unauthorized())
+        } else if path == "/api/auth" {
+            Ok(http::json(&serde_json::json!({
+                "providers": self.shared.auth.as_ref().map(|auth| auth.provider_names()).unwrap_or_default(),
+            })))
+        } else if path == "/api/iotd" {
+            let iotd = self.shared.iotd.lock().unwrap();
+            let pick = match url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "date")
+            {
+                Some((_, date)) => iotd.on(&date),
+                None => iotd.latest(),
+            };
+
+            match pick {
+                Some(pick) => Ok(http::json(&pick)),
+                None => Ok(http::not_found()),
+            }
+        } else if path == "/api/iotd/history" {
+            Ok(http::json(&self.shared.iotd.lock().unwrap().history()))
+        } else if path == "/api/archive" {
+            let date = url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "date")
+                .map(|(_, date)| date.into_owned())
+                .unwrap_or_else(dates::today);
+
+            match dates::day_start(&date).filter(|&start| dates::date(start) == date) {
+                Some(start) => Ok(http::json(&serde_json::json!({
+                    "date": date,
+                    "start": start,
+                    "finds": self.shared.archive.lock().unwrap().day(&date),
+                }))),
+                None => Ok(http::not_found()),
+            }
+        } else if path == "/stats" {
+            Ok(http::html(dashboard::render_stats(
+                &self.shared.timeline.lock().unwrap(),
+            )))
+        } else if path == "/api/stats/export" {
+            let params = url::form_urlencoded::parse(query.as_bytes()).collect::<HashMap<_, _>>();
+            let from = params.get("from").map(|date| dates::day_start(date));
+            let to = params
+                .get("to")
+                .map(|date| dates::day_start(date).map(|start| start + 86_400));
+
+            match (from, to) {
+                (Some(None), _) | (_, Some(None)) => Ok(http::not_found()),
+                (from, to) => {
+                    let timeline = self.shared.timeline.lock().unwrap();
+                    let buckets = timeline.range(
+                        from.flatten().unwrap_or(0),
+                        to.flatten().unwrap_or(u64::MAX),
+                    );
+
+                    if params.get("format").map_or(false, |format| format == "csv") {
+                        Ok(http::csv(timeline::to_csv(&buckets)))
+                    } else {
+                        Ok(http::json(&buckets))
+                    }
+                }
+            }
+        } else if path == "/api/images/random" {
+            let n = url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "n")
+                .and_then(|(_, n)| n.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_RANDOM_IMAGES)
+                .min(MAX_RANDOM_IMAGES);
+
+            Ok(http::json(&self.shared.archive.lock().unwrap().sample(n)))
+        } else if path == "/admin" {
+            Ok(http::html(dashboard::render_admin()))
+        } else if path == "/api/admin/connections" {
+            let users = self.shared.users.lock().unwrap();
+            let mut connections = users
+                .iter()
+                .map(|(connection_id, user)| {
+                    serde_json::json!({
+                        "connection_id": connection_id,
+                        "ip": user.ip,
+                        "region": user.region,
+                        "client_id": user.client_id,
+                        "is_bruteforcing": user.is_bruteforcing,
+                        "is_over_rate": user.is_over_rate,
+                    })
+                })
+                .collect::<Vec<_>>();
+            connections.sort_by_key(|connection| connection["connection_id"].as_u64());
+
+            Ok(http::json(&connections))
+        } else if path == "/api/admin/ips" {
+            let mut ips = BTreeMap::<_, IpStats>::new();
+
+            for user in self.shared.users.lock().unwrap().values() {
+                let stats = ips.entry(user.ip.clone()).or_default();
+                stats.connections += 1;
+                stats.bruteforcing += user.is_bruteforcing as u64;
+            }
+
+            for rejection in self.shared.rejections.lock().unwrap().iter() {
+                ips.entry(rejection.ip.clone()).or_default().rejections += 1;
+            }
+
+            Ok(http::json(
+                &ips.into_iter()
+                    .map(|(ip, stats)| IpStats { ip, ..stats })
+                    .collect::<Vec<_>>(),
+            ))
+        } else if path == "/api/admin/rejections" {
+            Ok(http::json(
+                &self
+                    .shared
+                    .rejections
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .collect::<Vec<_>>(),
+            ))
+        } else if path == "/api/admin/bans" {
+            Ok(http::json(&*self.shared.bans.lock().unwrap()))
+        } else if (path == "/api/admin/ban" || path == "/api/admin/unban") && req.method() == "POST"
+        {
+            let params = url::form_urlencoded::parse(query.as_bytes()).collect::<HashMap<_, _>>();
+            let mut bans = self.shared.bans.lock().unwrap();
+
+            let param = |key| {
+                params
+                    .get(key)
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string())
+            };
+
+            let banned = path == "/api/admin/ban";
+
+            if bans.set(param("ip"), param("client_id"), banned) {
+                self.shared.save_bans(&bans);
+                drop(bans);
+
+                if banned {
+                    self.shared.publish(Event::ClientBanned {
+                        ip: param("ip"),
+                        client_id: param("client_id"),
+                    });
+                }
+            }
+
+            Ok(http::json(&serde_json::json!({ "ok": true })))
+        } else if path == "/api/admin/announce" && req.method() == "POST" {
+            match url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == "text") {
+                Some((_, text)) if !text.is_empty() => {
+                    self.shared.broadcast(
+                        &self.out,
+                        WsMessage {
+                            text: Some(text.into_owned()),
+                            ..WsMessage::new(WsMessageType::Announce)
+                        },
+                    );
+
+                    Ok(http::json(&serde_json::json!({ "ok": true })))
+                }
+                _ => Ok(http::not_found()),
+            }
+        } else if path == "/api/admin/quarantine" {
+            Ok(http::json(&self.shared.quarantine.lock().unwrap().finds()))
+        } else if (path == "/api/admin/quarantine/release"
+            || path == "/api/admin/quarantine/discard")
+            && req.method() == "POST"
+        {
+            let mut quarantine = self.shared.quarantine.lock().unwrap();
+            let finds = url::form_urlencoded::parse(query.as_bytes())
+                .filter(|(key, _)| key == "id")
+                .filter_map(|(_, id)| ImageId::new(&id))
+                .filter_map(|id| quarantine.remove(id))
+                .collect::<Vec<_>>();
+            self.shared.save_quarantine(&quarantine);
+            drop(quarantine);
+
+            if finds.is_empty() {
+                return Ok(http::not_found());
+            }
+
+            let count = finds.len();
+
+            if path.ends_with("/release") {
+                let submissions = finds
+                    .into_iter()
+                    .map(|find| Submission {
+                        id: find.id,
+                        client_id: find.client_id,
+                        client_msg_id: None,
+                        received_at: Instant::now(),
+                        source: find.source,
+                        origin: find.origin,
+                        signature: find.signature,
+                        found_at: find.found_at,
+                        labels: Vec::new(),
+                    })
+                    .collect::<Vec<_>>();
+                let shared = self.shared.clone();
+                let out = self.out.clone();
+
+                // Finds quarantined before verification were never screened.
+                thread::spawn(move || {
+                    for submission in submissions {
+                        match shared.screen_find(None, &submission, None) {
+                            Ok(true) => shared.accept_find(&out, submission),
+                            Ok(false) => {}
+                            Err(err) => shared.quarantine_find(&out, submission, &err),
+                        }
+                    }
+                });
+            }
+
+            Ok(http::json(
+                &serde_json::json!({ "ok": true, "count": count }),
+            ))
+        } else if path == "/api/admin/token" && req.method() == "POST" {
+            // Only from `ADMIN_TOKEN` itself, so issued tokens cannot be
+            // renewed past their expiry.
+            match &self.shared.admin_token {
+                Some(admin_token)
+                    if Self::request_credential(req, query).as_ref() == Some(admin_token) =>
+                {
+                    let (token, exp) = auth::sign_admin_token(admin_token);
+
+                    Ok(http::json(
+                        &serde_json::json!({ "token": token, "exp": exp }),
+                    ))
+                }
+                Some(_) => Ok(http::unauthorized()),
+                None => Ok(http::not_found()),
+            }
+        } else if path == "/api/admin/delete" && req.method() == "POST" {
+            let client_id = url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "client_id")
+                .map(|(_, client_id)| client_id.into_owned())
+                .unwrap_or_default();
+
+            if self.shared.delete_client(&client_id) {
+                Ok(http::json(&serde_json::json!({ "ok": true })))
+            } else {
+                Ok(http::not_found())
+            }
+        } else if path == "/api/metrics" {
+            // Not holding the metrics lock while taking the history one, which
+            // broadcasting takes in the other order.
+            let history_evictions = self.shared.history.lock().unwrap().evictions();
+            let recent_ids_evictions = self.shared.recent_ids.lock().unwrap().evictions();
+            let mut metrics = self.shared.metrics.lock().unwrap();
+            metrics.history_evictions = history_evictions;
+            metrics.recent_ids_evictions = recent_ids_evictions;
+            metrics.writer = self.shared.writer.stats();
+
+            Ok(http::json(&*metrics))
+        } else if path.starts_with("/auth/") {
+            Ok(self
+                .shared
+                .auth
+                .as_ref()
+                .and_then(|auth| auth.handle(path, query))
+                .unwrap_or_else(http::not_found))
+        } else if let Some(name) = path.strip_prefix("/u/") {
+            match self.shared.profiles.lock().unwrap().by_name(name) {
+                Some(profile) => Ok(http::html(render_profile(profile))),
+                None => Ok(http::not_found()),
+            }
+        } else if let Some(name) = path.strip_prefix("/api/u/") {
+            match self.shared.profiles.lock().unwrap().by_name(name) {
+                Some(profile) => Ok(http::json(profile)),
+                None => Ok(http::not_found()),
+            }
+        } else if let Some((contents, content_type)) = assets::get(path) {
+            Ok(http::asset(path, contents, content_type))
+        } else {
+            Ok(http::not_found())
         }
     }
 
     fn on_open(&mut self, shake: Handshake) -> Result<()> {
-        self.users.lock().unwrap().insert(
-            self.out.connection_id(),
-            User {
+        self.shared.send_runtime_config(&self.out, false);
+        self.shared.send_bruteforce_disabled(&self.out, false);
+
+        let backoff_secs = self
+            .shared
+            .swarm_throttle
+            .lock()
+            .unwrap()
+            .secs_left(Instant::now());
+
+        if backoff_secs > 0 {
+            self.shared.send_throttled(&self.out, backoff_secs, false);
+        }
+
+        if self.shared.pow_difficulty != 0 {
+            self.rotate_challenge();
+            self.out.timeout(CHALLENGE_ROTATION_MS, ROTATE_CHALLENGE)?;
+        }
+
+        let resumed = self.resume_token.take().and_then(|token| {
+            self.shared
+                .suspended
+                .lock()
+                .unwrap()
+                .resume(&token, Instant::now())
+        });
+        let mut user = match resumed {
+            Some(user) => {
+                self.shared.metrics.lock().unwrap().sessions_resumed += 1;
+                user
+            }
+            None => User {
                 is_bruteforcing: false,
+                is_over_rate: false,
+                client_id: None,
+                resume_token: String::new(),
+                region: None,
+                ip: None,
             },
-        );
+        };
 
-        if let Ok(ws_message) = serde_json::to_string(&WsMessage {
-            msg_type: WsMessageType::UsersWatching,
-            text: None,
-            number: Some(self.users.lock().unwrap().iter().count() as u64),
-        }) {
-            self.out.broadcast(Message::text(ws_message));
+        user.ip = shake.remote_addr().ok().flatten();
+
+        if let Some(regions) = &self.shared.regions {
+            user.region = user
+                .ip
+                .as_ref()
+                .and_then(|ip| ip.parse().ok())
+                .map(|ip| regions.region(ip));
         }
 
-        if let Ok(new_ws_message) = serde_json::to_string(&WsMessage {
-            msg_type: WsMessageType::UsersBruteforcing,
-            text: None,
-            number: Some(
-                self.users
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .filter(|(id, user)| user.is_bruteforcing)
-                    .count() as u64,
-            ),
-        }) {
-            self.out.broadcast(Message::text(new_ws_message));
+        if let Some(identity) = &self.identity {
+            user.client_id = Some(identity.sub.clone());
         }
 
-        Ok(())
-    }
+        // A new token every time, so one that leaked with an old connection
+        // is useless.
+        user.resume_token = resume::new_token();
+        self.send(&WsMessage {
+            text: Some(user.resume_token.clone()),
+            ..WsMessage::new(WsMessageType::Resume)
+        });
 
-    fn on_error(&mut self, err: WSError) {
-        self.users.lock().unwrap().remove(&self.out.connection_id());
+        self.shared
+            .users
+            .lock()
+            .unwrap()
+            .insert(self.out.connection_id(), user);
 
-        if let Ok(ws_message) = serde_json::to_string(&WsMessage {
-            msg_type: WsMessageType::UsersWatching,
-            text: None,
-            number: Some(self.users.lock().unwrap().iter().count() as u64),
-        }) {
-            self.out.broadcast(Message::text(ws_message));
+        if self.is_banned() {
+            self.shared.remove_user(self.out.connection_id());
+            return self.out.close(CloseCode::Policy);
         }
 
-        if let Ok(new_ws_message) = serde_json::to_string(&WsMessage {
-            msg_type: WsMessageType::UsersBruteforcing,
-            text: None,
-            number: Some(
-                self.users
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .filter(|(id, user)| user.is_bruteforcing)
-                    .count() as u64,
-            ),
-        }) {
-            self.out.broadcast(Message::text(new_ws_message));
+        self.shared.publish(Event::ClientJoined {
+            connection_id: self.out.connection_id(),
+            client_id: self.client_id(),
+            ip: self.shared.ip(&self.out),
+        });
+
+        if let Some(identity) = &self.identity {
+            self.send(&WsMessage {
+                text: Some(identity.name.clone()),
+                ..WsMessage::new(WsMessageType::Authenticated)
+            });
+
+            if let Some(settings) = self.shared.accounts.lock().unwrap().settings(&identity.sub) {
+                self.send(&WsMessage {
+                    text: Some(settings.clone()),
+                    ..WsMessage::new(WsMessageType::Settings)
+                });
+            }
+
+            self.send_registered(&self.shared.profiles.lock().unwrap(), &identity.sub);
         }
-    }
 
-    fn on_close(&mut self, code: CloseCode, reason: &str) {
-        self.users.lock().unwrap().remove(&self.out.connection_id());
+        self.shared.record_users();
 
-        if let Ok(ws_message) = serde_json::to_string(&WsMessage {
-            msg_type: WsMessageType::UsersWatching,
-            text: None,
-            number: Some(self.users.lock().unwrap().iter().count() as u64),
-        }) {
-            self.out.broadcast(Message::text(ws_message));
+        let users_watching = self.shared.users.lock().unwrap().len() as u64;
+        let milestone = self
+            .shared
+            .stats
+            .lock()
+            .unwrap()
+            .record_users(users_watching);
+
+        if milestone.is_some() {
+            self.shared.save_stats(&self.shared.stats.lock().unwrap());
         }
 
-        if let Ok(new_ws_message) = serde_json::to_string(&WsMessage {
-            msg_type: WsMessageType::UsersBruteforcing,
-            text: None,
-            number: Some(
-                self.users
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .filter(|(id, user)| user.is_bruteforcing)
-                    .count() as u64,
-            ),
-        }) {
-            self.out.broadcast(Message::text(new_ws_message));
+        self.shared
+            .send_counts(&self.out, self.shared.counts(), false);
+
+        if let Some(region_counts) = self.shared.region_counts() {
+            self.shared.send_regions(&self.out, &region_counts, false);
+        }
+
+        self.shared.broadcast_milestone(&self.out, milestone);
+
+        Ok(())
+    }
+
+    fn on_timeout(&mut self, event: Token) -> Result<()> {
+        if event == ROTATE_CHALLENGE {
+            self.rotate_challenge();
+            self.out.timeout(CHALLENGE_ROTATION_MS, ROTATE_CHALLENGE)?;
         }
+
+        Ok(())
+    }
+
+    fn on_error(&mut self, err: WSError) {
+        eprintln!("connection {} failed: {}", self.out.connection_id(), err);
+
+        self.shared.remove_user(self.out.connection_id());
+    }
+
+    fn on_close(&mut self, code: CloseCode, reason: &str) {
+        self.shared.remove_user(self.out.connection_id());
     }
 
     fn on_message(&mut self, msg: Message) -> Result<()> {
+        if self.is_banned() {
+            self.shared.remove_user(self.out.connection_id());
+            return self.out.close(CloseCode::Policy);
+        }
+
         if let Ok(text) = msg.as_text() {
             if let Ok(ws_message) = serde_json::from_str::<WsMessage>(&text) {
                 match ws_message.msg_type {
                     WsMessageType::New => {
-                        if let Some(text) = ws_message.text {
-                            if let Ok(new_ws_message) = serde_json::to_string(&WsMessage {
-                                msg_type: WsMessageType::New,
-                                text: Some(text),
-                                number: None,
-                            }) {
-                                self.out.broadcast(Message::text(new_ws_message));
+                        if let Some(id) = ws_message.text.as_deref().and_then(ImageId::normalize) {
+                            let submission = Submission {
+                                id,
+                                client_id: self.client_id(),
+                                client_msg_id: ws_message.client_msg_id,
+                                received_at: Instant::now(),
+                                // Only the server replays.
+                                source: ws_message
+                                    .source
+                                    .filter(|&source| source != FindSource::ArchiveReplay),
+                                origin: None,
+                                signature: None,
+                                found_at: None,
+                                labels: Vec::new(),
+                            };
+
+                            if let Some(reason) = self.shared.pause_reason() {
+                                self.shared
+                                    .reject_submission(&self.out, &submission, reason);
+                            } else if self.is_over_rate() {
+                                self.shared.reject_submission(
+                                    &self.out,
+                                    &submission,
+                                    "request rate above the minimum interval",
+                                );
+                            } else if self.check_pow(submission.id.as_str(), ws_message.number) {
+                                let suspicion = self.shared.spam.lock().unwrap().check_submission(
+                                    &self.spam_sources(),
+                                    submission.id.as_str(),
+                                    dates::now_secs(),
+                                );
+
+                                match suspicion {
+                                    Some(reason) => {
+                                        self.shared.quarantine_find(&self.out, submission, reason)
+                                    }
+                                    None => self.shared.clone().submit_find(&self.out, submission),
+                                }
+                            } else {
+                                self.shared.reject_submission(
+                                    &self.out,
+                                    &submission,
+                                    "invalid proof of work",
+                                );
+                            }
+                        }
+                    }
+                    WsMessageType::Announce => {
+                        let is_authorized = match (&self.shared.admin_token, &ws_message.token) {
+                            (Some(admin_token), Some(token)) => {
+                                auth::verify_admin_token(admin_token, token)
                             }
+                            _ => false,
+                        };
+
+                        if !is_authorized {
+                            self.send_error("announcements need a valid admin token");
+                        } else if let Some(text) = ws_message.text {
+                            self.shared.broadcast(
+                                &self.out,
+                                WsMessage {
+                                    text: Some(text),
+                                    ..WsMessage::new(WsMessageType::Announce)
+                                },
+                            );
+                        }
+                    }
+                    WsMessageType::Rate => {
+                        if let Some(requests_per_second) = ws_message.number {
+                            let requests_per_second = requests_per_second.min(MAX_REPORTED_RATE);
+
+                            self.reported_requests =
+                                requests_per_second.saturating_mul(RATE_REPORT_SECS);
+                            self.shared.record_requests(self.reported_requests);
+                            self.check_rate(requests_per_second);
+                        }
+                    }
+                    WsMessageType::Throttled => {
+                        if let Some(throttled) = ws_message.number {
+                            self.shared
+                                .swarm_throttle
+                                .lock()
+                                .unwrap()
+                                .record_throttled(throttled.min(self.reported_requests));
+                            self.reported_requests = 0;
+                        }
+                    }
+                    WsMessageType::Time => self.send(&WsMessage {
+                        number: ws_message.number,
+                        at: Some(dates::now_ms()),
+                        ..WsMessage::new(WsMessageType::Time)
+                    }),
+                    WsMessageType::Resync => {
+                        if let Some(from_seq) = ws_message.number {
+                            self.shared.resync(&self.out, from_seq, self.role);
                         }
                     }
                     WsMessageType::Start => {
-                        let mut users = self.users.lock().unwrap();
+                        let mut users = self.shared.users.lock().unwrap();
 
                         if let Some(user) = users.get_mut(&self.out.connection_id()) {
                             user.is_bruteforcing = true;
-
-                            if let Ok(new_ws_message) = serde_json::to_string(&WsMessage {
-                                msg_type: WsMessageType::UsersBruteforcing,
-                                text: None,
-                                number: Some(
-                                    users
-                                        .iter()
-                                        .filter(|(id, user)| user.is_bruteforcing)
-                                        .count() as u64,
-                                ),
-                            }) {
-                                self.out.broadcast(Message::text(new_ws_message));
-                            }
                         }
                     }
                     WsMessageType::Stop => {
-                        let mut users = self.users.lock().unwrap();
+                        let mut users = self.shared.users.lock().unwrap();
 
                         if let Some(user) = users.get_mut(&self.out.connection_id()) {
                             user.is_bruteforcing = false;
+                        }
+                    }
+                    WsMessageType::Hello => {
+                        if let Some(client_id) = ws_message.text.filter(|client_id| {
+                            client_id.len() >= CLIENT_ID_MIN_LEN
+                                && client_id.len() <= CLIENT_ID_MAX_LEN
+                                && client_id.bytes().all(|b| b.is_ascii_alphanumeric())
+                        }) {
+                            if self.identity.is_none() {
+                                if let Some(user) = self
+                                    .shared
+                                    .users
+                                    .lock()
+                                    .unwrap()
+                                    .get_mut(&self.out.connection_id())
+                                {
+                                    user.client_id = Some(client_id.clone());
+                                }
+
+                                self.send_registered(
+                                    &self.shared.profiles.lock().unwrap(),
+                                    &client_id,
+                                );
+                            }
+                        }
+                    }
+                    WsMessageType::SyncSettings => {
+                        if let (Some(identity), Some(settings)) = (&self.identity, ws_message.text)
+                        {
+                            let mut accounts = self.shared.accounts.lock().unwrap();
+
+                            match accounts.set_settings(&identity.sub, settings) {
+                                Ok(()) => self.shared.save_accounts(&accounts),
+                                Err(reason) => self.send_error(reason),
+                            }
+                        }
+                    }
+                    WsMessageType::Register => match (self.client_id(), ws_message.text) {
+                        (Some(client_id), Some(name)) => {
+                            let mut profiles = self.shared.profiles.lock().unwrap();
 
-                            if let Ok(new_ws_message) = serde_json::to_string(&WsMessage {
-                                msg_type: WsMessageType::UsersBruteforcing,
-                                text: None,
-                                number: Some(
-                                    users
-                                        .iter()
-                                        .filter(|(id, user)| user.is_bruteforcing)
-                                        .count() as u64,
-                                ),
-                            }) {
-                                self.out.broadcast(Message::text(new_ws_message));
+                            match profiles.register(&client_id, &name, ws_message.url) {
+                                Ok(()) => {
+                                    self.shared.save_profiles(&profiles);
+                                    self.send_registered(&profiles, &client_id);
+                                }
+                                Err(reason) => self.send_error(reason),
+                            }
+                        }
+                        _ => self.send_error("registration needs a client ID and a name"),
+                    },
+                    WsMessageType::Federate => match &self.peer {
+                        None if self
+                            .shared
+                            .federation
+                            .is_peer_token(ws_message.token.as_deref()) =>
+                        {
+                            let name = match self.shared.federation.verify_hello(&ws_message) {
+                                Ok(name) => name.to_owned(),
+                                Err(reason) => {
+                                    eprintln!("refused hello of a peer: {}", reason);
+                                    self.shared.metrics.lock().unwrap().federation_rejections += 1;
+                                    self.send_error("invalid federation hello");
+                                    return Ok(());
+                                }
+                            };
+
+                            // Peers are not users.
+                            self.shared
+                                .users
+                                .lock()
+                                .unwrap()
+                                .remove(&self.out.connection_id());
+                            self.shared.record_users();
+                            self.peer = Some(name);
+                            self.send(&self.shared.federation.hello());
+                        }
+                        None => self.send_error("invalid federation token"),
+                        Some(peer) => {
+                            let origin = ws_message.origin.unwrap_or_else(|| peer.clone());
+
+                            // Back from a peer it was pushed to.
+                            if origin == self.shared.federation.name {
+                                return Ok(());
+                            }
+
+                            if let Some(id) =
+                                ws_message.text.as_deref().and_then(ImageId::normalize)
+                            {
+                                if let Err(reason) = self.shared.federation.verify_find(
+                                    &origin,
+                                    id,
+                                    ws_message.at,
+                                    ws_message.signature.as_deref(),
+                                ) {
+                                    eprintln!(
+                                        "refused find {} of {} pushed by peer {}: {}",
+                                        id, origin, peer, reason
+                                    );
+                                    self.shared.metrics.lock().unwrap().federation_rejections += 1;
+                                    return Ok(());
+                                }
+
+                                let mut known = self.shared.federation.known.lock().unwrap();
+                                known.record(id, peer);
+                                known.record(id, &origin);
+                                drop(known);
+
+                                if let Some(reason) = self.shared.pause_reason() {
+                                    eprintln!(
+                                        "refused find {} of {} pushed by peer {}: {}",
+                                        id, origin, peer, reason
+                                    );
+                                    self.shared.metrics.lock().unwrap().federation_rejections += 1;
+                                    return Ok(());
+                                }
+
+                                let submission = Submission {
+                                    id,
+                                    client_id: None,
+                                    client_msg_id: None,
+                                    received_at: Instant::now(),
+                                    source: ws_message.source,
+                                    origin: Some(origin),
+                                    signature: ws_message.signature,
+                                    found_at: ws_message.at,
+                                    labels: Vec::new(),
+                                };
+                                // Screened like finds from clients, but not
+                                // held to their find rate, a peer passes on
+                                // the finds of a whole instance.
+                                let suspicion = self.shared.spam.lock().unwrap().check_submission(
+                                    &[],
+                                    id.as_str(),
+                                    dates::now_secs(),
+                                );
+
+                                self.shared.metrics.lock().unwrap().federated_finds += 1;
+
+                                match suspicion {
+                                    Some(reason) => {
+                                        self.shared.quarantine_find(&self.out, submission, reason)
+                                    }
+                                    None => self.shared.clone().submit_find(&self.out, submission),
+                                }
+                            }
+                        }
+                    },
+                    WsMessageType::Seen => {
+                        if let (Some(peer), Some(text)) = (&self.peer, &ws_message.text) {
+                            if let Err(reason) = self.shared.federation.verify_seen(
+                                peer,
+                                text,
+                                ws_message.signature.as_deref(),
+                            ) {
+                                eprintln!("refused seen set of peer {}: {}", peer, reason);
+                                self.shared.metrics.lock().unwrap().federation_rejections += 1;
+                                return Ok(());
+                            }
+
+                            let mut known = self.shared.federation.known.lock().unwrap();
+
+                            for id in text.split(' ').filter_map(ImageId::normalize) {
+                                known.record(id, peer);
                             }
                         }
                     }
@@ -194,16 +1914,400 @@ impl Handler for Server {
     }
 }
 
+/// Creates the data directory if needed, returning whether it had to, or
+/// `None` if it is unusable and nothing will be kept across restarts.
+fn create_data_dir(data_dir: &str) -> Option<bool> {
+    if fs::metadata(data_dir).is_ok() {
+        return Some(false);
+    }
+
+    match fs::create_dir_all(data_dir) {
+        Ok(()) => Some(true),
+        Err(err) => {
+            eprintln!("could not create data directory {}: {}", data_dir, err);
+            None
+        }
+    }
+}
+
+/// The path of a store, `var` if set or `name` in the data directory.
+fn data_file(var: &str, data_dir: Option<&str>, name: &str) -> Option<String> {
+    env::var(var)
+        .ok()
+        .or_else(|| data_dir.map(|data_dir| format!("{}/{}", data_dir, name)))
+}
+
+/// Loads a store, refusing to start rather than overwrite one it cannot
+/// read.
+fn load_store<T: Default>(
+    path: &Option<String>,
+    load: fn(&str) -> std::result::Result<T, StoreError>,
+) -> T {
+    match path.as_deref().map(load) {
+        Some(Ok(store)) => store,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        None => T::default(),
+    }
+}
+
+/// The capacity set in `var`, or `default`.
+fn capacity(var: &str, default: usize) -> usize {
+    env::var(var)
+        .ok()
+        .and_then(|capacity| capacity.parse().ok())
+        .filter(|&capacity| capacity > 0)
+        .unwrap_or(default)
+}
+
+/// The days after which the stores drop what they recorded, set in
+/// `RETENTION_DAYS`.
+fn retention_days() -> Option<u64> {
+    env::var("RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+}
+
+fn print_banner(listen_addr: &str, data_dir: &str) {
+    let port = listen_addr.rsplit(':').next().unwrap_or_default();
+
+    println!();
+    println!("  Random Imgur Wall is running for the first time.");
+    println!("  Data is kept in {}.", data_dir);
+
+    if assets::get("/").is_some() {
+        println!("  Open http://localhost:{}/ in your browser.", port);
+    } else {
+        println!(
+            "  Clients connect to ws://localhost:{}/ws, serve the website separately.",
+            port
+        );
+    }
+
+    println!();
+}
+
 fn main() {
     env_logger::init();
 
-    let listen_addr = env::var("WS_LISTEN_ADDR").expect("WS_LISTEN_ADDR must be defined.");
+    let replay_options = replay::Options::from_args().unwrap_or_else(|usage| {
+        eprintln!("{}", usage);
+        process::exit(1);
+    });
+
+    let listen_addr = env::var("WS_LISTEN_ADDR").unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_owned());
+    let data_dir = env::var("DATA_DIR").unwrap_or_else(|_| DEFAULT_DATA_DIR.to_owned());
+    let first_run = create_data_dir(&data_dir);
+    let data = first_run.map(|_| data_dir.as_str());
+
+    let stats_path = data_file("STATS_FILE", data, "stats.json");
+    let profiles_path = data_file("PROFILES_FILE", data, "profiles.json");
+    let accounts_path = data_file("ACCOUNTS_FILE", data, "accounts.json");
+    let iotd_path = data_file("IOTD_FILE", data, "iotd.json");
+    let runtime_config_path = env::var("RUNTIME_CONFIG_FILE").ok();
+    let archive_path = data_file("ARCHIVE_FILE", data, "archive.jsonl");
+    let timeline_path = data_file("TIMELINE_FILE", data, "timeline.json");
+    let quarantine_path = data_file("QUARANTINE_FILE", data, "quarantine.json");
+    let bans_path = data_file("BANS_FILE", data, "bans.json");
+
+    let shared = Arc::new(Shared {
+        users: Mutex::new(HashMap::new()),
+        stats: Mutex::new(load_store(&stats_path, Stats::load)),
+        profiles: Mutex::new(load_store(&profiles_path, Profiles::load)),
+        accounts: Mutex::new(load_store(&accounts_path, Accounts::load)),
+        iotd: Mutex::new(load_store(&iotd_path, ImageOfTheDay::load)),
+        archive: Mutex::new(load_store(&archive_path, Archive::load)),
+        timeline: Mutex::new(load_store(&timeline_path, Timeline::load)),
+        quarantine: Mutex::new(load_store(&quarantine_path, Quarantine::load)),
+        bans: Mutex::new(load_store(&bans_path, Bans::load)),
+        rejections: Mutex::new(Rejections::default()),
+        spam: Mutex::new(SpamFilter::default()),
+        history: Mutex::new(History::new(capacity(
+            "HISTORY_LEN",
+            history::DEFAULT_CAPACITY,
+        ))),
+        fair_queue: Mutex::new(FairQueue::default()),
+        broadcast_interval: Duration::from_secs(1)
+            / env::var("MAX_BROADCASTS_PER_SEC")
+                .ok()
+                .and_then(|rate| rate.parse().ok())
+                .unwrap_or(fairness::DEFAULT_BROADCASTS_PER_SEC)
+                .max(1),
+        recent_ids: Mutex::new(RecentIds::new(capacity(
+            "RECENT_IDS",
+            dedup::DEFAULT_CAPACITY,
+        ))),
+        metrics: Mutex::new(Metrics::default()),
+        outbound: Mutex::new(Outbound::new(
+            env::var("IMGUR_REQUESTS_PER_SEC")
+                .ok()
+                .and_then(|rate| rate.parse().ok())
+                .unwrap_or(outbound::DEFAULT_REQUESTS_PER_SEC),
+        )),
+        send_failures: Mutex::new(HashMap::new()),
+        suspended: Mutex::new(Suspended::default()),
+        // Sessions are gone for good past the resume grace period anyway.
+        counts_grace: Duration::from_secs(
+            env::var("COUNTS_GRACE_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(DEFAULT_COUNTS_GRACE_SECS),
+        )
+        .min(resume::RESUME_GRACE),
+        activity: Mutex::new(Activity::default()),
+        swarm_throttle: Mutex::new(SwarmThrottle::default()),
+        federation: Federation::from_env().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        }),
+        regions: env::var("GEOIP_DB").ok().map(|path| {
+            Regions::open(&path).unwrap_or_else(|err| {
+                eprintln!("could not open {}: {}", path, err);
+                process::exit(1);
+            })
+        }),
+        streams: Streams::default(),
+        // Replaying into the recording would never end.
+        recorder: env::var("REPLAY_FILE")
+            .ok()
+            .filter(|_| replay_options.is_none())
+            .map(|path| {
+                Recorder::open(&path).map(Mutex::new).unwrap_or_else(|err| {
+                    eprintln!("could not open replay file {}: {}", path, err);
+                    process::exit(1);
+                })
+            }),
+        runtime_config: Mutex::new(
+            runtime_config_path
+                .as_ref()
+                .and_then(|path| runtime_config::load(path).ok())
+                .unwrap_or_default(),
+        ),
+        stats_path,
+        profiles_path,
+        accounts_path,
+        iotd_path,
+        archive_path,
+        timeline_path,
+        quarantine_path,
+        bans_path,
+        auth: Auth::from_env(),
+        admin_token: env::var("ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty()),
+        access: env::var("ACCESS_FILE")
+            .ok()
+            .map(|path| {
+                AccessPolicy::load(&path).unwrap_or_else(|err| {
+                    eprintln!("could not load access policy {}: {}", path, err);
+                    process::exit(1);
+                })
+            })
+            .unwrap_or_default(),
+        pow_difficulty: env::var("POW_DIFFICULTY")
+            .ok()
+            .and_then(|difficulty| difficulty.parse().ok())
+            .unwrap_or(DEFAULT_POW_DIFFICULTY),
+        verify_finds: env::var("VERIFY_FINDS").map_or(false, |value| value == "1"),
+        classifier: Classifier::from_env(),
+        screen: Screen::from_env().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        }),
+        policies: Policies::from_env().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1);
+        }),
+        events: EventBus::from_env(),
+        writer: Writer::start(),
+        legacy_counts: env::var("LEGACY_COUNTS").map_or(false, |value| value == "1"),
+    });
+
+    let iotd_shared = shared.clone();
+    thread::spawn(move || loop {
+        thread::sleep(IOTD_CHECK_INTERVAL);
+
+        let mut iotd = iotd_shared.iotd.lock().unwrap();
+
+        if iotd.roll_over(&dates::today()) {
+            iotd_shared.save_iotd(&iotd);
+        }
+    });
+
+    if let Some(retention_days) = retention_days() {
+        let purge_shared = shared.clone();
+        thread::spawn(move || loop {
+            purge_shared.purge(retention_days);
+            thread::sleep(PURGE_INTERVAL);
+        });
+    }
+
+    let max_connections = capacity("MAX_CONNECTIONS", DEFAULT_MAX_CONNECTIONS);
+
+    let ws = Builder::new()
+        .with_settings(Settings {
+            max_connections,
+            ..Settings::default()
+        })
+        .build(|out| Server {
+            out,
+            shared: shared.clone(),
+            identity: None,
+            role: Role::Public,
+            challenge: String::new(),
+            previous_challenge: None,
+            resume_token: None,
+            reported_requests: 0,
+            peer: None,
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("could not start the websocket server: {}", err);
+            process::exit(1);
+        });
+
+    if let Some(path) = runtime_config_path {
+        runtime_config::watch(path, shared.clone(), ws.broadcaster());
+    }
+
+    // Connections coming and going only show up once per interval, so a
+    // reconnect storm does not turn into a broadcast storm.
+    let counts_shared = shared.clone();
+    let counts_broadcaster = ws.broadcaster();
+    thread::spawn(move || {
+        let mut last_counts = Counts::default();
+        let mut previous_counts = Counts::default();
+        let mut last_region_counts = None;
+
+        loop {
+            thread::sleep(COUNTS_INTERVAL);
+
+            let counts = counts_shared.counts();
+
+            // Only once they held for a whole interval, so blips while users
+            // come and go aren't sent.
+            if counts == previous_counts && counts != last_counts {
+                counts_shared.send_counts(&counts_broadcaster, counts, true);
+                counts_shared.streams.publish(&StreamEvent::Counts(counts));
+                counts_shared
+                    .activity
+                    .lock()
+                    .unwrap()
+                    .record_users(counts.watching, Instant::now());
+                last_counts = counts;
+            }
+
+            previous_counts = counts;
+
+            let region_counts = counts_shared.region_counts();
+
+            if region_counts != last_region_counts {
+                if let Some(region_counts) = &region_counts {
+                    counts_shared.send_regions(&counts_broadcaster, region_counts, true);
+                }
+
+                last_region_counts = region_counts;
+            }
+        }
+    });
+
+    // Clients only see their own 429s, the server tells them all to slow
+    // down once imgur pushes back on many of them.
+    let swarm_shared = shared.clone();
+    let swarm_broadcaster = ws.broadcaster();
+    thread::spawn(move || loop {
+        thread::sleep(swarm::CHECK_INTERVAL);
+
+        let backoff_secs = swarm_shared
+            .swarm_throttle
+            .lock()
+            .unwrap()
+            .check(Instant::now());
+
+        if let Some(backoff_secs) = backoff_secs {
+            if backoff_secs > 0 {
+                swarm_shared.metrics.lock().unwrap().swarm_backoffs += 1;
+            }
+
+            swarm_shared.send_throttled(&swarm_broadcaster, backoff_secs, true);
+        }
+    });
+
+    // Finds are broadcast at a steady pace, taking turns between those who
+    // found them.
+    let fair_shared = shared.clone();
+    let fair_broadcaster = ws.broadcaster();
+    thread::spawn(move || loop {
+        thread::sleep(fair_shared.broadcast_interval);
+
+        let queued = fair_shared.fair_queue.lock().unwrap().pop();
+
+        if let Some((ws_message, received_at)) = queued {
+            fair_shared.broadcast(&fair_broadcaster, ws_message);
+            fair_shared
+                .metrics
+                .lock()
+                .unwrap()
+                .record_broadcast(received_at.elapsed());
+        }
+    });
+
+    let activity_shared = shared.clone();
+    let activity_broadcaster = ws.broadcaster();
+    thread::spawn(move || loop {
+        thread::sleep(activity::ACTIVITY_INTERVAL);
+
+        let item = activity_shared.activity.lock().unwrap().next();
+
+        if let Some(text) = item {
+            activity_shared.send_activity(&activity_broadcaster, text);
+        }
+    });
+
+    if let Some(options) = replay_options {
+        let replay_shared = shared.clone();
+        let replay_broadcaster = ws.broadcaster();
+        thread::spawn(move || {
+            if let Err(err) = replay::replay(
+                &options.path,
+                options.speed,
+                options.repeat,
+                replay_shared,
+                replay_broadcaster,
+            ) {
+                eprintln!("could not replay {}: {}", options.path, err);
+            }
+        });
+    }
+
+    if let Ok(stream_addr) = env::var("STREAM_LISTEN_ADDR") {
+        if let Err(err) = stream::listen(&stream_addr, shared.clone()) {
+            eprintln!("could not listen on {}: {}", stream_addr, err);
+            process::exit(1);
+        }
+    }
+
+    if !shared.policies.is_empty() {
+        println!("content policies: {}", shared.policies.names().join(", "));
+    }
+
+    if let Some(screen) = &shared.screen {
+        println!("screening finds against {} hashes", screen.hash_count());
+    }
+
+    if let Some(public_key) = shared.federation.public_key() {
+        println!("federation public key: {}", public_key);
+    }
+
+    federation::connect(shared.clone());
 
-    let users = Arc::new(Mutex::new(HashMap::new()));
+    if first_run == Some(true) {
+        print_banner(&listen_addr, &data_dir);
+    }
 
-    listen(listen_addr, |out| Server {
-        out,
-        users: users.clone(),
-    })
-    .unwrap();
+    if let Err(err) = ws.listen(&listen_addr) {
+        eprintln!("could not listen on {}: {}", listen_addr, err);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use crate::writer::WriterStats;
+
+#[derive(Default, Serialize)]
+pub struct Metrics {
+    pub verifications_pending: u64,
+    pub verifications: u64,
+    pub verification_failures: u64,
+    pub verification_ms_total: u64,
+    pub verification_ms_max: u64,
+    /// Probes answered without asking imgur: known misses, or imgur
+    /// throttling the server, or too many probes queued.
+    pub outbound_refused: u64,
+    pub imgur_throttles: u64,
+    pub rate_violations: u64,
+    pub broadcasts: u64,
+    pub broadcast_ms_total: u64,
+    pub broadcast_ms_max: u64,
+    pub send_errors: u64,
+    pub broadcast_errors: u64,
+    pub send_failure_disconnects: u64,
+    pub sessions_resumed: u64,
+    /// Times clients were told to back off because imgur throttled them.
+    pub swarm_backoffs: u64,
+    pub history_evictions: u64,
+    pub recent_ids_evictions: u64,
+    /// Finds never broadcast because their contributor had too many
+    /// waiting.
+    pub fair_queue_drops: u64,
+    /// Finds pushed by federated instances, before they are checked.
+    pub federated_finds: u64,
+    /// Finds and seen sets from federated instances refused for their
+    /// signature.
+    pub federation_rejections: u64,
+    /// Finds let through because the NSFW classifier could not be reached.
+    pub classification_failures: u64,
+    /// Finds on a hash list of known abusive images.
+    pub screen_matches: u64,
+    /// Events not posted to `EVENTS_WEBHOOK_URL` because it lagged behind.
+    pub webhook_drops: u64,
+    pub writer: WriterStats,
+}
+
+impl Metrics {
+    /// Records the time between receiving a find and broadcasting it.
+    pub fn record_broadcast(&mut self, elapsed: Duration) {
+        let elapsed_ms = elapsed.as_millis() as u64;
+
+        self.broadcasts += 1;
+        self.broadcast_ms_total += elapsed_ms;
+        self.broadcast_ms_max = self.broadcast_ms_max.max(elapsed_ms);
+    }
+
+    pub fn record_verification<T>(&mut self, elapsed: Duration, result: &Result<T, String>) {
+        let elapsed_ms = elapsed.as_millis() as u64;
+
+        self.verifications_pending = self.verifications_pending.saturating_sub(1);
+        self.verifications += 1;
+        self.verification_ms_total += elapsed_ms;
+        self.verification_ms_max = self.verification_ms_max.max(elapsed_ms);
+
+        if result.is_err() {
+            self.verification_failures += 1;
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::store::{self, StoreError};
+
+const MAX_REJECTIONS: usize = 200;
+
+/// A submission the server refused, for the operator dashboard.
+#[derive(Serialize)]
+pub struct Rejection {
+    pub at: u64,
+    pub id: String,
+    pub reason: String,
+    pub client_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+/// Connections and recent rejections of an IP address.
+#[derive(Default, Serialize)]
+pub struct IpStats {
+    pub ip: Option<String>,
+    pub connections: u64,
+    pub bruteforcing: u64,
+    pub rejections: u64,
+}
+
+/// The last refused submissions, newest last.
+#[derive(Default)]
+pub struct Rejections {
+    rejections: VecDeque<Rejection>,
+}
+
+impl Rejections {
+    pub fn record(&mut self, rejection: Rejection) {
+        if self.rejections.len() == MAX_REJECTIONS {
+            self.rejections.pop_front();
+        }
+
+        self.rejections.push_back(rejection);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rejection> {
+        self.rejections.iter()
+    }
+}
+
+/// IP addresses and client IDs kept off the server by the operator.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Bans {
+    ips: BTreeSet<String>,
+    client_ids: BTreeSet<String>,
+}
+
+impl Bans {
+    pub fn load(path: &str) -> Result<Bans, StoreError> {
+        store::load(path)
+    }
+
+    pub fn is_banned(&self, ip: Option<&str>, client_id: Option<&str>) -> bool {
+        ip.map_or(false, |ip| self.ips.contains(ip))
+            || client_id.map_or(false, |client_id| self.client_ids.contains(client_id))
+    }
+
+    /// Bans or lifts the ban on an IP or a client ID, returning whether
+    /// that changed anything.
+    pub fn set(&mut self, ip: Option<String>, client_id: Option<String>, banned: bool) -> bool {
+        let mut changed = false;
+
+        for (set, value) in [(&mut self.ips, ip), (&mut self.client_ids, client_id)] {
+            if let Some(value) = value {
+                changed |= if banned {
+                    set.insert(value)
+                } else {
+                    set.remove(&value)
+                };
+            }
+        }
+
+        changed
+    }
+}
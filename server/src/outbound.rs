@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::verify::Probe;
+
+pub const DEFAULT_REQUESTS_PER_SEC: u32 = 2;
+/// Probes are refused rather than queued for longer than this.
+const MAX_WAIT: Duration = Duration::from_secs(10);
+/// Missing images stay missing, probing them again is wasted.
+const MISS_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_CACHED_MISSES: usize = 10_000;
+/// How long to stop probing after a 429 without `Retry-After`, doubled for
+/// each 429 in a row.
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+/// Every request the server makes to imgur goes through here, so a flood of
+/// submissions never turns into a flood of requests to imgur.
+pub struct Outbound {
+    interval: Duration,
+    next_slot: Option<Instant>,
+    misses: HashMap<String, Instant>,
+    /// Requests are refused until then after imgur answered 429.
+    open_until: Option<Instant>,
+    throttles_in_a_row: u32,
+}
+
+impl Outbound {
+    pub fn new(requests_per_sec: u32) -> Outbound {
+        Outbound {
+            interval: Duration::from_secs(1) / requests_per_sec.max(1),
+            next_slot: None,
+            misses: HashMap::new(),
+            open_until: None,
+            throttles_in_a_row: 0,
+        }
+    }
+
+    /// How long to wait before probing `id`, or why it is not probed. Each
+    /// permit takes a slot, whether the probe is made or not.
+    pub fn permit(&mut self, id: &str, now: Instant) -> Result<Duration, String> {
+        if self
+            .misses
+            .get(id)
+            .map_or(false, |at| now.duration_since(*at) < MISS_TTL)
+        {
+            return Err("image does not exist".to_owned());
+        }
+
+        if self.is_open(now) {
+            return Err("imgur is throttling the server, try again later".to_owned());
+        }
+
+        let slot = self.next_slot.map_or(now, |slot| slot.max(now));
+        let wait = slot - now;
+
+        if wait > MAX_WAIT {
+            return Err("server is busy, try again later".to_owned());
+        }
+
+        self.next_slot = Some(slot + self.interval);
+
+        Ok(wait)
+    }
+
+    /// Records what imgur answered for `id` and turns it into the content
+    /// hash of the image, if it exists.
+    pub fn record(
+        &mut self,
+        id: &str,
+        probe: Probe,
+        now: Instant,
+    ) -> Result<Option<String>, String> {
+        if !matches!(probe, Probe::Throttled(_)) {
+            self.throttles_in_a_row = 0;
+        }
+
+        match probe {
+            Probe::Exists(content_hash) => Ok(content_hash),
+            Probe::Missing => {
+                self.cache_miss(id, now);
+                Err("image does not exist".to_owned())
+            }
+            Probe::Throttled(retry_after) => {
+                let backoff = BASE_BACKOFF * 2u32.pow(self.throttles_in_a_row.min(10));
+
+                self.throttles_in_a_row += 1;
+                self.open_until = Some(now + retry_after.unwrap_or(backoff).min(MAX_BACKOFF));
+
+                Err("imgur is throttling the server, try again later".to_owned())
+            }
+            Probe::Unreachable(err) => Err(format!("could not reach imgur: {}", err)),
+        }
+    }
+
+    fn is_open(&self, now: Instant) -> bool {
+        self.open_until.map_or(false, |until| now < until)
+    }
+
+    fn cache_miss(&mut self, id: &str, now: Instant) {
+        if self.misses.len() >= MAX_CACHED_MISSES {
+            self.misses
+                .retain(|_, at| now.duration_since(*at) < MISS_TTL);
+        }
+
+        if self.misses.len() >= MAX_CACHED_MISSES {
+            if let Some(oldest) = self
+                .misses
+                .iter()
+                .min_by_key(|(_, at)| **at)
+                .map(|(id, _)| id.clone())
+            {
+                self.misses.remove(&oldest);
+            }
+        }
+
+        self.misses.insert(id.to_owned(), now);
+    }
+}
@@ -0,0 +1,199 @@
+//! Operator rules run on every find that passed the server's own checks,
+//! just before it is broadcast. The policies named in `CONTENT_POLICIES`
+//! run in order; to compile one in, implement `ContentPolicy` and add it to
+//! `by_name`.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+
+use protocol::{FindSource, ImageId};
+
+use crate::scripts::Scripts;
+
+/// A find as policies see it, and as it is broadcast once they all let it
+/// through.
+#[derive(Clone)]
+pub struct Find {
+    pub id: ImageId,
+    pub client_id: Option<String>,
+    pub source: Option<FindSource>,
+    /// The federated instance it was found on, if not this one.
+    pub origin: Option<String>,
+    /// Shown next to the find, added by `Action::Annotate`.
+    pub labels: Vec<String>,
+}
+
+pub enum Action {
+    Accept,
+    /// Refuses the find, telling the submitter why.
+    Block(String),
+    /// Holds the find back for review.
+    Quarantine(String),
+    /// Goes on with the find changed.
+    Rewrite(Find),
+    /// Goes on with a label added to the find.
+    Annotate(String),
+}
+
+pub trait ContentPolicy: Send + Sync {
+    /// What it is listed as in `CONTENT_POLICIES` and in logs.
+    fn name(&self) -> &'static str;
+
+    fn check(&self, find: &Find) -> Action;
+}
+
+/// What the policies made of a find.
+pub enum Outcome {
+    Accept(Find),
+    Block(String),
+    Quarantine(String),
+}
+
+#[derive(Default)]
+pub struct Policies {
+    policies: Vec<Box<dyn ContentPolicy>>,
+}
+
+impl Policies {
+    pub fn from_env() -> Result<Policies, String> {
+        let mut policies = Policies::default();
+
+        for name in env::var("CONTENT_POLICIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            policies.policies.push(by_name(name)?);
+        }
+
+        Ok(policies)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.policies.iter().map(|policy| policy.name()).collect()
+    }
+
+    /// Runs every policy on `find` until one blocks or quarantines it.
+    pub fn apply(&self, mut find: Find) -> Outcome {
+        for policy in &self.policies {
+            match policy.check(&find) {
+                Action::Accept => {}
+                Action::Block(reason) => return Outcome::Block(reason),
+                Action::Quarantine(reason) => {
+                    return Outcome::Quarantine(format!("{}: {}", policy.name(), reason))
+                }
+                Action::Rewrite(rewritten) => find = rewritten,
+                Action::Annotate(label) => find.labels.push(label),
+            }
+        }
+
+        Outcome::Accept(find)
+    }
+}
+
+fn by_name(name: &str) -> Result<Box<dyn ContentPolicy>, String> {
+    Ok(match name {
+        "blocklist" => Box::new(Blocklist::from_env()?),
+        "review-anonymous" => Box::new(ReviewAnonymous),
+        "hide-source" => Box::new(HideSource),
+        "label-origin" => Box::new(LabelOrigin),
+        "scripts" => Box::new(Scripts::from_env()?),
+        _ => return Err(format!("CONTENT_POLICIES: unknown policy \"{}\"", name)),
+    })
+}
+
+/// Blocks the IDs listed in `POLICY_BLOCKLIST_FILE`, one per line.
+struct Blocklist {
+    ids: HashSet<ImageId>,
+}
+
+impl Blocklist {
+    fn from_env() -> Result<Blocklist, String> {
+        let path = env::var("POLICY_BLOCKLIST_FILE")
+            .map_err(|_| "the blocklist policy needs POLICY_BLOCKLIST_FILE".to_owned())?;
+        let content =
+            fs::read_to_string(&path).map_err(|err| format!("could not read {}: {}", path, err))?;
+
+        Ok(Blocklist {
+            ids: content
+                .lines()
+                .map(str::trim)
+                .filter_map(ImageId::new)
+                .collect(),
+        })
+    }
+}
+
+impl ContentPolicy for Blocklist {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    fn check(&self, find: &Find) -> Action {
+        if self.ids.contains(&find.id) {
+            Action::Block("this image is blocked on this server".to_owned())
+        } else {
+            Action::Accept
+        }
+    }
+}
+
+/// Holds back finds from clients that are not logged in or have no client
+/// ID, for servers that only trust their regulars.
+struct ReviewAnonymous;
+
+impl ContentPolicy for ReviewAnonymous {
+    fn name(&self) -> &'static str {
+        "review-anonymous"
+    }
+
+    fn check(&self, find: &Find) -> Action {
+        if find.client_id.is_none() && find.origin.is_none() {
+            Action::Quarantine("found by an anonymous client".to_owned())
+        } else {
+            Action::Accept
+        }
+    }
+}
+
+/// Does not tell users which kind of client found an image.
+struct HideSource;
+
+impl ContentPolicy for HideSource {
+    fn name(&self) -> &'static str {
+        "hide-source"
+    }
+
+    fn check(&self, find: &Find) -> Action {
+        if find.source.is_none() {
+            return Action::Accept;
+        }
+
+        Action::Rewrite(Find {
+            source: None,
+            ..find.clone()
+        })
+    }
+}
+
+/// Labels finds from federated instances with where they were found.
+struct LabelOrigin;
+
+impl ContentPolicy for LabelOrigin {
+    fn name(&self) -> &'static str {
+        "label-origin"
+    }
+
+    fn check(&self, find: &Find) -> Action {
+        match &find.origin {
+            Some(origin) => Action::Annotate(format!("via {}", origin)),
+            None => Action::Accept,
+        }
+    }
+}
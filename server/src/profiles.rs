@@ -0,0 +1,157 @@
+use std::collections::{HashMap, VecDeque};
+
+use protocol::{is_valid_name, ImageId};
+
+use crate::store::{self, StoreError};
+
+const RECENT_FINDS: usize = 24;
+const AVATAR_MAX_LEN: usize = 256;
+
+#[derive(Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub avatar: Option<String>,
+    pub finds: u64,
+    pub recent: VecDeque<ImageId>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Profiles {
+    by_client: HashMap<String, Profile>,
+}
+
+impl Profiles {
+    pub fn load(path: &str) -> Result<Profiles, StoreError> {
+        store::load(path)
+    }
+
+    /// Registers or renames the profile of `client_id`.
+    pub fn register(
+        &mut self,
+        client_id: &str,
+        name: &str,
+        avatar: Option<String>,
+    ) -> Result<(), &'static str> {
+        if !is_valid_name(name) {
+            return Err("names must be 3 to 20 letters, digits, '-' or '_'");
+        }
+
+        if let Some(avatar) = &avatar {
+            if !avatar.starts_with("https://") || avatar.len() > AVATAR_MAX_LEN {
+                return Err("avatars must be https:// links");
+            }
+        }
+
+        let taken = self.by_client.iter().any(|(other_id, profile)| {
+            other_id != client_id && profile.name.eq_ignore_ascii_case(name)
+        });
+
+        if taken {
+            return Err("this name is already taken");
+        }
+
+        let profile = self
+            .by_client
+            .entry(client_id.to_owned())
+            .or_insert_with(|| Profile {
+                name: String::new(),
+                avatar: None,
+                finds: 0,
+                recent: VecDeque::new(),
+            });
+
+        profile.name = name.to_owned();
+        profile.avatar = avatar;
+
+        Ok(())
+    }
+
+    /// Credits a find to `client_id` if it registered a profile.
+    pub fn record_find(&mut self, client_id: &str, id: ImageId) -> bool {
+        if let Some(profile) = self.by_client.get_mut(client_id) {
+            profile.finds += 1;
+            profile.recent.push_front(id);
+            profile.recent.truncate(RECENT_FINDS);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove(&mut self, client_id: &str) -> bool {
+        self.by_client.remove(client_id).is_some()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Profile> {
+        self.by_client
+            .values()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn by_client(&self, client_id: &str) -> Option<&Profile> {
+        self.by_client.get(client_id)
+    }
+}
+
+pub fn render_profile(profile: &Profile) -> String {
+    let avatar = match &profile.avatar {
+        Some(avatar) => format!(
+            r#"<img class="avatar" referrerpolicy="no-referrer" src="{}" alt="">"#,
+            escape_html(avatar)
+        ),
+        None => String::new(),
+    };
+
+    let recent = profile
+        .recent
+        .iter()
+        .map(|id| {
+            format!(
+                r#"<a target="_blank" rel="noopener" referrerpolicy="no-referrer" href="https://i.imgur.com/{0}.png"><img referrerpolicy="no-referrer" src="https://i.imgur.com/{0}m.jpg" alt="{0}"></a>"#,
+                id
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8" />
+  <meta content="width=device-width, initial-scale=1.0" name="viewport" />
+  <title>{name} - Random Imgur Wall</title>
+  <style>
+    body {{ font-family: sans-serif; background-color: #e2e2e2; color: #333333; margin: 0; padding: 1em; }}
+    section {{ background-color: #fffaff; margin: 0.5em auto; padding: 0.5em 1em; max-width: 60em; box-shadow: 3px 3px 3px rgba(50, 50, 50, 0.2); }}
+    .avatar {{ width: 96px; height: 96px; object-fit: cover; float: right; }}
+    .recent img {{ width: 160px; height: 160px; object-fit: cover; margin: 0.2em; }}
+  </style>
+</head>
+<body>
+  <section>
+    {avatar}
+    <h1>{name}</h1>
+    <p>Lifetime finds: <b>{finds}</b></p>
+  </section>
+  <section class="recent">
+    <h2>Recent discoveries</h2>
+    {recent}
+  </section>
+</body>
+</html>
+"#,
+        name = escape_html(&profile.name),
+        avatar = avatar,
+        finds = profile.finds,
+        recent = recent
+    )
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,70 @@
+use protocol::{FindSource, ImageId};
+
+use crate::store::{self, StoreError};
+
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Serialize, Deserialize)]
+pub struct QuarantinedFind {
+    pub id: ImageId,
+    pub client_id: Option<String>,
+    pub reason: String,
+    pub at: u64,
+    #[serde(default)]
+    pub source: Option<FindSource>,
+    #[serde(default)]
+    pub origin: Option<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub found_at: Option<u64>,
+}
+
+/// Suspicious finds held back from the wall until an admin reviews them.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Quarantine {
+    finds: Vec<QuarantinedFind>,
+}
+
+impl Quarantine {
+    pub fn load(path: &str) -> Result<Quarantine, StoreError> {
+        store::load(path)
+    }
+
+    pub fn insert(&mut self, find: QuarantinedFind) {
+        if self.finds.iter().any(|other| other.id == find.id) {
+            return;
+        }
+
+        self.finds.push(find);
+
+        if self.finds.len() > MAX_ENTRIES {
+            self.finds.remove(0);
+        }
+    }
+
+    pub fn remove(&mut self, id: ImageId) -> Option<QuarantinedFind> {
+        let index = self.finds.iter().position(|find| find.id == id)?;
+
+        Some(self.finds.remove(index))
+    }
+
+    pub fn purge_before(&mut self, at: u64) -> bool {
+        let finds = self.finds.len();
+        self.finds.retain(|find| find.at >= at);
+
+        self.finds.len() != finds
+    }
+
+    pub fn remove_client(&mut self, client_id: &str) -> bool {
+        let finds = self.finds.len();
+        self.finds
+            .retain(|find| find.client_id.as_deref() != Some(client_id));
+
+        self.finds.len() != finds
+    }
+
+    pub fn finds(&self) -> &[QuarantinedFind] {
+        &self.finds
+    }
+}
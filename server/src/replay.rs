@@ -0,0 +1,197 @@
+//! Recording of every sequenced broadcast to an append-only file, and its
+//! replay. Each frame is the time of the broadcast in milliseconds since the
+//! epoch as a big-endian `u64`, the length of the message as a big-endian
+//! `u32`, then the message as JSON.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use protocol::{FindSource, WsMessage, WsMessageType};
+use ws::Sender;
+
+use crate::{dates, Shared};
+
+/// Far above any broadcast, so a longer frame means a corrupt recording.
+const MAX_MESSAGE_LEN: u32 = 64 * 1024;
+/// Between the end of a recording and its start again with `--loop`.
+const LOOP_PAUSE: Duration = Duration::from_secs(1);
+
+pub struct Options {
+    pub path: String,
+    pub speed: f64,
+    pub repeat: bool,
+}
+
+impl Options {
+    /// Parses `replay <file> [--speed N] [--loop]`, or no arguments at all
+    /// for a normal run.
+    pub fn from_args() -> Result<Option<Options>, &'static str> {
+        const USAGE: &str = "usage: server [replay <file> [--speed N] [--loop]]";
+
+        let mut args = env::args().skip(1);
+
+        match args.next().as_deref() {
+            None => return Ok(None),
+            Some("replay") => {}
+            Some(_) => return Err(USAGE),
+        }
+
+        let mut options = Options {
+            path: args.next().ok_or(USAGE)?,
+            speed: 1.0,
+            repeat: false,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--speed" => {
+                    options.speed = args
+                        .next()
+                        .and_then(|speed| speed.parse().ok())
+                        .filter(|&speed: &f64| speed > 0.0)
+                        .ok_or(USAGE)?
+                }
+                "--loop" => options.repeat = true,
+                _ => return Err(USAGE),
+            }
+        }
+
+        Ok(Some(options))
+    }
+}
+
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn open(path: &str) -> io::Result<Recorder> {
+        Ok(Recorder {
+            file: OpenOptions::new().create(true).append(true).open(path)?,
+        })
+    }
+
+    pub fn record(&mut self, at: u64, message: &str) {
+        let mut frame = Vec::with_capacity(12 + message.len());
+        frame.extend_from_slice(&at.to_be_bytes());
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(message.as_bytes());
+
+        if let Err(err) = self.file.write_all(&frame) {
+            eprintln!("could not record broadcast: {}", err);
+        }
+    }
+}
+
+/// Reads the next frame, or `None` at the end of the file, a frame cut
+/// short by a crash included.
+fn read_frame(reader: &mut impl Read) -> io::Result<Option<(u64, Vec<u8>)>> {
+    let mut header = [0; 12];
+
+    match reader.read_exact(&mut header) {
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+
+    let mut at = [0; 8];
+    let mut len = [0; 4];
+    at.copy_from_slice(&header[..8]);
+    len.copy_from_slice(&header[8..]);
+
+    let len = u32::from_be_bytes(len);
+
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("frame of {} bytes, over {}", len, MAX_MESSAGE_LEN),
+        ));
+    }
+
+    let mut message = vec![0; len as usize];
+
+    match reader.read_exact(&mut message) {
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        result => result.map(|()| Some((u64::from_be_bytes(at), message))),
+    }
+}
+
+/// Broadcasts the recording at `path` again, `speed` times faster than it
+/// was recorded, over and over with a pause in between if `repeat`.
+/// Messages get new sequence numbers, and finds the time they are replayed
+/// at. An empty recording is an error rather than a busy loop.
+pub fn replay(
+    path: &str,
+    speed: f64,
+    repeat: bool,
+    shared: Arc<Shared>,
+    out: Sender,
+) -> io::Result<()> {
+    loop {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut previous_at = None;
+
+        while let Some((at, message)) = read_frame(&mut reader)? {
+            if let Some(previous_at) = previous_at {
+                let delay_ms = at.saturating_sub(previous_at) as f64 / speed;
+                thread::sleep(Duration::from_millis(delay_ms as u64));
+            }
+
+            previous_at = Some(at);
+
+            if let Ok(mut ws_message) = serde_json::from_slice::<WsMessage>(&message) {
+                ws_message.seq = None;
+
+                if matches!(ws_message.msg_type, WsMessageType::New) {
+                    ws_message.at = Some(dates::now_ms());
+                    ws_message.source = Some(FindSource::ArchiveReplay);
+                }
+
+                shared.broadcast(&out, ws_message);
+            }
+        }
+
+        if previous_at.is_none() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "nothing to replay"));
+        }
+
+        if !repeat {
+            return Ok(());
+        }
+
+        thread::sleep(LOOP_PAUSE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(at: u64, len: u32, message: &[u8]) -> Vec<u8> {
+        let mut frame = at.to_be_bytes().to_vec();
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(message);
+        frame
+    }
+
+    #[test]
+    fn frames_are_read_until_one_is_cut_short() {
+        let mut recording = frame(1, 2, b"{}");
+        recording.extend(frame(2, 10, b"{}"));
+        let mut reader = &recording[..];
+
+        assert_eq!(read_frame(&mut reader).unwrap(), Some((1, b"{}".to_vec())));
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn frames_over_the_maximum_length_are_refused() {
+        let recording = frame(1, u32::MAX, b"{}");
+
+        let err = read_frame(&mut &recording[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
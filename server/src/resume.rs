@@ -0,0 +1,58 @@
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use std::collections::HashMap;
+use std::iter;
+use std::time::{Duration, Instant};
+
+/// How long after a connection drops its session can be resumed.
+pub const RESUME_GRACE: Duration = Duration::from_secs(60);
+const TOKEN_LEN: usize = 24;
+
+pub fn new_token() -> String {
+    iter::repeat(())
+        .map(|()| thread_rng().sample(Alphanumeric))
+        .take(TOKEN_LEN)
+        .collect()
+}
+
+/// Sessions of dropped connections by resume token, kept for a grace period
+/// so a client that reconnects carries on instead of starting over.
+pub struct Suspended<T> {
+    sessions: HashMap<String, (Instant, T)>,
+}
+
+impl<T> Default for Suspended<T> {
+    fn default() -> Self {
+        Suspended {
+            sessions: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Suspended<T> {
+    pub fn suspend(&mut self, token: String, session: T, now: Instant) {
+        self.expire(now);
+        self.sessions.insert(token, (now, session));
+    }
+
+    /// Takes the session suspended under `token`, if it is still within the
+    /// grace period.
+    pub fn resume(&mut self, token: &str, now: Instant) -> Option<T> {
+        self.expire(now);
+        self.sessions.remove(token).map(|(_, session)| session)
+    }
+
+    /// Sessions suspended less than `within` ago.
+    pub fn recent(&self, now: Instant, within: Duration) -> impl Iterator<Item = &T> {
+        self.sessions
+            .values()
+            .filter(move |(suspended_at, _)| now.duration_since(*suspended_at) < within)
+            .map(|(_, session)| session)
+    }
+
+    fn expire(&mut self, now: Instant) {
+        self.sessions
+            .retain(|_, (suspended_at, _)| now.duration_since(*suspended_at) < RESUME_GRACE);
+    }
+}
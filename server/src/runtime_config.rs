@@ -0,0 +1,49 @@
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use protocol::RuntimeConfig;
+use ws::Sender;
+
+use crate::Shared;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+pub fn load(path: &str) -> Result<RuntimeConfig, String> {
+    let data = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let runtime_config =
+        serde_json::from_str::<RuntimeConfig>(&data).map_err(|err| err.to_string())?;
+
+    match runtime_config.quiet_hours {
+        Some(quiet_hours) if !quiet_hours.is_valid() => {
+            Err("quiet_hours start and end must be minutes since midnight, below 1440".to_owned())
+        }
+        _ => Ok(runtime_config),
+    }
+}
+
+/// Reloads `path` whenever it gets modified and pushes the new configuration
+/// to every client.
+pub fn watch(path: String, shared: Arc<Shared>, broadcaster: Sender) {
+    thread::spawn(move || {
+        let mut modified = None::<SystemTime>;
+
+        loop {
+            let last_modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+
+            if last_modified != modified {
+                modified = last_modified;
+
+                match load(&path) {
+                    Ok(runtime_config) => shared.set_runtime_config(&broadcaster, runtime_config),
+                    Err(err) => eprintln!("could not load runtime config {}: {}", path, err),
+                }
+            }
+
+            thread::sleep(WATCH_INTERVAL);
+        }
+    });
+}
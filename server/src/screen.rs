@@ -0,0 +1,226 @@
+//! Screening of finds against lists of hashes of known abusive images,
+//! before they are broadcast or stored anywhere. Lists hold MD5 hashes,
+//! compared with the `ETag` imgur sends, which is the MD5 of the image, or
+//! SHA-256 hashes of the image, which is downloaded to compute it.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::verify::{self, Probe};
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+const REPORT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Images are not read past this, imgur caps uploads at 20 MB.
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+const MD5_HEX_LEN: usize = 32;
+const SHA256_HEX_LEN: usize = 64;
+
+/// A find whose image is on a hash list.
+#[derive(Serialize)]
+pub struct Match {
+    pub at: u64,
+    pub id: String,
+    /// The file name of the list it is on.
+    pub list: String,
+    pub hash: String,
+    pub client_id: Option<String>,
+    pub ip: Option<String>,
+    pub origin: Option<String>,
+}
+
+pub struct Screen {
+    /// The list each hash is on, by lowercase hex hash.
+    hashes: HashMap<String, String>,
+    /// Whether any hash is a SHA-256, without which images are never
+    /// downloaded.
+    has_sha256: bool,
+    report_url: Option<String>,
+}
+
+impl Screen {
+    /// Loads the lists in `HASH_LIST_FILES`, if any.
+    pub fn from_env() -> Result<Option<Screen>, String> {
+        let paths = env::var("HASH_LIST_FILES").unwrap_or_default();
+        let mut hashes = HashMap::new();
+
+        for path in paths
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+        {
+            let list = Path::new(path).file_name().map_or_else(
+                || path.to_owned(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            let content = fs::read_to_string(path)
+                .map_err(|err| format!("could not read hash list {}: {}", path, err))?;
+
+            for (line_number, line) in content.lines().enumerate() {
+                let line = line.trim();
+
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                // Lists often carry more columns after the hash.
+                let hash = line
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+
+                if !(hash.len() == MD5_HEX_LEN || hash.len() == SHA256_HEX_LEN)
+                    || !hash.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    return Err(format!(
+                        "hash list {} line {}: expected an MD5 or SHA-256 hex hash",
+                        path,
+                        line_number + 1
+                    ));
+                }
+
+                hashes.insert(hash, list.clone());
+            }
+        }
+
+        if hashes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Screen {
+            has_sha256: hashes.keys().any(|hash| hash.len() == SHA256_HEX_LEN),
+            hashes,
+            report_url: env::var("SCREEN_REPORT_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+        }))
+    }
+
+    pub fn hash_count(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Looks an image up in the lists by its `etag`, or by its SHA-256 from
+    /// `sha256` otherwise if the lists hold any, returning the list and the
+    /// hash it matched.
+    pub fn check(
+        &self,
+        etag: Option<&str>,
+        sha256: impl FnOnce() -> Result<String, String>,
+    ) -> Result<Option<(String, String)>, String> {
+        if let Some(etag) = etag.map(str::to_ascii_lowercase) {
+            if let Some(list) = self.hashes.get(&etag) {
+                return Ok(Some((list.clone(), etag)));
+            }
+        }
+
+        if !self.has_sha256 {
+            return Ok(None);
+        }
+
+        let hash = sha256()?;
+
+        Ok(self.hashes.get(&hash).map(|list| (list.clone(), hash)))
+    }
+
+    /// Tells the operator about a match, on stderr and with a `POST` of it
+    /// to `SCREEN_REPORT_URL`.
+    pub fn report(&self, report: &Match) {
+        eprintln!(
+            "{} matched hash list {} ({}), submitted by {:?} from {:?}{}",
+            report.id,
+            report.list,
+            report.hash,
+            report.client_id,
+            report.ip,
+            report
+                .origin
+                .as_ref()
+                .map(|origin| format!(" via {}", origin))
+                .unwrap_or_default()
+        );
+
+        if let Some(url) = &self.report_url {
+            let response = ureq::post(url)
+                .timeout(REPORT_TIMEOUT)
+                .send_json(serde_json::json!(report));
+
+            if !response.ok() {
+                eprintln!(
+                    "could not report {} to {}: {}",
+                    report.id,
+                    url,
+                    response
+                        .synthetic_error()
+                        .as_ref()
+                        .map_or_else(|| response.status().to_string(), ToString::to_string)
+                );
+            }
+        }
+    }
+}
+
+/// Downloads the image of `id`, answering with its SHA-256 as the content
+/// hash. Made through `Shared::request_imgur` like probes.
+pub fn download_sha256(id: &str) -> Probe {
+    let response = ureq::get(&format!("https://i.imgur.com/{}.png", id))
+        .redirects(0)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .call();
+
+    if response.synthetic_error().is_some() || response.status() != 200 {
+        return verify::answer(&response);
+    }
+
+    let mut image = Vec::new();
+
+    match response
+        .into_reader()
+        .take(MAX_IMAGE_BYTES)
+        .read_to_end(&mut image)
+    {
+        Ok(_) => Probe::Exists(Some(format!("{:x}", Sha256::digest(&image)))),
+        Err(err) => Probe::Unreachable(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(hashes: &[&str]) -> Screen {
+        let hashes = hashes
+            .iter()
+            .map(|hash| (hash.to_string(), "list.txt".to_owned()))
+            .collect::<HashMap<_, _>>();
+
+        Screen {
+            has_sha256: hashes.keys().any(|hash| hash.len() == SHA256_HEX_LEN),
+            hashes,
+            report_url: None,
+        }
+    }
+
+    #[test]
+    fn images_are_only_downloaded_for_sha256_lists() {
+        let md5 = "0123456789abcdef0123456789abcdef";
+        let sha256 = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+        let matched = screen(&[md5]).check(Some("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), || {
+            panic!("downloaded for an MD5 only list")
+        });
+        assert_eq!(matched, Ok(None));
+
+        let matched = screen(&[md5, sha256]).check(None, || Ok(sha256.to_owned()));
+        assert_eq!(
+            matched,
+            Ok(Some(("list.txt".to_owned(), sha256.to_owned())))
+        );
+    }
+}
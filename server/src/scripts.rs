@@ -0,0 +1,310 @@
+//! Filters written in Lua by the operator, run as the `scripts` content
+//! policy without recompiling the server. Every `*.lua` file in
+//! `SCRIPTS_DIR` defines a `filter(find)` function, called with a table of
+//! the find's `id`, `client_id`, `source`, `origin` and `labels`, that
+//! returns nothing to let it through, or a verdict and its reason or label:
+//! `"block"`, `"quarantine"` or `"annotate"`.
+//!
+//! Scripts only get the string, table and math libraries, without
+//! `pcall` so they cannot catch running out of time, a memory limit and a
+//! time limit per call. Changed files are reloaded on the fly.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use mlua::{Function, HookTriggers, Lua, LuaOptions, StdLib, Value};
+use protocol::FindSource;
+
+use crate::policy::{Action, ContentPolicy, Find};
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+const TIME_LIMIT: Duration = Duration::from_millis(50);
+/// How often a running script checks the time limit.
+const HOOK_INSTRUCTIONS: u32 = 10_000;
+/// Loading files from scripts is left to the server, and errors are not
+/// theirs to catch.
+const REMOVED_GLOBALS: [&str; 6] = ["dofile", "loadfile", "load", "require", "pcall", "xpcall"];
+
+struct Script {
+    name: String,
+    modified: SystemTime,
+    /// Only run by one find at a time.
+    lua: Mutex<Lua>,
+}
+
+impl Script {
+    fn load(path: &Path, modified: SystemTime) -> Result<Script, String> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let error = |err: mlua::Error| format!("script {}: {}", name, err);
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("could not read script {}: {}", name, err))?;
+        let lua = Lua::new_with(
+            StdLib::STRING | StdLib::TABLE | StdLib::MATH,
+            LuaOptions::new(),
+        )
+        .map_err(error)?;
+
+        lua.set_memory_limit(MEMORY_LIMIT).map_err(error)?;
+        limit_time(&lua);
+
+        {
+            let globals = lua.globals();
+
+            for global in &REMOVED_GLOBALS {
+                globals.set(*global, Value::Nil).map_err(error)?;
+            }
+
+            lua.load(&source).set_name(&name).exec().map_err(error)?;
+            globals.get::<_, Function>("filter").map_err(error)?;
+        }
+
+        Ok(Script {
+            name,
+            modified,
+            lua: Mutex::new(lua),
+        })
+    }
+
+    fn run(&self, find: &Find) -> mlua::Result<(Option<String>, Option<String>)> {
+        let lua = self.lua.lock().unwrap();
+        limit_time(&lua);
+
+        let table = lua.create_table()?;
+        table.set("id", find.id.as_str())?;
+        table.set("client_id", find.client_id.as_deref())?;
+        table.set("source", find.source.map(FindSource::label))?;
+        table.set("origin", find.origin.as_deref())?;
+        table.set("labels", find.labels.clone())?;
+
+        let filter: Function = lua.globals().get("filter")?;
+
+        filter.call(table)
+    }
+}
+
+/// Interrupts whatever `lua` runs next once it took `TIME_LIMIT`, and
+/// anything it runs after that.
+fn limit_time(lua: &Lua) {
+    let deadline = Instant::now() + TIME_LIMIT;
+
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(HOOK_INSTRUCTIONS),
+        move |_, _| {
+            if Instant::now() >= deadline {
+                Err(mlua::Error::runtime("took too long"))
+            } else {
+                Ok(())
+            }
+        },
+    );
+}
+
+pub struct Scripts {
+    /// In file name order, held only to look them up.
+    scripts: Arc<Mutex<Vec<Arc<Script>>>>,
+}
+
+impl Scripts {
+    pub fn from_env() -> Result<Scripts, String> {
+        let dir = PathBuf::from(
+            env::var("SCRIPTS_DIR").map_err(|_| "the scripts policy needs SCRIPTS_DIR")?,
+        );
+        let scripts = lua_files(&dir)
+            .map_err(|err| format!("could not read {}: {}", dir.display(), err))?
+            .into_iter()
+            .map(|(path, modified)| Script::load(&path, modified).map(Arc::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        let scripts = Arc::new(Mutex::new(scripts));
+
+        let reloaded = scripts.clone();
+        thread::spawn(move || loop {
+            thread::sleep(RELOAD_INTERVAL);
+            reload(&dir, &reloaded);
+        });
+
+        Ok(Scripts { scripts })
+    }
+}
+
+/// Picks up added, changed and removed scripts. A script that no longer
+/// loads is kept as it was.
+fn reload(dir: &Path, scripts: &Mutex<Vec<Arc<Script>>>) {
+    let files = match lua_files(dir) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("could not read {}: {}", dir.display(), err);
+            return;
+        }
+    };
+    let mut previous = scripts.lock().unwrap().clone();
+    let is_unchanged = files.len() == previous.len()
+        && files
+            .iter()
+            .zip(previous.iter())
+            .all(|((path, modified), script)| {
+                path.file_name() == Some(script.name.as_ref()) && *modified == script.modified
+            });
+
+    if is_unchanged {
+        return;
+    }
+
+    // Loaded without holding the lock, finds keep using the old scripts
+    // until then.
+    let mut loaded = Vec::new();
+
+    for (path, modified) in files {
+        let old = previous
+            .iter()
+            .position(|script| path.file_name() == Some(script.name.as_ref()))
+            .map(|index| previous.remove(index));
+
+        match old {
+            Some(old) if old.modified == modified => loaded.push(old),
+            old => match Script::load(&path, modified) {
+                Ok(script) => {
+                    println!("loaded script {}", script.name);
+                    loaded.push(Arc::new(script));
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    loaded.extend(old);
+                }
+            },
+        }
+    }
+
+    *scripts.lock().unwrap() = loaded;
+}
+
+/// The `*.lua` files in `dir` with when they were last modified, by name.
+fn lua_files(dir: &Path) -> io::Result<Vec<(PathBuf, SystemTime)>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "lua")
+        {
+            let modified = fs::metadata(&path)?.modified()?;
+            files.push((path, modified));
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+impl ContentPolicy for Scripts {
+    fn name(&self) -> &'static str {
+        "scripts"
+    }
+
+    /// Runs every script until one blocks or quarantines the find. Scripts
+    /// that fail let it through.
+    fn check(&self, find: &Find) -> Action {
+        let mut labels = find.labels.clone();
+        let scripts = self.scripts.lock().unwrap().clone();
+
+        for script in scripts {
+            let (verdict, detail) = match script.run(find) {
+                Ok(answer) => answer,
+                Err(err) => {
+                    eprintln!("script {} failed on {}: {}", script.name, find.id, err);
+                    continue;
+                }
+            };
+            let detail = detail.unwrap_or_default();
+
+            match verdict.as_deref() {
+                None | Some("accept") => {}
+                Some("block") => return Action::Block(detail),
+                Some("quarantine") => {
+                    return Action::Quarantine(format!("{}: {}", script.name, detail))
+                }
+                Some("annotate") => labels.push(detail),
+                Some(verdict) => eprintln!(
+                    "script {} answered unknown verdict \"{}\"",
+                    script.name, verdict
+                ),
+            }
+        }
+
+        if labels.len() == find.labels.len() {
+            Action::Accept
+        } else {
+            Action::Rewrite(Find {
+                labels,
+                ..find.clone()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::ImageId;
+
+    fn script(source: &str) -> Script {
+        let path = env::temp_dir().join(format!("scripts-test-{}.lua", rand::random::<u64>()));
+        fs::write(&path, source).unwrap();
+        let script = Script::load(&path, SystemTime::now());
+        fs::remove_file(&path).unwrap();
+
+        script.unwrap()
+    }
+
+    fn find() -> Find {
+        Find {
+            id: ImageId::new("abcde").unwrap(),
+            client_id: None,
+            source: None,
+            origin: None,
+            labels: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn endless_loops_are_interrupted() {
+        let script = script("function filter(find) while true do end end");
+        let started_at = Instant::now();
+
+        assert!(script.run(&find()).is_err());
+        assert!(started_at.elapsed() < TIME_LIMIT * 10);
+    }
+
+    #[test]
+    fn running_out_of_time_cannot_be_caught() {
+        let script = script(
+            "function filter(find) while true do pcall(function() while true do end end) end end",
+        );
+        let started_at = Instant::now();
+
+        assert!(script.run(&find()).is_err());
+        assert!(started_at.elapsed() < TIME_LIMIT * 10);
+    }
+
+    #[test]
+    fn verdicts_are_returned() {
+        let script =
+            script("function filter(find) if find.id == 'abcde' then return 'block', 'no' end end");
+
+        assert_eq!(
+            script.run(&find()).unwrap(),
+            (Some("block".to_owned()), Some("no".to_owned()))
+        );
+    }
+}
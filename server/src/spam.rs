@@ -0,0 +1,150 @@
+use std::collections::{HashMap, VecDeque};
+
+use protocol::ID_MIN_LEN;
+
+const RATE_WINDOW_SECS: u64 = 60;
+const MAX_FINDS_PER_WINDOW: usize = 10;
+const RECENT_CONTENT: usize = 1000;
+const MAX_SAME_CONTENT: usize = 3;
+
+/// Heuristics flagging submissions that are unlikely to be honest finds.
+#[derive(Default)]
+pub struct SpamFilter {
+    finds_by_source: HashMap<String, VecDeque<u64>>,
+    recent_content: VecDeque<String>,
+}
+
+impl SpamFilter {
+    /// Checks a submission from `sources`, the connection, IP and client ID
+    /// it came from, and returns why it looks suspicious, if it does. Each
+    /// source is counted on its own, so switching client IDs does not reset
+    /// the count.
+    pub fn check_submission(
+        &mut self,
+        sources: &[String],
+        id: &str,
+        now: u64,
+    ) -> Option<&'static str> {
+        let since = now.saturating_sub(RATE_WINDOW_SECS);
+
+        self.finds_by_source.retain(|_, finds| {
+            while finds.front().map_or(false, |&at| at < since) {
+                finds.pop_front();
+            }
+
+            !finds.is_empty()
+        });
+
+        let mut is_too_many = false;
+
+        for source in sources {
+            let finds = self.finds_by_source.entry(source.clone()).or_default();
+            finds.push_back(now);
+            is_too_many |= finds.len() > MAX_FINDS_PER_WINDOW;
+        }
+
+        if is_too_many {
+            Some("too many finds from the same client")
+        } else if !looks_like_imgur_id(id) {
+            Some("ID does not look like an imgur ID")
+        } else {
+            None
+        }
+    }
+
+    /// Checks the content hash imgur reported for a find, flagging content
+    /// found under several IDs recently.
+    pub fn check_content(&mut self, content_hash: &str) -> Option<&'static str> {
+        let seen = self
+            .recent_content
+            .iter()
+            .filter(|hash| *hash == content_hash)
+            .count();
+
+        self.recent_content.push_back(content_hash.to_owned());
+
+        if self.recent_content.len() > RECENT_CONTENT {
+            self.recent_content.pop_front();
+        }
+
+        if seen >= MAX_SAME_CONTENT {
+            Some("same content found under several IDs")
+        } else {
+            None
+        }
+    }
+}
+
+/// Imgur IDs have the lengths the protocol accepts and mixed letters and
+/// digits, an all-digit ID from 6 characters being under a one in 50 000
+/// draw.
+fn looks_like_imgur_id(id: &str) -> bool {
+    protocol::is_valid_id(id) && (id.len() == ID_MIN_LEN || !id.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(connection: u32, ip: &str, client_id: &str) -> Vec<String> {
+        vec![
+            format!("connection:{}", connection),
+            format!("ip:{}", ip),
+            format!("client:{}", client_id),
+        ]
+    }
+
+    #[test]
+    fn too_many_finds_from_one_client_are_flagged() {
+        let mut spam = SpamFilter::default();
+
+        for _ in 0..MAX_FINDS_PER_WINDOW {
+            assert_eq!(
+                spam.check_submission(&sources(1, "a", "a"), "abcde", 0),
+                None
+            );
+        }
+
+        assert!(spam
+            .check_submission(&sources(1, "a", "a"), "abcde", 0)
+            .is_some());
+        // Once the window passed, it is forgotten.
+        assert_eq!(
+            spam.check_submission(&sources(1, "a", "a"), "abcde", RATE_WINDOW_SECS + 1),
+            None
+        );
+    }
+
+    #[test]
+    fn switching_client_ids_or_connections_does_not_reset_the_count() {
+        let mut spam = SpamFilter::default();
+
+        for n in 0..MAX_FINDS_PER_WINDOW as u32 {
+            let client_id = format!("client{}", n);
+            assert_eq!(
+                spam.check_submission(&sources(n, "same ip", &client_id), "abcde", 0),
+                None
+            );
+        }
+
+        assert!(spam
+            .check_submission(&sources(100, "same ip", "fresh"), "abcde", 0)
+            .is_some());
+        assert_eq!(
+            spam.check_submission(&sources(101, "other ip", "other"), "abcde", 0),
+            None
+        );
+    }
+
+    #[test]
+    fn ids_of_every_valid_length_are_accepted() {
+        assert!(looks_like_imgur_id("abcde"));
+        assert!(looks_like_imgur_id("abcde1"));
+        assert!(looks_like_imgur_id("abcde12"));
+        assert!(looks_like_imgur_id("12345"));
+        assert!(!looks_like_imgur_id("123456"));
+        assert!(!looks_like_imgur_id("1234567"));
+        assert!(!looks_like_imgur_id("abcd"));
+        assert!(!looks_like_imgur_id("abcde123"));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::store::{self, StoreError};
+
+const USER_MILESTONES: [u64; 7] = [10, 25, 50, 100, 250, 500, 1000];
+const SAVE_EVERY_FINDS: u64 = 10;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub images_found: u64,
+    pub peak_users: u64,
+}
+
+impl Stats {
+    pub fn load(path: &str) -> Result<Stats, StoreError> {
+        store::load(path)
+    }
+
+    /// Counts a new find and returns the milestone it reached, if any.
+    pub fn record_find(&mut self) -> Option<String> {
+        self.images_found += 1;
+
+        if is_images_milestone(self.images_found) {
+            Some(format!("{} images found!", self.images_found))
+        } else {
+            None
+        }
+    }
+
+    /// Tracks the peak number of concurrent users and returns the milestone
+    /// it reached the first time it gets crossed.
+    pub fn record_users(&mut self, users: u64) -> Option<String> {
+        if users <= self.peak_users {
+            return None;
+        }
+
+        let previous_peak = self.peak_users;
+        self.peak_users = users;
+
+        USER_MILESTONES
+            .iter()
+            .rev()
+            .find(|&&milestone| previous_peak < milestone && milestone <= users)
+            .map(|milestone| format!("{} people on the wall at once!", milestone))
+    }
+
+    pub fn should_save(&self) -> bool {
+        self.images_found % SAVE_EVERY_FINDS == 0
+    }
+}
+
+fn is_images_milestone(images_found: u64) -> bool {
+    match images_found {
+        1000 | 10_000 => true,
+        n => n >= 100_000 && n % 100_000 == 0,
+    }
+}
@@ -0,0 +1,44 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("could not read {path}: {source}")]
+    Read { path: String, source: io::Error },
+    #[error("could not parse {path}: {source}")]
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("could not write {path}: {source}")]
+    Write { path: String, source: io::Error },
+    #[error("could not serialize {path}: {source}")]
+    Serialize {
+        path: String,
+        source: serde_json::Error,
+    },
+}
+
+/// Reads the JSON store at `path`, empty if there is no such file yet. A
+/// file that cannot be read is an error, not a fresh start that would
+/// overwrite it on the next save.
+pub fn load<T: DeserializeOwned + Default>(path: &str) -> Result<T, StoreError> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(T::default()),
+        Err(source) => {
+            return Err(StoreError::Read {
+                path: path.to_owned(),
+                source,
+            })
+        }
+    };
+
+    serde_json::from_str(&data).map_err(|source| StoreError::Parse {
+        path: path.to_owned(),
+        source,
+    })
+}
@@ -0,0 +1,142 @@
+//! Events as newline-delimited JSON over a chunked HTTP response, for
+//! scripts without a websocket library. ws-rs closes the connection after
+//! answering a plain HTTP request, so the stream has its own listener.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use protocol::Counts;
+
+use crate::Shared;
+
+/// Events a subscriber can lag behind before it misses some.
+const BACKLOG: usize = 256;
+/// Each connection holds a thread, those over this are answered 503.
+const MAX_CONNECTIONS: usize = 100;
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+/// How long a client has to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a subscriber has to take each event before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StreamEvent<'a> {
+    Find { id: &'a str, at: Option<u64> },
+    Counts(Counts),
+    Milestone { text: &'a str },
+}
+
+#[derive(Default)]
+pub struct Streams {
+    subscribers: Mutex<Vec<SyncSender<String>>>,
+}
+
+impl Streams {
+    /// Sends `event` to every subscriber, skipping those too far behind.
+    pub fn publish(&self, event: &StreamEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+
+        if subscribers.is_empty() {
+            return;
+        }
+
+        if let Ok(line) = serde_json::to_string(event) {
+            subscribers.retain(|subscriber| {
+                !matches!(
+                    subscriber.try_send(line.clone()),
+                    Err(TrySendError::Disconnected(_))
+                )
+            });
+        }
+    }
+
+    fn subscribe(&self) -> Receiver<String> {
+        let (sender, receiver) = mpsc::sync_channel(BACKLOG);
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+pub fn listen(addr: &str, shared: Arc<Shared>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            if connections.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+                // Into an empty send buffer, so it cannot hold up accepting.
+                let _ = stream.set_nonblocking(true).and_then(|()| {
+                    stream.write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                });
+                continue;
+            }
+
+            connections.fetch_add(1, Ordering::SeqCst);
+
+            let shared = shared.clone();
+            let connections = connections.clone();
+            thread::spawn(move || {
+                let _ = serve(stream, &shared);
+                connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Answers `GET /api/stream` with the current counts then every event until
+/// the client goes away, and anything else with a 404.
+fn serve(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut header = String::new();
+
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut request = request_line.split_whitespace();
+    let method = request.next().unwrap_or_default();
+    let path = request
+        .next()
+        .and_then(|resource| resource.split('?').next())
+        .unwrap_or_default();
+
+    if method != "GET" || path != "/api/stream" {
+        return stream.write_all(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 15\r\nConnection: close\r\n\r\n404 - Not Found",
+        );
+    }
+
+    let events = shared.streams.subscribe();
+
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: application/x-ndjson\r\n\
+          Transfer-Encoding: chunked\r\n\
+          Cache-Control: no-cache\r\n\
+          Access-Control-Allow-Origin: *\r\n\r\n",
+    )?;
+
+    let counts = serde_json::to_string(&StreamEvent::Counts(shared.counts()))?;
+
+    for line in Some(counts).into_iter().chain(events) {
+        write!(stream, "{:x}\r\n{}\n\r\n", line.len() + 1, line)?;
+    }
+
+    Ok(())
+}
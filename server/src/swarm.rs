@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+
+/// How often the probes reported by clients are checked for 429s.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How long every client backs off once imgur throttles too many probes.
+pub const BACKOFF: Duration = Duration::from_secs(5 * 60);
+/// Below this many probes in an interval, a few 429s are only noise.
+const MIN_REQUESTS: u64 = 100;
+const MAX_THROTTLED_RATIO: f64 = 0.1;
+pub const REASON: &str = "imgur is rate limiting — backing off";
+
+/// Probes reported by clients and how many of them imgur answered with a
+/// 429, to slow every client down together when imgur pushes back.
+#[derive(Default)]
+pub struct SwarmThrottle {
+    requests: u64,
+    throttled: u64,
+    backing_off_until: Option<Instant>,
+}
+
+impl SwarmThrottle {
+    pub fn record_requests(&mut self, requests: u64) {
+        self.requests += requests;
+    }
+
+    pub fn record_throttled(&mut self, throttled: u64) {
+        self.throttled += throttled;
+    }
+
+    /// Called every `CHECK_INTERVAL`, returns the seconds clients should
+    /// back off for when it changes: `BACKOFF` while too many probes are
+    /// throttled, 0 once it is over.
+    pub fn check(&mut self, now: Instant) -> Option<u64> {
+        let requests = std::mem::take(&mut self.requests);
+        let throttled = std::mem::take(&mut self.throttled).min(requests);
+
+        if requests >= MIN_REQUESTS && throttled as f64 / requests as f64 >= MAX_THROTTLED_RATIO {
+            self.backing_off_until = Some(now + BACKOFF);
+            Some(BACKOFF.as_secs())
+        } else if self.backing_off_until.map_or(false, |until| now >= until) {
+            self.backing_off_until = None;
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Seconds left to back off for, for clients connecting meanwhile.
+    pub fn secs_left(&self, now: Instant) -> u64 {
+        self.backing_off_until
+            .map_or(0, |until| until.saturating_duration_since(now).as_secs())
+    }
+}
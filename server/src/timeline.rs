@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+
+use crate::dates;
+use crate::store::{self, StoreError};
+
+const HOUR_SECS: u64 = 3600;
+const DAY_SECS: u64 = 86_400;
+const RETENTION_HOURS: usize = 365 * 24;
+
+#[derive(Serialize, Deserialize)]
+pub struct Bucket {
+    pub start: u64,
+    pub finds: u64,
+    pub peak_users: u64,
+    pub rejected: u64,
+    /// Probes reported by clients, an estimate since they report averages.
+    #[serde(default)]
+    pub requests: u64,
+}
+
+#[derive(Default)]
+pub struct Day {
+    pub start: u64,
+    pub finds: u64,
+    pub peak_users: u64,
+    pub requests: u64,
+}
+
+/// Hourly activity counters, kept for a year so they can be exported.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Timeline {
+    buckets: VecDeque<Bucket>,
+    #[serde(skip)]
+    users: u64,
+}
+
+impl Timeline {
+    pub fn load(path: &str) -> Result<Timeline, StoreError> {
+        store::load(path)
+    }
+
+    /// Returns the bucket of the hour containing `at`, and whether it had to
+    /// be opened, which is when the previous hours are worth saving.
+    fn bucket(&mut self, at: u64) -> (&mut Bucket, bool) {
+        let start = at - at % HOUR_SECS;
+        let is_new = self.buckets.back().map_or(true, |last| last.start < start);
+
+        if is_new {
+            self.buckets.push_back(Bucket {
+                start,
+                finds: 0,
+                peak_users: self.users,
+                rejected: 0,
+                requests: 0,
+            });
+
+            while self.buckets.len() > RETENTION_HOURS {
+                self.buckets.pop_front();
+            }
+        }
+
+        (self.buckets.back_mut().unwrap(), is_new)
+    }
+
+    pub fn record_find(&mut self, at: u64) -> bool {
+        let (bucket, is_new) = self.bucket(at);
+        bucket.finds += 1;
+        is_new
+    }
+
+    pub fn record_rejection(&mut self, at: u64) -> bool {
+        let (bucket, is_new) = self.bucket(at);
+        bucket.rejected += 1;
+        is_new
+    }
+
+    pub fn record_requests(&mut self, at: u64, requests: u64) -> bool {
+        let (bucket, is_new) = self.bucket(at);
+        bucket.requests += requests;
+        is_new
+    }
+
+    pub fn record_users(&mut self, at: u64, users: u64) -> bool {
+        self.users = users;
+
+        let (bucket, is_new) = self.bucket(at);
+        bucket.peak_users = bucket.peak_users.max(users);
+        is_new
+    }
+
+    pub fn purge_before(&mut self, at: u64) -> bool {
+        let buckets = self.buckets.len();
+
+        while self
+            .buckets
+            .front()
+            .map_or(false, |bucket| bucket.start < at)
+        {
+            self.buckets.pop_front();
+        }
+
+        self.buckets.len() != buckets
+    }
+
+    /// The buckets starting within `[from, to)`.
+    pub fn range(&self, from: u64, to: u64) -> Vec<&Bucket> {
+        self.buckets
+            .iter()
+            .filter(|bucket| from <= bucket.start && bucket.start < to)
+            .collect()
+    }
+
+    /// Sums the buckets into `days` days ending today, including days
+    /// without any activity.
+    pub fn daily(&self, now: u64, days: u64) -> Vec<Day> {
+        let first = now - now % DAY_SECS - (days - 1) * DAY_SECS;
+        let mut daily = (0..days)
+            .map(|day| Day {
+                start: first + day * DAY_SECS,
+                ..Day::default()
+            })
+            .collect::<Vec<_>>();
+
+        for bucket in self.range(first, u64::MAX) {
+            if let Some(day) = daily.get_mut(((bucket.start - first) / DAY_SECS) as usize) {
+                day.finds += bucket.finds;
+                day.peak_users = day.peak_users.max(bucket.peak_users);
+                day.requests += bucket.requests;
+            }
+        }
+
+        daily
+    }
+}
+
+pub fn to_csv(buckets: &[&Bucket]) -> String {
+    let mut csv = String::from("hour,finds,peak_users,rejected,requests\n");
+
+    for bucket in buckets {
+        csv.push_str(&format!(
+            "{}T{:02}:00:00Z,{},{},{},{}\n",
+            dates::date(bucket.start),
+            bucket.start % DAY_SECS / HOUR_SECS,
+            bucket.finds,
+            bucket.peak_users,
+            bucket.rejected,
+            bucket.requests
+        ));
+    }
+
+    csv
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What imgur answered to a probe.
+pub enum Probe {
+    /// The image exists, with the hash of its content when imgur sent one.
+    Exists(Option<String>),
+    Missing,
+    /// Imgur answered 429, with how long it asked to wait if it did.
+    Throttled(Option<Duration>),
+    Unreachable(String),
+}
+
+/// Checks that `id` exists on imgur and gets the hash of its content from
+/// the `ETag` header. Missing images answer with a redirect to
+/// `removed.png`, so redirects are not followed.
+pub fn probe(id: &str) -> Probe {
+    let response = ureq::head(&format!("https://i.imgur.com/{}.png", id))
+        .redirects(0)
+        .timeout(PROBE_TIMEOUT)
+        .call();
+
+    answer(&response)
+}
+
+/// What a response about an image on imgur says about it.
+pub fn answer(response: &ureq::Response) -> Probe {
+    if let Some(err) = response.synthetic_error() {
+        Probe::Unreachable(err.to_string())
+    } else if response.status() == 200 {
+        Probe::Exists(
+            response
+                .header("ETag")
+                .map(|etag| etag.trim_matches('"').to_owned()),
+        )
+    } else if response.status() == 429 {
+        Probe::Throttled(
+            response
+                .header("Retry-After")
+                .and_then(|secs| secs.trim().parse().ok())
+                .map(Duration::from_secs),
+        )
+    } else {
+        Probe::Missing
+    }
+}
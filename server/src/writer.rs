@@ -0,0 +1,374 @@
+//! Writes the stores to disk on a thread of their own, so saving never holds
+//! up broadcasts. Writes are taken in batches where only the latest snapshot
+//! of each file is written. When the queue is full, snapshots wait aside to
+//! be written in order with the rest, while archive lines are dropped.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::store::StoreError;
+
+const QUEUE_LEN: usize = 1024;
+const MAX_BATCH: usize = 256;
+/// How often snapshots that did not fit in the queue are looked for when
+/// nothing else comes.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum Write {
+    /// The whole content of a file.
+    Replace { path: String, data: String },
+    /// A line to add at the end of a file.
+    Append { path: String, line: String },
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct WriterStats {
+    /// Writes waiting in the queue.
+    pub queue_depth: u64,
+    pub queue_depth_max: u64,
+    pub batches: u64,
+    pub files_written: u64,
+    pub write_ms_total: u64,
+    pub write_ms_max: u64,
+    /// Snapshots skipped because a later one of the same file was written.
+    pub coalesced: u64,
+    /// Snapshots set aside because the queue was full.
+    pub deferred: u64,
+    /// Archive lines lost because the queue was full.
+    pub dropped: u64,
+}
+
+/// Writes with the order they were asked for in.
+type Sequenced = (u64, Write);
+
+pub struct Writer {
+    sender: SyncSender<Sequenced>,
+    /// Snapshots that did not fit in the queue, the latest by path.
+    deferred: Arc<Mutex<HashMap<String, (u64, String)>>>,
+    stats: Arc<Mutex<WriterStats>>,
+    next_seq: AtomicU64,
+}
+
+impl Writer {
+    pub fn start() -> Writer {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+        let writer = Writer {
+            sender,
+            deferred: Arc::default(),
+            stats: Arc::default(),
+            next_seq: AtomicU64::new(0),
+        };
+
+        let deferred = writer.deferred.clone();
+        let stats = writer.stats.clone();
+        thread::spawn(move || run(&receiver, &deferred, &stats));
+
+        writer
+    }
+
+    /// Writes `value` as JSON to `path`.
+    pub fn save<T: Serialize>(&self, path: &str, value: &T) {
+        match serde_json::to_string(value) {
+            Ok(data) => self.replace(path, data),
+            Err(source) => eprintln!(
+                "{}",
+                StoreError::Serialize {
+                    path: path.to_owned(),
+                    source,
+                }
+            ),
+        }
+    }
+
+    pub fn replace(&self, path: &str, data: String) {
+        self.send(Write::Replace {
+            path: path.to_owned(),
+            data,
+        });
+    }
+
+    pub fn append(&self, path: &str, line: String) {
+        self.send(Write::Append {
+            path: path.to_owned(),
+            line,
+        });
+    }
+
+    pub fn stats(&self) -> WriterStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn send(&self, write: Write) {
+        let mut stats = self.stats.lock().unwrap();
+        // Taken under the stats lock, so writes are queued in order.
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+
+        match self.sender.try_send((seq, write)) {
+            Ok(()) => {
+                stats.queue_depth += 1;
+                stats.queue_depth_max = stats.queue_depth_max.max(stats.queue_depth);
+            }
+            Err(TrySendError::Full((_, Write::Replace { path, data }))) => {
+                stats.deferred += 1;
+                self.deferred.lock().unwrap().insert(path, (seq, data));
+            }
+            Err(TrySendError::Full((_, Write::Append { path, .. }))) => {
+                stats.dropped += 1;
+                eprintln!("write queue full, dropped a line of {}", path);
+            }
+            Err(TrySendError::Disconnected(_)) => eprintln!("the writer thread is gone"),
+        }
+    }
+}
+
+fn run(
+    receiver: &Receiver<Sequenced>,
+    deferred: &Mutex<HashMap<String, (u64, String)>>,
+    stats: &Mutex<WriterStats>,
+) {
+    loop {
+        let mut batch = match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(write) => vec![write],
+            Err(RecvTimeoutError::Timeout) => Vec::new(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+
+        let mut is_drained = false;
+
+        while batch.len() < MAX_BATCH {
+            match receiver.try_recv() {
+                Ok(write) => batch.push(write),
+                Err(_) => {
+                    is_drained = true;
+                    break;
+                }
+            }
+        }
+
+        stats.lock().unwrap().queue_depth -= batch.len() as u64;
+
+        // Snapshots set aside go in once everything queued before them was
+        // received, in order, so lines appended after them are kept and
+        // older writes never land over them.
+        let last_seq = batch.last().map(|(seq, _)| *seq);
+        let mut ready = Vec::new();
+
+        deferred.lock().unwrap().retain(|path, (seq, data)| {
+            let is_ready = is_drained || Some(*seq) < last_seq;
+
+            if is_ready {
+                ready.push((
+                    *seq,
+                    Write::Replace {
+                        path: path.clone(),
+                        data: mem::take(data),
+                    },
+                ));
+            }
+
+            !is_ready
+        });
+
+        if !ready.is_empty() {
+            batch.extend(ready);
+            batch.sort_by_key(|(seq, _)| *seq);
+        }
+
+        if !batch.is_empty() {
+            write_batch(batch.into_iter().map(|(_, write)| write).collect(), stats);
+        }
+    }
+}
+
+fn write_batch(batch: Vec<Write>, stats: &Mutex<WriterStats>) {
+    let mut replaces = BTreeMap::new();
+    let mut appends = BTreeMap::<String, String>::new();
+    let mut coalesced = 0;
+
+    for write in batch {
+        match write {
+            Write::Replace { path, data } => {
+                // The snapshot already holds the lines appended before it.
+                appends.remove(&path);
+
+                if replaces.insert(path, data).is_some() {
+                    coalesced += 1;
+                }
+            }
+            Write::Append { path, line } => {
+                let data = match replaces.get_mut(&path) {
+                    Some(data) => data,
+                    None => appends.entry(path).or_default(),
+                };
+
+                data.push_str(&line);
+                data.push('\n');
+            }
+        }
+    }
+
+    let started_at = Instant::now();
+    let files_written = (replaces.len() + appends.len()) as u64;
+
+    for (path, data) in replaces {
+        if let Err(source) = write_atomically(&path, &data) {
+            eprintln!("{}", StoreError::Write { path, source });
+        }
+    }
+
+    for (path, lines) in appends {
+        let appended = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+
+        if let Err(source) = appended {
+            eprintln!("{}", StoreError::Write { path, source });
+        }
+    }
+
+    let elapsed_ms = started_at.elapsed().as_millis() as u64;
+    let mut stats = stats.lock().unwrap();
+
+    stats.batches += 1;
+    stats.files_written += files_written;
+    stats.write_ms_total += elapsed_ms;
+    stats.write_ms_max = stats.write_ms_max.max(elapsed_ms);
+    stats.coalesced += coalesced;
+}
+
+/// Writes `data` to `<path>.tmp`, then moves it over `path`, so a crash
+/// mid-write leaves the previous snapshot rather than half of the new one.
+fn write_atomically(path: &str, data: &str) -> io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = File::create(&tmp_path)?;
+
+    file.write_all(data.as_bytes())?;
+    file.sync_all()?;
+
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::Path;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("writer-test-{}-{}", rand::random::<u64>(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn snapshots_replace_files_without_leaving_temporary_ones() {
+        let path = temp_path("store.json");
+        let stats = Mutex::default();
+
+        fs::write(&path, "old and longer").unwrap();
+        write_batch(
+            vec![
+                Write::Replace {
+                    path: path.clone(),
+                    data: "first".to_owned(),
+                },
+                Write::Replace {
+                    path: path.clone(),
+                    data: "second".to_owned(),
+                },
+            ],
+            &stats,
+        );
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+        assert_eq!(stats.lock().unwrap().coalesced, 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lines_are_appended_after_the_snapshot_before_them() {
+        let path = temp_path("archive.jsonl");
+        let stats = Mutex::default();
+
+        write_batch(
+            vec![
+                Write::Append {
+                    path: path.clone(),
+                    line: "dropped".to_owned(),
+                },
+                Write::Replace {
+                    path: path.clone(),
+                    data: "snapshot\n".to_owned(),
+                },
+                Write::Append {
+                    path: path.clone(),
+                    line: "kept".to_owned(),
+                },
+            ],
+            &stats,
+        );
+        write_batch(
+            vec![Write::Append {
+                path: path.clone(),
+                line: "appended".to_owned(),
+            }],
+            &stats,
+        );
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "snapshot\nkept\nappended\n"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lines_queued_after_a_deferred_snapshot_are_kept() {
+        let path = temp_path("archive.jsonl");
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+        let deferred = Mutex::new(HashMap::new());
+        let stats = Mutex::new(WriterStats {
+            queue_depth: 2,
+            ..WriterStats::default()
+        });
+
+        for (seq, line) in [(0, "in the snapshot"), (2, "after the snapshot")] {
+            let write = Write::Append {
+                path: path.clone(),
+                line: line.to_owned(),
+            };
+            sender.send((seq, write)).unwrap();
+        }
+
+        deferred
+            .lock()
+            .unwrap()
+            .insert(path.clone(), (1, "snapshot\n".to_owned()));
+        drop(sender);
+
+        run(&receiver, &deferred, &stats);
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "snapshot\nafter the snapshot\n"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Runs the server binary on a free port and plays clients against it over
+//! websockets.
+
+use std::env;
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use protocol::{FindSource, WsMessage, WsMessageType};
+use ws::{Handler, Handshake, Message};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Server {
+    child: Child,
+    data_dir: PathBuf,
+    addr: String,
+}
+
+impl Server {
+    /// Runs the server with `args`, and `envs` on top of a fresh data
+    /// directory and no proof of work.
+    fn start(name: &str, args: &[&str], envs: &[(&str, &str)]) -> Server {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .to_string();
+        let data_dir = env::temp_dir().join(format!("server-e2e-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&data_dir);
+
+        let child = Command::new(env!("CARGO_BIN_EXE_server"))
+            .env("WS_LISTEN_ADDR", &addr)
+            .env("DATA_DIR", &data_dir)
+            .env("POW_DIFFICULTY", "0")
+            .envs(envs.iter().copied())
+            .args(args)
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let started = Instant::now();
+
+        while TcpStream::connect(&addr).is_err() {
+            assert!(started.elapsed() < STARTUP_TIMEOUT, "server did not start");
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        Server {
+            child,
+            data_dir,
+            addr,
+        }
+    }
+
+    /// Connects a client that sends `messages` once connected, and returns
+    /// what the server sends it after its first message, which is sent once
+    /// the client counts as watching.
+    fn connect(&self, messages: Vec<WsMessage>) -> Receiver<WsMessage> {
+        let url = format!("ws://{}/ws", self.addr);
+        let (sender, receiver) = mpsc::channel();
+        let messages = messages
+            .iter()
+            .map(|ws_message| serde_json::to_string(ws_message).unwrap())
+            .collect::<Vec<_>>();
+
+        thread::spawn(move || {
+            // Errors end the thread, and the test waiting on it times out.
+            let _ = ws::connect(url, move |out| Client {
+                out,
+                messages: messages.clone(),
+                received: sender.clone(),
+            });
+        });
+
+        receiver
+            .recv_timeout(MESSAGE_TIMEOUT)
+            .expect("the server did not greet the client");
+
+        receiver
+    }
+}
+
+struct Client {
+    out: ws::Sender,
+    /// Sent once connected.
+    messages: Vec<String>,
+    received: Sender<WsMessage>,
+}
+
+impl Handler for Client {
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        for message in &self.messages {
+            self.out.send(message.as_str())?;
+        }
+
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: Message) -> ws::Result<()> {
+        if let Ok(ws_message) = serde_json::from_str(msg.as_text()?) {
+            let _ = self.received.send(ws_message);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+fn find(id: &str) -> WsMessage {
+    WsMessage {
+        text: Some(id.to_owned()),
+        ..WsMessage::new(WsMessageType::New)
+    }
+}
+
+/// The next broadcast find, skipping counts and everything else.
+fn next_find(messages: &Receiver<WsMessage>) -> String {
+    next_find_message(messages).text.unwrap()
+}
+
+fn next_find_message(messages: &Receiver<WsMessage>) -> WsMessage {
+    let started = Instant::now();
+
+    loop {
+        let timeout = MESSAGE_TIMEOUT
+            .checked_sub(started.elapsed())
+            .expect("no find was broadcast");
+        let ws_message = messages.recv_timeout(timeout).unwrap();
+
+        if let WsMessageType::New = ws_message.msg_type {
+            return ws_message;
+        }
+    }
+}
+
+#[test]
+fn finds_are_broadcast_to_every_client() {
+    let server = Server::start("broadcast", &[], &[]);
+    let watcher = server.connect(Vec::new());
+    let finder = server.connect(vec![find("not an id!"), find("abcd123")]);
+
+    assert_eq!(next_find(&watcher), "abcd123");
+    assert_eq!(next_find(&finder), "abcd123");
+}
+
+#[test]
+fn duplicate_finds_are_broadcast_once() {
+    let server = Server::start("duplicates", &[], &[]);
+    let watcher = server.connect(Vec::new());
+    let _finder = server.connect(vec![find("abcd123"), find("abcd123"), find("efgh456")]);
+
+    assert_eq!(next_find(&watcher), "abcd123");
+    assert_eq!(next_find(&watcher), "efgh456");
+}
+
+#[test]
+fn recorded_finds_are_replayed() {
+    let recording = env::temp_dir().join(format!("server-e2e-{}.replay", std::process::id()));
+    let recording = recording.to_str().unwrap();
+    let _ = fs::remove_file(recording);
+
+    let server = Server::start("record", &[], &[("REPLAY_FILE", recording)]);
+    let watcher = server.connect(Vec::new());
+    let _finder = server.connect(vec![find("abcd123")]);
+
+    assert_eq!(next_find(&watcher), "abcd123");
+    drop(server);
+
+    let server = Server::start("replay", &["replay", recording, "--loop"], &[]);
+    let watcher = server.connect(Vec::new());
+    let replayed = next_find_message(&watcher);
+
+    assert_eq!(replayed.text.as_deref(), Some("abcd123"));
+    assert!(matches!(replayed.source, Some(FindSource::ArchiveReplay)));
+
+    let _ = fs::remove_file(recording);
+}
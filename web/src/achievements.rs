@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+pub const STORAGE_KEY: &str = "random-imgur-wall.achievements";
+
+const QUICK_FIND_SECS: f64 = 60.0;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Achievement {
+    FirstFind,
+    TenFinds,
+    HundredFinds,
+    ThousandFinds,
+    ThousandAttempts,
+    HundredThousandAttempts,
+    QuickFind,
+    DoubleFind,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 8] = [
+        Achievement::FirstFind,
+        Achievement::TenFinds,
+        Achievement::HundredFinds,
+        Achievement::ThousandFinds,
+        Achievement::ThousandAttempts,
+        Achievement::HundredThousandAttempts,
+        Achievement::QuickFind,
+        Achievement::DoubleFind,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Achievement::FirstFind => "Beginner's luck",
+            Achievement::TenFinds => "Collector",
+            Achievement::HundredFinds => "Curator",
+            Achievement::ThousandFinds => "Archivist",
+            Achievement::ThousandAttempts => "Persistent",
+            Achievement::HundredThousandAttempts => "Relentless",
+            Achievement::QuickFind => "Quick draw",
+            Achievement::DoubleFind => "Lightning strikes twice",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::FirstFind => "Find your first image",
+            Achievement::TenFinds => "Find 10 images",
+            Achievement::HundredFinds => "Find 100 images",
+            Achievement::ThousandFinds => "Find 1000 images",
+            Achievement::ThousandAttempts => "Send 1000 requests",
+            Achievement::HundredThousandAttempts => "Send 100000 requests",
+            Achievement::QuickFind => "Find an image within a minute of pressing Start",
+            Achievement::DoubleFind => "Find two images in a row",
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    finds: u64,
+    attempts: u64,
+}
+
+impl Achievements {
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Accounts for one probe result and returns the achievements it unlocked.
+    /// `session_secs` is the time since Start, `streak` the current run of
+    /// consecutive finds.
+    pub fn record(&mut self, found: bool, session_secs: f64, streak: u64) -> Vec<Achievement> {
+        self.attempts += 1;
+
+        if found {
+            self.finds += 1;
+        }
+
+        let candidates = [
+            (Achievement::FirstFind, self.finds >= 1),
+            (Achievement::TenFinds, self.finds >= 10),
+            (Achievement::HundredFinds, self.finds >= 100),
+            (Achievement::ThousandFinds, self.finds >= 1000),
+            (Achievement::ThousandAttempts, self.attempts >= 1000),
+            (
+                Achievement::HundredThousandAttempts,
+                self.attempts >= 100_000,
+            ),
+            (
+                Achievement::QuickFind,
+                found && session_secs <= QUICK_FIND_SECS,
+            ),
+            (Achievement::DoubleFind, streak >= 2),
+        ];
+
+        let mut unlocked = Vec::new();
+
+        for &(achievement, reached) in candidates.iter() {
+            if reached && !self.is_unlocked(achievement) {
+                self.unlocked.push(achievement);
+                unlocked.push(achievement);
+            }
+        }
+
+        unlocked
+    }
+}
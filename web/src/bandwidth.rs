@@ -0,0 +1,69 @@
+use anyhow::Error;
+use protocol::WsMessage;
+use yew::format::{Binary, Text};
+
+/// Bytes a websocket frame with `payload` bytes takes on the wire. Frames
+/// from the browser are masked, which adds 4 bytes.
+fn frame_len(payload: usize, masked: bool) -> u64 {
+    let header = match payload {
+        0..=125 => 2,
+        126..=65535 => 4,
+        _ => 10,
+    };
+    let mask = if masked { 4 } else { 0 };
+
+    (header + mask + payload) as u64
+}
+
+/// Traffic on the websocket connection since the page loaded.
+#[derive(Default)]
+pub struct Bandwidth {
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl Bandwidth {
+    pub fn record_sent(&mut self, payload: usize) {
+        self.sent += frame_len(payload, true);
+    }
+
+    pub fn record_received(&mut self, payload: usize) {
+        self.received += frame_len(payload, false);
+    }
+}
+
+/// A message received on the websocket, with the size of its payload.
+pub struct Received {
+    pub len: usize,
+    pub message: Result<WsMessage, Error>,
+}
+
+impl From<Text> for Received {
+    fn from(text: Text) -> Received {
+        match text {
+            Ok(text) => Received {
+                len: text.len(),
+                message: serde_json::from_str(&text).map_err(Error::from),
+            },
+            Err(err) => Received {
+                len: 0,
+                message: Err(err),
+            },
+        }
+    }
+}
+
+impl From<Binary> for Received {
+    fn from(binary: Binary) -> Received {
+        match binary {
+            Ok(data) => Received {
+                len: data.len(),
+                message: serde_json::from_slice(&data).map_err(Error::from),
+            },
+            Err(err) => Received {
+                len: 0,
+                message: Err(err),
+            },
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Light
+    }
+}
+
+impl Theme {
+    pub fn class(self) -> &'static str {
+        match self {
+            Theme::Light => "theme-light",
+            Theme::Dark => "theme-dark",
+        }
+    }
+}
+
+/// Deployment settings read from `/config.json`. Unknown keys are refused
+/// so that typos do not go unnoticed.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Empty for the server serving the wall, see `same_origin_ws_url`.
+    #[serde(default)]
+    pub ws_url: String,
+    /// Mirrors to fail over to when `ws_url` is down, in order.
+    #[serde(default)]
+    pub ws_urls: Vec<String>,
+    #[serde(default = "default_bruteforce_enabled")]
+    pub bruteforce_enabled: bool,
+    #[serde(default = "default_interval_ms")]
+    pub default_interval_ms: u64,
+    #[serde(default)]
+    pub min_interval_ms: u64,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Seeds the generator of IDs and mock events, see `?seed=`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn default_bruteforce_enabled() -> bool {
+    true
+}
+
+fn default_interval_ms() -> u64 {
+    100
+}
+
+impl Config {
+    /// The deployment settings used when `/config.json` cannot be, with the
+    /// server typed in by the user.
+    pub fn fallback(ws_url: String) -> Config {
+        Config {
+            ws_url,
+            ws_urls: Vec::new(),
+            bruteforce_enabled: default_bruteforce_enabled(),
+            default_interval_ms: default_interval_ms(),
+            min_interval_ms: 0,
+            theme: Theme::default(),
+            seed: None,
+        }
+    }
+
+    /// Every server to connect to, in order of preference.
+    pub fn upstreams(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.ws_url).chain(&self.ws_urls)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for ws_url in self.upstreams() {
+            if !ws_url.starts_with("ws://") && !ws_url.starts_with("wss://") {
+                return Err(format!(
+                    "ws_url must start with ws:// or wss://, got \"{}\"",
+                    ws_url
+                ));
+            }
+        }
+
+        if self.default_interval_ms < self.min_interval_ms {
+            return Err(format!(
+                "default_interval_ms ({}) is below min_interval_ms ({})",
+                self.default_interval_ms, self.min_interval_ms
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the server is when it also serves the wall, the usual setup.
+pub fn same_origin_ws_url(protocol: &str, host: &str) -> String {
+    let scheme = if protocol == "https:" { "wss" } else { "ws" };
+
+    format!("{}://{}/ws", scheme, host)
+}
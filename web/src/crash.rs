@@ -0,0 +1,39 @@
+use std::panic;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Covers the page with the panic message and a reload button, since a
+/// panicked app otherwise just stops responding without telling anyone.
+pub fn set_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let message = info.to_string();
+
+        js! { @(no_return)
+            var message = @{message};
+            console.error(message);
+
+            if (document.getElementById("crash")) {
+                return;
+            }
+
+            var crash = document.createElement("div");
+            crash.id = "crash";
+
+            var title = document.createElement("h2");
+            title.textContent = "Something broke, reloading the page should fix it.";
+
+            var details = document.createElement("pre");
+            details.textContent = message + "\n\nversion " + @{VERSION};
+
+            var reload = document.createElement("button");
+            reload.type = "button";
+            reload.textContent = "Reload";
+            reload.onclick = function () { location.reload(); };
+
+            crash.appendChild(title);
+            crash.appendChild(details);
+            crash.appendChild(reload);
+            document.body.appendChild(crash);
+        }
+    }));
+}
@@ -0,0 +1,12 @@
+use thiserror::Error;
+
+/// What can go wrong in the client without being the user's doing.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("invalid request URL {url}: {source}")]
+    Request { url: String, source: http::Error },
+    #[error("could not connect to {url}: {message}")]
+    Connect { url: String, message: String },
+    #[error("a message was dropped while disconnected")]
+    NotConnected,
+}
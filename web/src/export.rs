@@ -0,0 +1,92 @@
+use client_core::original_url;
+
+/// Files handing the finds of a session over to download tools.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// One URL per line, for `gallery-dl -i`, `yt-dlp -a` or `wget -i`.
+    UrlList,
+    /// A gallery-dl input file naming each file after when it was found.
+    GalleryDl,
+    /// A shell script downloading each find with curl, named the same way.
+    Script,
+}
+
+impl ExportFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::UrlList => "random-imgur-wall-links.txt",
+            ExportFormat::GalleryDl => "random-imgur-wall-gallery-dl.txt",
+            ExportFormat::Script => "random-imgur-wall-download.sh",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            ExportFormat::UrlList | ExportFormat::GalleryDl => "text/plain",
+            ExportFormat::Script => "application/x-sh",
+        }
+    }
+
+    /// Lists `finds`, IDs with when they were found in seconds since the
+    /// unix epoch.
+    pub fn render<'a>(self, finds: impl Iterator<Item = (&'a str, u64)>) -> String {
+        let mut file = match self {
+            ExportFormat::UrlList => String::new(),
+            ExportFormat::GalleryDl => {
+                "# gallery-dl -i random-imgur-wall-gallery-dl.txt\n".to_owned()
+            }
+            ExportFormat::Script => {
+                "#!/bin/sh\n# Downloads finds from Random Imgur Wall.\nset -e\n".to_owned()
+            }
+        };
+
+        // IDs are alphanumeric, nothing needs quoting or escaping.
+        for (id, found_at) in finds {
+            let url = original_url(id);
+            let name = format!("{}_{}", format_timestamp(found_at), id);
+
+            match self {
+                ExportFormat::UrlList => file.push_str(&format!("{}\n", url)),
+                ExportFormat::GalleryDl => file.push_str(&format!(
+                    "-filename = \"{}.{{extension}}\"\n{}\n",
+                    name, url
+                )),
+                ExportFormat::Script => {
+                    file.push_str(&format!("curl -fsSL -o '{}.png' '{}'\n", name, url))
+                }
+            }
+        }
+
+        file
+    }
+}
+
+/// Formats a unix timestamp as a UTC `YYYY-MM-DD_HH-MM-SS`, which sorts by
+/// time and is a valid file name everywhere.
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    let secs_of_day = secs % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+pub const STORAGE_KEY: &str = "random-imgur-wall.favorites";
+
+/// Image IDs the user marked as favorite, oldest first.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Favorites {
+    ids: Vec<String>,
+}
+
+impl Favorites {
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.iter().any(|favorite| favorite == id)
+    }
+
+    pub fn add(&mut self, id: String) {
+        if !self.contains(&id) {
+            self.ids.push(id);
+        }
+    }
+
+    /// Removes `id` and returns where it was, to put it back on undo.
+    pub fn remove(&mut self, id: &str) -> Option<usize> {
+        let position = self.ids.iter().position(|favorite| favorite == id)?;
+        self.ids.remove(position);
+
+        Some(position)
+    }
+
+    pub fn insert(&mut self, position: usize, id: String) {
+        if !self.contains(&id) {
+            let position = position.min(self.ids.len());
+            self.ids.insert(position, id);
+        }
+    }
+}
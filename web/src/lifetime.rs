@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+pub const STORAGE_KEY: &str = "random-imgur-wall.lifetime-stats";
+
+const SAVE_EVERY_REQUESTS: u64 = 100;
+
+/// Personal counters kept in local storage so they survive page reloads.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    pub requests: u64,
+    pub images_found: u64,
+    pub images_failed: u64,
+}
+
+impl LifetimeStats {
+    /// Counts a probe and tells whether it is a good time to save.
+    pub fn record_probe(&mut self, found: bool) -> bool {
+        self.requests += 1;
+
+        if found {
+            self.images_found += 1;
+        }
+
+        found || self.requests % SAVE_EVERY_REQUESTS == 0
+    }
+}
@@ -1,103 +1,465 @@
 #![recursion_limit = "8192"]
 
+#[macro_use]
+extern crate stdweb;
+
+mod achievements;
+mod bandwidth;
+mod config;
+mod crash;
+mod error;
+mod export;
+mod favorites;
+mod lifetime;
+mod measure;
+mod mock;
+mod playback;
+mod prefetch;
+mod presets;
+mod settings;
+mod timers;
+mod undo;
+mod upstreams;
+
 use anyhow::{anyhow, Error};
 
 use serde::{Deserialize, Serialize};
 
-use yew::format::{Binary, Json, Nothing};
+use yew::format::{Binary, Json, Nothing, Text};
 
 use yew::services::console::ConsoleService;
 use yew::services::fetch::{
-    FetchOptions, FetchService, FetchTask, Redirect, Referrer, ReferrerPolicy, Request, Response,
+    Cache, FetchOptions, FetchService, FetchTask, Redirect, Referrer, ReferrerPolicy, Request,
+    Response,
 };
-use yew::services::interval::{IntervalService, IntervalTask};
-use yew::services::timeout::{TimeoutService, TimeoutTask};
+use yew::services::interval::IntervalService;
+use yew::services::storage::{Area, StorageService};
+use yew::services::timeout::TimeoutService;
 use yew::services::websocket::{WebSocketService, WebSocketStatus, WebSocketTask};
 
+use yew::events::{ClickEvent, ContextMenuEvent, SubmitEvent, TouchEnd, TouchStart};
 use yew::{html, html::ChangeData, Component, ComponentLink, Html, Renderable, ShouldRender};
 
-use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
+use stdweb::unstable::TryInto;
+use stdweb::web::event::{IEvent, IMouseEvent, ITouchEvent, ResizeEvent};
+use stdweb::web::{window, Date, IEventTarget};
 
-use std::iter;
+use std::mem;
 
 use std::time::Duration;
 
 use http::response::Parts;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 
+use protocol::{
+    normalize_id, Counts, FindSource, QuietHours, RegionCounts, RuntimeConfig, WsMessage,
+    WsMessageType, RATE_REPORT_SECS,
+};
+
+use client_core::generator::Generator;
+use client_core::lifecycle::{Event, Hello, Link, State};
+use client_core::pacing::{self, Minimums, Pacing, Schedule, Tick};
+use client_core::session::{format_bytes, format_duration, Session, SessionSummary};
+use client_core::wall::push_evicting;
+use client_core::{
+    find_message, original_url, page_url, report_url, Challenge, SeqCheck, SeqTracker,
+};
+use rand::Rng;
+
+use achievements::{Achievement, Achievements};
+use bandwidth::{Bandwidth, Received};
+use config::{same_origin_ws_url, Config};
+use error::ClientError;
+use export::ExportFormat;
+use favorites::Favorites;
+use lifetime::LifetimeStats;
+use mock::MockServer;
+use playback::{ArchivedFind, DayArchive, Playback};
+use prefetch::{Prefetcher, Priority};
+use presets::UserPresets;
+use settings::{
+    format_time, parse_number, parse_optional_time, DataSaverMode, FeedFilter, ImageClick,
+    ProbeReferrer, Settings, SettingsError, SettingsTab, TileSize, WallLayout,
+};
+use timers::{Timer, Timers};
+use undo::UndoStack;
+use upstreams::Upstreams;
+
+const CLIENT_ID_KEY: &str = "random-imgur-wall.client-id";
+const SESSION_TOKEN_KEY: &str = "random-imgur-wall.session";
+/// The server typed in when `/config.json` is missing or invalid.
+const WS_URL_KEY: &str = "random-imgur-wall.ws-url";
+const CLIENT_ID_LEN: usize = 16;
+const IMAGE_MAX_RETRIES: u32 = 3;
+const REPORT_URL: &str = "https://help.imgur.com/hc/en-us/articles/208582296-Reporting-Content";
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const MILESTONE_DURATION: Duration = Duration::from_secs(15);
+const TICKER_LEN: usize = 5;
+const MAX_SESSION_LINKS: usize = 100_000;
+const MOBILE_MAX_WIDTH: i32 = 700;
+const SWIPE_MIN_DISTANCE: f64 = 50.0;
+const PULL_TO_REFRESH_DISTANCE: f64 = 120.0;
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_SUBMIT_ATTEMPTS: u32 = 5;
+const RATE_REPORT_INTERVAL: Duration = Duration::from_secs(RATE_REPORT_SECS);
+const RANDOM_IMAGES: usize = 20;
+const MAX_IMAGES: usize = 1000;
+const MIN_INTERVAL_MS: u64 = 50;
+const MAX_PROBES_IN_FLIGHT: usize = 256;
+const LATENCY_SAMPLES: usize = 100;
+const FALLBACK_IMAGES: usize = 3;
+const PLAYBACK_TICK: Duration = Duration::from_millis(250);
+/// Finds of a replayed day loaded before they show up.
+const PLAYBACK_PREFETCH: usize = 5;
+/// Images loading ahead of the wall at once, fewer with data saver.
+const MAX_PREFETCHES: usize = 6;
+const DATA_SAVER_MAX_PREFETCHES: usize = 2;
+const PLAYBACK_SPEEDS: [(f64, &str); 4] = [
+    (10.0, "10 minutes per minute"),
+    (60.0, "1 hour per minute"),
+    (360.0, "6 hours per minute"),
+    (1440.0, "1 day per minute"),
+];
+
 #[derive(Serialize, Deserialize)]
-struct Config {
-    ws_url: String,
+struct AuthProviders {
+    providers: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
-enum WsMessageType {
-    UsersBruteforcing,
-    UsersWatching,
-    Start,
-    Stop,
-    New,
+struct ImageOfTheDay {
+    date: String,
+    id: String,
 }
 
-#[derive(Serialize, Deserialize)]
-struct WsMessage {
-    msg_type: WsMessageType,
-    text: Option<String>,
-    number: Option<u64>,
+struct Toast {
+    id: u32,
+    text: String,
+    undo: bool,
+}
+
+/// A destructive action on the wall, with what it takes to revert it.
+enum Undoable {
+    /// An image hidden from the wall, and where it was.
+    Hide(usize, Image),
+    /// A favorite removed, and where it was in the list.
+    Unfavorite(usize, String),
+    /// Every image that was on the wall.
+    ClearWall(VecDeque<Image>),
+    /// The session statistics as they were before being reset.
+    ClearStats(SessionStats),
+    /// The settings as they were before applying a preset.
+    Settings(Settings),
+}
+
+/// The menu of actions on one image, opened where it was right-clicked or
+/// long-pressed.
+struct ContextMenu {
+    id: String,
+    x: f64,
+    y: f64,
+}
+
+/// Counters shown in the "This session" statistics.
+struct SessionStats {
+    total_requests: u64,
+    images_found_self: u64,
+    images_found: u64,
+    images_failed: u64,
+    images_evicted: u64,
+    probes_skipped: u64,
+    bandwidth: Bandwidth,
+}
+
+struct PendingFind {
+    id: String,
+    attempts: u32,
+    sent_at: f64,
+}
+
+struct Image {
+    id: String,
+    retries: u32,
+    from_archive: bool,
+    revealed: bool,
+    source: Option<FindSource>,
+    /// Notes the server's content policies attached to it.
+    labels: Vec<String>,
+    /// Found by this client, shown in its own pane in the split layout.
+    is_own: bool,
+    /// Natural width and height, known before it goes on the wall so its
+    /// tile has the right shape from the start.
+    size: Option<(u32, u32)>,
+}
+
+impl Image {
+    fn new(id: String) -> Self {
+        Image {
+            id,
+            retries: 0,
+            from_archive: false,
+            revealed: false,
+            source: None,
+            labels: Vec::new(),
+            is_own: false,
+            size: None,
+        }
+    }
+
+    fn src(&self, thumbnail: bool) -> String {
+        if thumbnail {
+            return match self.retries {
+                0 => format!("https://i.imgur.com/{}m.jpg", self.id),
+                _ => format!("https://i.imgur.com/{}m.jpg?{}", self.id, self.retries),
+            };
+        }
+
+        match self.retries {
+            0 => format!("https://i.imgur.com/{}.png", self.id),
+            1 => format!("https://i.imgur.com/{}.png?{}", self.id, self.retries),
+            2 => format!("https://i.imgur.com/{}.jpg?{}", self.id, self.retries),
+            _ => format!("https://i.imgur.com/{}m.jpg?{}", self.id, self.retries),
+        }
+    }
 }
 
 struct Model {
     link: ComponentLink<Model>,
     config: Option<Config>,
+    config_error: Option<String>,
+    /// The server address being typed in, while there is no config.
+    setup_ws_url: Option<String>,
+    setup_error: Option<String>,
+    ws_url_from_setup: bool,
+    upstreams: Upstreams,
+    runtime_config: RuntimeConfig,
     console_service: ConsoleService,
     fetch_service: FetchService,
     fetch_task: Option<FetchTask>,
+    iotd_fetch_task: Option<FetchTask>,
     find_fetch_tasks: HashMap<String, FetchTask>,
+    manual_id: String,
+    manual_error: Option<String>,
+    manual_fetch_task: Option<FetchTask>,
     ws_service: WebSocketService,
     ws_task: Option<WebSocketTask>,
+    generator: Generator,
+    seed: Option<u64>,
+    mock: Option<MockServer>,
     interval_service: IntervalService,
-    interval_task: Option<IntervalTask>,
-    reset_interval_task: Option<IntervalTask>,
-    rate_interval_task: Option<IntervalTask>,
     timeout_service: TimeoutService,
-    timeout_task: Option<TimeoutTask>,
-    is_started: bool,
-    interval: Duration,
-    images: VecDeque<String>,
+    timers: Timers<Timer>,
+    state: State,
+    pacing: Pacing,
+    quiet_hours: Option<QuietHours>,
+    quiet_secs_left: Option<u32>,
+    images: VecDeque<Image>,
+    /// Every find shown this session, with when it was found in seconds
+    /// since the unix epoch.
+    session_finds: VecDeque<(String, u64)>,
+    image_retry_timers: Timers<String>,
+    images_failed: u64,
+    images_evicted: u64,
+    probes_skipped: u64,
+    bandwidth: Bandwidth,
+    display_latencies: VecDeque<f64>,
+    clock_offset: f64,
+    clock_sample_rtt: Option<f64>,
+    lifetime_stats: LifetimeStats,
     total_requests: u64,
     requests_per_second: u64,
     requests_per_second_current: u64,
     images_found_self: u64,
     images_found: u64,
     users_watching: u64,
+    region_counts: RegionCounts,
     users_bruteforcing: u64,
     concurrent_loaded: usize,
     show_from_top: bool,
-    is_rate_limited: bool,
-    rate_limit: u64,
+    tile_size: TileSize,
+    image_click: ImageClick,
+    blur_images: bool,
+    watch_only: bool,
+    text_only: bool,
+    hidden_sources: Vec<FindSource>,
+    feed_filter: FeedFilter,
+    layout: WallLayout,
+    /// This client's finds in the split layout, the others stay in
+    /// `images`.
+    own_images: VecDeque<Image>,
+    /// Images loading off the wall to get their size, by ID.
+    measuring: HashMap<String, Image>,
+    prefetcher: Prefetcher,
+    /// IDs this client submitted, to tell its finds apart in the feed.
+    own_finds: HashSet<String>,
+    own_finds_broadcast: u64,
+    others_finds_broadcast: u64,
+    user_presets: UserPresets,
+    preset_name: String,
+    preset_import: String,
+    columns: usize,
+    viewport_width: i32,
+    settings_open: bool,
+    settings_tab: SettingsTab,
+    settings_errors: Vec<SettingsError>,
+    lightbox: Option<String>,
+    touch_start: Option<(f64, f64)>,
+    data_saver_mode: DataSaverMode,
+    cache_busting: bool,
+    no_store: bool,
+    probe_referrer: ProbeReferrer,
+    save_data: bool,
+    battery_low: bool,
+    session: Option<Session>,
+    session_summary: Option<SessionSummary>,
+    storage: StorageService,
+    achievements: Achievements,
+    toasts: Vec<Toast>,
+    toast_timers: Timers<u32>,
+    next_toast_id: u32,
+    undo_stack: UndoStack<Undoable>,
+    favorites: Favorites,
+    context_menu: Option<ContextMenu>,
+    milestone: Option<String>,
+    activity: VecDeque<String>,
+    client_id: String,
+    registration_name: String,
+    registration_avatar: String,
+    registered_name: Option<String>,
+    registered_finds: u64,
+    session_token: Option<String>,
+    resume_token: Option<String>,
+    account_name: Option<String>,
+    auth_providers: Vec<String>,
+    page_url: String,
+    challenge: Option<Challenge>,
+    iotd_history: Vec<ImageOfTheDay>,
+    iotd_index: usize,
+    playback: Option<Playback>,
+    playback_date: String,
+    playback_speed: f64,
+    archive_fetch_task: Option<FetchTask>,
+    random_fetch_task: Option<FetchTask>,
+    archive_fallback_secs: u64,
+    last_new_at: f64,
+    seq: SeqTracker,
+    is_resyncing: bool,
+    pending_finds: BTreeMap<u64, PendingFind>,
+    next_client_msg_id: u64,
+    reported_requests: u64,
+    /// Probes imgur answered with a 429 since the last `Rate`.
+    throttled_probes: u64,
+    /// Why the server asked every client to probe slower, while it lasts.
+    swarm_backoff: Option<String>,
 }
 
 enum Msg {
     FetchConfig,
     FetchConfigDone(Result<Config, Error>),
+    SetupWsUrlChanged(String),
+    SetupSubmitted,
+    ForgetWsUrl,
     WsConnect,
     WsConnected,
     WsLost,
+    WsReceived(Received),
     WsMessage(Result<WsMessage, Error>),
     WsSend(WsMessage),
+    MockTick,
     IntervalChanged(String),
+    QuietStartChanged(String),
+    QuietEndChanged(String),
+    ArchiveFallbackChanged(String),
     Start,
     Stop,
     TryFind,
     Found((String, String)),
-    NotFound(String),
+    NotFound((String, String)),
+    ManualIdChanged(String),
+    SubmitManualId,
+    ManualIdFound((String, String)),
+    ManualIdNotFound((String, String)),
     ResetRequestsPerSecond,
     LoadedChanged(String),
     ShowModeSelected(bool),
+    TileSizeSelected(TileSize),
+    ImageClickSelected(ImageClick),
+    ColumnsChanged(String),
     RateLimitChanged(String),
     ResetRateLimit,
+    ImageFailed(String),
+    ImageRetry(String),
+    ViewportResized,
+    ToggleSettings,
+    SettingsTabSelected(SettingsTab),
+    ResetSettings,
+    ApplyPreset(String),
+    PresetNameChanged(String),
+    SavePreset,
+    DeletePreset,
+    ExportPresets,
+    PresetImportChanged(String),
+    ImportPresets,
+    BlurImagesToggled,
+    WatchOnlyToggled,
+    TextOnlyToggled,
+    SourceToggled(FindSource),
+    FeedFilterSelected(FeedFilter),
+    LayoutSelected(WallLayout),
+    ImageMeasured(String, u32, u32),
+    RevealImage(String),
+    OpenLightbox(String),
+    CloseLightbox,
+    LightboxNext,
+    LightboxPrevious,
+    TouchStart(f64, f64),
+    TouchEnd(f64, f64),
+    WsReconnect,
+    BatteryChanged(bool),
+    DataSaverModeSelected(DataSaverMode),
+    CacheBustingToggled,
+    NoStoreToggled,
+    ProbeReferrerSelected(ProbeReferrer),
+    CloseSessionSummary,
+    ExportSessionSummary,
+    ShareSessionSummary,
+    DismissToast(u32),
+    OpenContextMenu(String, f64, f64),
+    CloseContextMenu,
+    CopyImageLink(String),
+    CopyLinks { session: bool },
+    ExportFinds(ExportFormat),
+    ToggleFavorite(String),
+    ReportImage(String),
+    HideImage(String),
+    ClearWall,
+    ClearStats,
+    Undo(u32),
+    DismissMilestone,
+    RegistrationNameChanged(String),
+    RegistrationAvatarChanged(String),
+    Register,
+    AuthProvidersFetched(Vec<String>),
+    Logout,
+    IotdFetched(Vec<ImageOfTheDay>),
+    IotdSelected(usize),
+    PlaybackDateChanged(String),
+    PlaybackLoad,
+    PlaybackLoaded(Result<DayArchive, Error>),
+    PlaybackToggle,
+    PlaybackTick,
+    PlaybackSeek(String),
+    PlaybackSpeedSelected(f64),
+    PlaybackExit,
+    ResendPendingFinds,
+    ReportRate,
+    ProbeThrottled(String),
+    SwarmBackoffOver,
+    RandomFromArchive,
+    RandomFromArchiveLoaded(Result<Vec<ArchivedFind>, Error>, bool),
     NoOp,
 }
 
@@ -112,46 +474,213 @@ impl Component for Model {
         let console_service = ConsoleService::new();
         let timeout_service = TimeoutService::new();
 
+        let mut storage = StorageService::new(Area::Local);
+
+        let Json(achievements): Json<Result<Achievements, Error>> =
+            storage.restore(achievements::STORAGE_KEY);
+        let Json(lifetime_stats): Json<Result<LifetimeStats, Error>> =
+            storage.restore(lifetime::STORAGE_KEY);
+        let Json(favorites): Json<Result<Favorites, Error>> =
+            storage.restore(favorites::STORAGE_KEY);
+        let Json(user_presets): Json<Result<UserPresets, Error>> =
+            storage.restore(presets::STORAGE_KEY);
+
+        let page_url: String = js!(return location.href.split("#")[0];)
+            .try_into()
+            .unwrap_or_default();
+        let hash: String = js!(return location.hash;).try_into().unwrap_or_default();
+        let mock: bool = js!(return new URLSearchParams(location.search).get("mock") === "1";)
+            .try_into()
+            .unwrap_or(false);
+        let seed = js!(return new URLSearchParams(location.search).get("seed") || "";)
+            .try_into()
+            .ok()
+            .and_then(|seed: String| seed.parse().ok());
+        let mut generator = seed.map(Generator::seeded).unwrap_or_default();
+
+        if hash.starts_with("#session=") {
+            storage.store(
+                SESSION_TOKEN_KEY,
+                Ok::<String, Error>(hash["#session=".len()..].to_owned()),
+            );
+            js! { history.replaceState(null, "", @{&page_url}); }
+        }
+
+        let session_token = storage
+            .restore::<Result<String, Error>>(SESSION_TOKEN_KEY)
+            .ok();
+
+        let client_id = match storage.restore::<Result<String, Error>>(CLIENT_ID_KEY) {
+            Ok(client_id) => client_id,
+            Err(_) => {
+                let client_id = generator.alphanumeric(CLIENT_ID_LEN);
+                storage.store(CLIENT_ID_KEY, Ok::<String, Error>(client_id.clone()));
+                client_id
+            }
+        };
+
         link.send_message(Msg::FetchConfig);
 
+        let resized = link.callback(|_| Msg::ViewportResized);
+        window().add_event_listener(move |_: ResizeEvent| resized.emit(()));
+
+        let battery = link.callback(Msg::BatteryChanged);
+        let on_battery = move |low: bool| battery.emit(low);
+        js! {
+            var on_battery = @{on_battery};
+            if (navigator.getBattery) {
+                navigator.getBattery().then(function (battery) {
+                    var update = function () {
+                        on_battery(battery.level <= 0.2 && !battery.charging);
+                    };
+                    battery.addEventListener("levelchange", update);
+                    battery.addEventListener("chargingchange", update);
+                    update();
+                });
+            }
+        }
+
+        let defaults = Settings::default();
+
         Model {
             link,
             config: None,
+            config_error: None,
+            setup_ws_url: None,
+            setup_error: None,
+            ws_url_from_setup: false,
+            upstreams: Upstreams::default(),
+            runtime_config: RuntimeConfig::default(),
             console_service,
             fetch_service,
             fetch_task: None,
+            iotd_fetch_task: None,
             find_fetch_tasks: HashMap::new(),
+            manual_id: String::new(),
+            manual_error: None,
+            manual_fetch_task: None,
             ws_service,
             ws_task: None,
+            mock: if mock {
+                Some(MockServer::new(generator.fork()))
+            } else {
+                None
+            },
+            generator,
+            seed,
             interval_service,
-            interval_task: None,
-            reset_interval_task: None,
-            rate_interval_task: None,
             timeout_service,
-            timeout_task: None,
-            is_started: false,
-            interval: Duration::from_millis(100),
+            timers: Timers::default(),
+            state: State::default(),
+            pacing: Pacing::new(
+                Duration::from_millis(defaults.interval_ms),
+                defaults.rate_limit,
+            ),
+            quiet_hours: defaults.quiet_hours,
+            quiet_secs_left: None,
             images: VecDeque::new(),
+            session_finds: VecDeque::new(),
+            image_retry_timers: Timers::default(),
+            images_failed: 0,
+            images_evicted: 0,
+            probes_skipped: 0,
+            bandwidth: Bandwidth::default(),
+            display_latencies: VecDeque::new(),
+            clock_offset: 0.0,
+            clock_sample_rtt: None,
+            lifetime_stats: lifetime_stats.unwrap_or_default(),
             total_requests: 0,
             requests_per_second: 0,
             requests_per_second_current: 0,
             images_found_self: 0,
             images_found: 0,
             users_watching: 0,
+            region_counts: RegionCounts::new(),
             users_bruteforcing: 0,
-            concurrent_loaded: 100,
-            show_from_top: false,
-            is_rate_limited: true,
-            rate_limit: 2,
+            concurrent_loaded: defaults.concurrent_loaded,
+            show_from_top: defaults.show_from_top,
+            tile_size: defaults.tile_size,
+            image_click: defaults.image_click,
+            blur_images: defaults.blur_images,
+            watch_only: defaults.watch_only,
+            text_only: defaults.text_only,
+            hidden_sources: defaults.hidden_sources,
+            feed_filter: defaults.feed_filter,
+            layout: defaults.layout,
+            own_images: VecDeque::new(),
+            measuring: HashMap::new(),
+            prefetcher: Prefetcher::default(),
+            own_finds: HashSet::new(),
+            own_finds_broadcast: 0,
+            others_finds_broadcast: 0,
+            user_presets: user_presets.unwrap_or_default(),
+            preset_name: String::new(),
+            preset_import: String::new(),
+            columns: defaults.columns,
+            viewport_width: window().inner_width(),
+            settings_open: false,
+            settings_tab: SettingsTab::Basic,
+            settings_errors: Vec::new(),
+            lightbox: None,
+            touch_start: None,
+            data_saver_mode: defaults.data_saver_mode,
+            cache_busting: defaults.cache_busting,
+            no_store: defaults.no_store,
+            probe_referrer: defaults.probe_referrer,
+            save_data: js!(return !!(navigator.connection && navigator.connection.saveData);)
+                .try_into()
+                .unwrap_or(false),
+            battery_low: false,
+            session: None,
+            session_summary: None,
+            storage,
+            achievements: achievements.unwrap_or_default(),
+            toasts: Vec::new(),
+            toast_timers: Timers::default(),
+            next_toast_id: 0,
+            undo_stack: UndoStack::default(),
+            favorites: favorites.unwrap_or_default(),
+            context_menu: None,
+            milestone: None,
+            activity: VecDeque::new(),
+            client_id,
+            registration_name: String::new(),
+            registration_avatar: String::new(),
+            registered_name: None,
+            registered_finds: 0,
+            session_token,
+            resume_token: None,
+            account_name: None,
+            auth_providers: Vec::new(),
+            page_url,
+            challenge: None,
+            iotd_history: Vec::new(),
+            iotd_index: 0,
+            playback: None,
+            playback_date: js!(return new Date().toISOString().slice(0, 10);)
+                .try_into()
+                .unwrap_or_default(),
+            playback_speed: PLAYBACK_SPEEDS[1].0,
+            archive_fetch_task: None,
+            random_fetch_task: None,
+            archive_fallback_secs: 0,
+            last_new_at: Date::now(),
+            seq: SeqTracker::default(),
+            is_resyncing: false,
+            pending_finds: BTreeMap::new(),
+            next_client_msg_id: 0,
+            reported_requests: 0,
+            throttled_probes: 0,
+            swarm_backoff: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::FetchConfig => {
-                self.fetch_task =
-                    Some(self.fetch_service.fetch(
-                        Request::get("/config.json").body(Nothing).unwrap(),
+                if let Some(request) = self.get("/config.json".to_owned()) {
+                    self.fetch_task = Some(self.fetch_service.fetch(
+                        request,
                         self.link.callback(
                             move |response: Response<Json<Result<Config, Error>>>| {
                                 let (meta, Json(config)) = response.into_parts();
@@ -166,93 +695,267 @@ impl Component for Model {
                             },
                         ),
                     ));
+                }
 
                 false
             }
-            Msg::FetchConfigDone(Ok(config)) => {
+            Msg::FetchConfigDone(Ok(mut config)) => {
+                if config.ws_url.is_empty() {
+                    config.ws_url = if config.ws_urls.is_empty() {
+                        location_ws_url()
+                    } else {
+                        config.ws_urls.remove(0)
+                    };
+                }
+
+                if let Err(err) = config.validate() {
+                    self.link
+                        .send_message(Msg::FetchConfigDone(Err(anyhow!("/config.json: {}", err))));
+
+                    return false;
+                }
+
+                self.pacing
+                    .set_interval(Duration::from_millis(config.default_interval_ms));
+
+                if let (None, Some(seed)) = (self.seed, config.seed) {
+                    self.reseed(seed);
+                }
+
+                if self.mock.is_none() {
+                    self.fetch_server_data(&config);
+                }
+
+                self.upstreams = Upstreams::new(config.upstreams().cloned());
                 self.config = Some(config);
+                self.config_error = None;
+                self.setup_ws_url = None;
+                self.pacing.set_minimums(self.minimums());
 
                 self.link.send_message(Msg::WsConnect);
-                self.reset_interval_task = Some(self.interval_service.spawn(
-                    Duration::from_secs(1),
-                    self.link.callback(|_| Msg::ResetRequestsPerSecond),
-                ));
-                self.rate_interval_task = Some(self.interval_service.spawn(
-                    Duration::from_secs(self.rate_limit),
-                    self.link.callback(|_| Msg::ResetRateLimit),
-                ));
+                self.timers.set(
+                    Timer::ResetRequestsPerSecond,
+                    Box::new(self.interval_service.spawn(
+                        Duration::from_secs(1),
+                        self.link.callback(|_| Msg::ResetRequestsPerSecond),
+                    )),
+                );
+                self.timers.set(
+                    Timer::ResendPendingFinds,
+                    Box::new(
+                        self.interval_service
+                            .spawn(ACK_TIMEOUT, self.link.callback(|_| Msg::ResendPendingFinds)),
+                    ),
+                );
+                self.spawn_rate_interval();
+
+                false
+            }
+            Msg::FetchConfigDone(Err(err)) => {
+                self.config_error = Some(err.to_string());
+
+                let stored = self
+                    .storage
+                    .restore::<Result<String, Error>>(WS_URL_KEY)
+                    .ok()
+                    .map(Config::fallback)
+                    .filter(|config| config.validate().is_ok());
+
+                match stored {
+                    Some(config) if !self.ws_url_from_setup => {
+                        self.ws_url_from_setup = true;
+                        self.link.send_message(Msg::FetchConfigDone(Ok(config)));
+
+                        false
+                    }
+                    _ => {
+                        if self.setup_ws_url.is_none() {
+                            self.setup_ws_url = Some(location_ws_url());
+                        }
+
+                        true
+                    }
+                }
+            }
+            Msg::SetupWsUrlChanged(ws_url) => {
+                self.setup_ws_url = Some(ws_url);
+
+                false
+            }
+            Msg::SetupSubmitted => {
+                if let Some(ws_url) = &self.setup_ws_url {
+                    let config = Config::fallback(ws_url.trim().to_owned());
+
+                    match config.validate() {
+                        Ok(()) => {
+                            self.storage
+                                .store(WS_URL_KEY, Ok::<String, Error>(config.ws_url.clone()));
+                            self.ws_url_from_setup = true;
+                            self.setup_error = None;
+                            self.link.send_message(Msg::FetchConfigDone(Ok(config)));
+                        }
+                        Err(err) => self.setup_error = Some(err),
+                    }
+                }
+
+                true
+            }
+            Msg::ForgetWsUrl => {
+                self.storage.remove(WS_URL_KEY);
+                js! { location.reload(); }
+
+                false
+            }
+            Msg::WsConnect if self.mock.is_some() => {
+                if !self.timers.is_set(&Timer::Mock) {
+                    self.timers.set(
+                        Timer::Mock,
+                        Box::new(
+                            self.interval_service
+                                .spawn(mock::TICK, self.link.callback(|_| Msg::MockTick)),
+                        ),
+                    );
+                    self.link.send_message(Msg::WsConnected);
+                }
 
                 false
             }
             Msg::WsConnect => {
-                if let Some(config) = &self.config {
+                if let Some(upstream) = self.upstreams.current() {
                     if self.ws_task.is_none() {
-                        let callback = self.link.callback(|Json(data)| Msg::WsMessage(data));
+                        let callback = self.link.callback(Msg::WsReceived);
                         let notification = self.link.callback(|status| match status {
                             WebSocketStatus::Opened => Msg::WsConnected,
                             WebSocketStatus::Closed | WebSocketStatus::Error => Msg::WsLost.into(),
                         });
-                        let task = self
-                            .ws_service
-                            .connect(&config.ws_url, callback, notification)
-                            .unwrap();
-                        self.ws_task = Some(task);
+                        let mut params = Vec::new();
+
+                        if let Some(token) = &self.session_token {
+                            params.push(format!("session={}", token));
+                        }
+
+                        if let Some(token) = &self.resume_token {
+                            params.push(format!("resume={}", token));
+                        }
+
+                        let ws_url = if params.is_empty() {
+                            upstream.to_owned()
+                        } else {
+                            format!("{}?{}", upstream, params.join("&"))
+                        };
+                        match self.ws_service.connect(&ws_url, callback, notification) {
+                            Ok(task) => self.ws_task = Some(task),
+                            Err(err) => self.report(ClientError::Connect {
+                                url: ws_url,
+                                message: err.to_string(),
+                            }),
+                        }
                     }
                 }
                 false
             }
             Msg::WsConnected => {
-                self.link.send_message(Msg::Start);
+                self.upstreams.connected(Date::now());
+
+                let hello = Hello {
+                    client_id: &self.client_id,
+                    now_ms: Date::now() as u64,
+                    resync_from: self.seq.next(),
+                    watch_only: self.watch_only,
+                };
+                let mut link = WebLink {
+                    link: &self.link,
+                    timers: &mut self.timers,
+                    timeout_service: &mut self.timeout_service,
+                };
+
+                if self.state.connected(&mut link, &hello) {
+                    self.is_resyncing = true;
+                }
+
                 false
             }
             Msg::WsLost => {
                 self.ws_task = None;
+                self.upstreams.lost(Date::now());
+                self.state.lost(&mut WebLink {
+                    link: &self.link,
+                    timers: &mut self.timers,
+                    timeout_service: &mut self.timeout_service,
+                });
+
+                false
+            }
+            Msg::WsSend(msg) if self.mock.is_some() => {
+                if let Some(mock) = &mut self.mock {
+                    for reply in mock.reply(&msg, Date::now() as u64) {
+                        self.link.send_message(Msg::WsMessage(Ok(reply)));
+                    }
+                }
 
-                self.timeout_task = Some(self.timeout_service.spawn(
-                    Duration::from_secs(1),
-                    self.link.callback(|_| Msg::WsConnect),
-                ));
+                false
+            }
+            Msg::MockTick => {
+                if let Some(mock) = &mut self.mock {
+                    for msg in mock.tick(Date::now() as u64) {
+                        self.link.send_message(Msg::WsMessage(Ok(msg)));
+                    }
+                }
 
                 false
             }
             Msg::WsSend(msg) => {
-                self.ws_task.as_mut().unwrap().send(Json(&msg));
+                if let Ok(text) = serde_json::to_string(&msg) {
+                    match &mut self.ws_task {
+                        Some(ws_task) => {
+                            self.bandwidth.record_sent(text.len());
+                            ws_task.send::<Text>(Ok(text));
+                        }
+                        None => self.report(ClientError::NotConnected),
+                    }
+                }
 
                 false
             }
+            Msg::WsReceived(received) => {
+                self.bandwidth.record_received(received.len);
+                self.update(Msg::WsMessage(received.message))
+            }
+            Msg::WsMessage(Ok(ref msg)) if !self.check_seq(msg.seq) => false,
             Msg::WsMessage(Ok(msg)) => match msg.msg_type {
                 WsMessageType::New => {
                     if let Some(text) = msg.text {
-                        if text.is_ascii() && text.chars().all(char::is_alphanumeric) {
-                            if self.is_rate_limited == false || self.rate_limit == 0 {
-                                if self.concurrent_loaded != 0 {
-                                    while self.images.len() > self.concurrent_loaded {
-                                        if self.show_from_top {
-                                            self.images.pop_front();
-                                        } else {
-                                            self.images.pop_back();
-                                        }
-                                    }
+                        if let Some(text) = normalize_id(&text) {
+                            let is_own = self.own_finds.contains(&text);
 
-                                    if self.images.len() >= self.concurrent_loaded {
-                                        if self.show_from_top {
-                                            self.images.pop_front();
-                                        } else {
-                                            self.images.pop_back();
-                                        }
-                                    }
-                                }
+                            if is_own {
+                                self.own_finds_broadcast += 1;
+                            } else {
+                                self.others_finds_broadcast += 1;
+                            }
 
-                                if self.show_from_top {
-                                    self.images.push_back(text);
-                                } else {
-                                    self.images.push_front(text);
-                                }
+                            // Each pane of the split layout has its own rate limit.
+                            let has_own_pane = is_own && self.layout == WallLayout::Split;
+
+                            if self.feed_filter.accepts(is_own)
+                                && self.pacing.try_show(has_own_pane)
+                            {
+                                let found_at = msg.at.unwrap_or(self.server_now() as u64) / 1000;
+                                self.show_image(
+                                    text,
+                                    false,
+                                    found_at,
+                                    msg.source,
+                                    msg.labels.unwrap_or_default(),
+                                );
 
-                                self.is_rate_limited = true;
+                                if let Some(at) = msg.at {
+                                    self.record_display_latency(self.server_now() - at as f64);
+                                }
                             }
 
                             self.images_found += 1;
+                            self.last_new_at = Date::now();
 
                             true
                         } else {
@@ -262,17 +965,192 @@ impl Component for Model {
                         false
                     }
                 }
-                WsMessageType::UsersWatching => {
-                    if let Some(number) = msg.number {
-                        self.users_watching = number;
+                WsMessageType::Announce => {
+                    if let Some(text) = msg.text {
+                        self.show_toast(format!("Announcement: {}", text));
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Milestone => {
+                    if let Some(text) = msg.text {
+                        self.milestone = Some(text);
+                        self.timers.set(
+                            Timer::Milestone,
+                            Box::new(self.timeout_service.spawn(
+                                MILESTONE_DURATION,
+                                self.link.callback(|_| Msg::DismissMilestone),
+                            )),
+                        );
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Registered => {
+                    self.registered_name = msg.text;
+                    self.registered_finds = msg.number.unwrap_or(0);
+                    if let Some(name) = &self.registered_name {
+                        self.registration_name = name.clone();
+                    }
+                    if let Some(avatar) = msg.url {
+                        self.registration_avatar = avatar;
+                    }
+                    true
+                }
+                WsMessageType::Challenge => {
+                    if let Some(challenge) = Challenge::from_message(&msg) {
+                        self.challenge = Some(challenge);
+                        self.resend_pending_finds(0.0);
+                    }
+                    false
+                }
+                WsMessageType::BruteforceDisabled => {
+                    self.runtime_config.bruteforce_disabled = true;
+                    self.runtime_config.bruteforce_disabled_reason = msg.text;
+
+                    if self.state.is_bruteforcing() {
+                        self.link.send_message(Msg::Stop);
+                    }
+
+                    true
+                }
+                WsMessageType::ConfigUpdate => {
+                    if let Some(runtime_config) = msg
+                        .text
+                        .and_then(|text| serde_json::from_str::<RuntimeConfig>(&text).ok())
+                    {
+                        self.runtime_config = runtime_config;
+                        self.pacing.set_minimums(self.minimums());
+                        self.update_quiet_hours();
+
+                        if self.state.is_bruteforcing() {
+                            if self.is_bruteforce_enabled() {
+                                self.spawn_probe_interval();
+                            } else {
+                                self.link.send_message(Msg::Stop);
+                            }
+                        }
+
+                        self.spawn_rate_interval();
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Resync => {
+                    if let Some(start) = msg.number {
+                        self.seq.replay_from(start);
+                        self.is_resyncing = false;
+                    }
+                    false
+                }
+                WsMessageType::Authenticated => {
+                    self.account_name = msg.text;
+                    true
+                }
+                WsMessageType::Settings => {
+                    if let Some(settings) = msg
+                        .text
+                        .and_then(|text| serde_json::from_str::<Settings>(&text).ok())
+                    {
+                        self.apply_settings(settings);
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Error => {
+                    if let Some(text) = msg.text {
+                        self.show_toast(format!("Server error: {}", text));
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Ack => {
+                    if let Some(client_msg_id) = msg.client_msg_id {
+                        self.pending_finds.remove(&client_msg_id);
+                    }
+                    false
+                }
+                WsMessageType::Rejected => {
+                    if let Some(client_msg_id) = msg.client_msg_id {
+                        self.pending_finds.remove(&client_msg_id);
+                    }
+
+                    if let (Some(id), Some(reason)) = (msg.text, msg.reason) {
+                        self.show_toast(format!("Find {} was rejected: {}", id, reason));
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Time => {
+                    if let (Some(sent_at), Some(server_ms)) = (msg.number, msg.at) {
+                        self.record_clock_sample(sent_at as f64, server_ms as f64);
+                    }
+
+                    false
+                }
+                WsMessageType::Resume => {
+                    self.resume_token = msg.text;
+                    false
+                }
+                WsMessageType::Counts => {
+                    if let Some(counts) = msg
+                        .text
+                        .and_then(|text| serde_json::from_str::<Counts>(&text).ok())
+                    {
+                        self.users_watching = counts.watching;
+                        self.users_bruteforcing = counts.bruteforcing;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                WsMessageType::Throttled => {
+                    match msg.number.filter(|&secs| secs > 0) {
+                        Some(secs) => {
+                            self.swarm_backoff = Some(msg.text.unwrap_or_else(|| {
+                                "imgur is rate limiting — backing off".to_owned()
+                            }));
+                            // Lifts itself in case the server never says so.
+                            self.timers.set(
+                                Timer::SwarmBackoff,
+                                Box::new(self.timeout_service.spawn(
+                                    Duration::from_secs(secs),
+                                    self.link.callback(|_| Msg::SwarmBackoffOver),
+                                )),
+                            );
+                        }
+                        None => {
+                            self.swarm_backoff = None;
+                            self.timers.cancel(&Timer::SwarmBackoff);
+                        }
+                    }
+
+                    if self.state.is_bruteforcing() {
+                        self.spawn_probe_interval();
+                    }
+
+                    true
+                }
+                WsMessageType::Activity => {
+                    if let Some(text) = msg.text {
+                        push_evicting(&mut self.activity, text, TICKER_LEN, true);
                         true
                     } else {
                         false
                     }
                 }
-                WsMessageType::UsersBruteforcing => {
-                    if let Some(number) = msg.number {
-                        self.users_bruteforcing = number;
+                WsMessageType::Regions => {
+                    if let Some(region_counts) = msg
+                        .text
+                        .and_then(|text| serde_json::from_str::<RegionCounts>(&text).ok())
+                    {
+                        self.region_counts = region_counts;
                         true
                     } else {
                         false
@@ -281,39 +1159,27 @@ impl Component for Model {
                 _ => false,
             },
             Msg::TryFind => {
-                let alnum = iter::repeat(())
-                    .map(|()| thread_rng().sample(Alphanumeric))
-                    .take(7)
-                    .collect::<String>();
-
-                self.find_fetch_tasks.insert(
-                    alnum.to_owned(),
-                    self.fetch_service.fetch_binary_with_options(
-                        Request::get(format!("https://i.imgur.com/{}.png", &alnum))
-                            .body(Nothing)
-                            .unwrap(),
-                        FetchOptions {
-                            cache: None,
-                            credentials: None,
-                            redirect: Some(Redirect::Error),
-                            mode: None,
-                            referrer: None,
-                            referrer_policy: Some(ReferrerPolicy::NoReferrer),
-                            integrity: None,
-                        },
-                        self.link.callback(move |response: Response<Nothing>| {
-                            let (meta, _) = response.into_parts();
+                if self.find_fetch_tasks.len() >= MAX_PROBES_IN_FLIGHT {
+                    self.probes_skipped += 1;
+                    return false;
+                }
 
-                            let message = format!("{:#?}", meta);
+                let alnum = self.generator.id();
 
-                            if meta.status.as_u16() != 408 {
-                                Msg::Found((message, alnum.clone()))
-                            } else {
-                                Msg::NotFound(message)
-                            }
-                        }),
-                    ),
-                );
+                if self.mock.is_some() {
+                    if self.generator.rng().gen_bool(mock::FIND_PROBABILITY) {
+                        self.link.send_message(Msg::Found((String::new(), alnum)));
+                    } else {
+                        self.link
+                            .send_message(Msg::NotFound((String::new(), alnum)));
+                    }
+
+                    return false;
+                }
+
+                if let Some(task) = self.probe(alnum.clone(), Msg::Found, Msg::NotFound) {
+                    self.find_fetch_tasks.insert(alnum, task);
+                }
 
                 false
             }
@@ -321,234 +1187,2982 @@ impl Component for Model {
                 // self.console_service.log(&message);
 
                 self.find_fetch_tasks.remove(&data);
-                self.link.send_message(Msg::WsSend(WsMessage {
-                    msg_type: WsMessageType::New,
-                    text: Some(data),
-                    number: None,
-                }));
+                self.submit_find(data);
 
                 self.images_found_self += 1;
                 self.requests_per_second_current += 1;
                 self.total_requests += 1;
+                self.record_probe(true);
 
                 true
             }
-            Msg::NotFound(message) => {
+            Msg::NotFound((message, data)) => {
                 // self.console_service.log(&message);
 
+                self.find_fetch_tasks.remove(&data);
+
                 self.requests_per_second_current += 1;
                 self.total_requests += 1;
+                self.record_probe(false);
 
                 true
             }
-            Msg::IntervalChanged(new_interval) => {
-                if let Ok(interval) = new_interval.parse::<u64>() {
-                    self.interval = Duration::from_millis(interval);
+            Msg::ManualIdChanged(value) => {
+                self.manual_id = value;
+                self.manual_error = None;
+
+                false
+            }
+            Msg::SubmitManualId => match parse_image_link(&self.manual_id) {
+                Some(id) if self.mock.is_some() => {
+                    self.link
+                        .send_message(Msg::ManualIdFound((String::new(), id)));
+
+                    false
                 }
+                Some(id) => {
+                    self.manual_error = None;
+                    self.manual_fetch_task =
+                        self.probe(id, Msg::ManualIdFound, Msg::ManualIdNotFound);
 
-                if self.is_started {
-                    self.interval_task = Some(
-                        self.interval_service
-                            .spawn(self.interval, self.link.callback(|_| Msg::TryFind)),
-                    );
+                    true
                 }
+                None => {
+                    self.manual_error = Some("This is not an imgur image link or ID.".to_owned());
 
-                false
-            }
-            Msg::LoadedChanged(new_loaded) => {
-                if let Ok(loaded) = new_loaded.parse::<usize>() {
-                    self.concurrent_loaded = loaded;
+                    true
                 }
+            },
+            Msg::ManualIdFound((_, id)) => {
+                self.manual_fetch_task = None;
+                self.manual_id.clear();
+                self.submit_find(id);
+                self.show_toast("Thanks, the image was submitted.".to_owned());
 
-                false
+                true
             }
-            Msg::ShowModeSelected(value) => {
-                self.show_from_top = value;
+            Msg::ManualIdNotFound((_, id)) => {
+                self.manual_fetch_task = None;
+                self.manual_error = Some(format!("Image {} does not exist on imgur.", id));
 
                 true
             }
-            Msg::RateLimitChanged(new_rate_limit) => {
-                if let Ok(rate_limit) = new_rate_limit.parse::<u64>() {
-                    self.rate_limit = rate_limit;
+            Msg::IntervalChanged(new_interval) => {
+                let interval = parse_number(&new_interval, MIN_INTERVAL_MS, u64::MAX, " ms");
+
+                if let Some(interval) = self.check_setting("interval", interval) {
+                    self.pacing.set_interval(Duration::from_millis(interval));
 
-                    if self.rate_limit != 0 {
-                        self.rate_interval_task = Some(self.interval_service.spawn(
-                            Duration::from_secs(self.rate_limit),
-                            self.link.callback(|_| Msg::ResetRateLimit),
-                        ));
+                    if self.state.is_bruteforcing() {
+                        self.spawn_probe_interval();
                     }
+
+                    self.settings_changed();
                 }
 
-                false
+                true
             }
-            Msg::Start => {
-                if self.is_started == false {
-                    self.interval_task = Some(
-                        self.interval_service
-                            .spawn(self.interval, self.link.callback(|_| Msg::TryFind)),
-                    );
-
-                    self.link.send_message(Msg::WsSend(WsMessage {
-                        msg_type: WsMessageType::Start,
-                        text: None,
-                        number: None,
-                    }));
+            Msg::QuietStartChanged(value) => {
+                if let Some(start) = self.check_setting("quiet-hours", parse_optional_time(&value))
+                {
+                    self.quiet_hours = start.map(|start| QuietHours {
+                        start,
+                        end: self
+                            .quiet_hours
+                            .map_or(start, |quiet_hours| quiet_hours.end),
+                    });
+                    self.update_quiet_hours();
+                    self.settings_changed();
                 }
 
-                self.is_started = true;
+                true
+            }
+            Msg::QuietEndChanged(value) => {
+                if let Some(end) = self.check_setting("quiet-hours", parse_optional_time(&value)) {
+                    self.quiet_hours = end.map(|end| QuietHours {
+                        start: self
+                            .quiet_hours
+                            .map_or(end, |quiet_hours| quiet_hours.start),
+                        end,
+                    });
+                    self.update_quiet_hours();
+                    self.settings_changed();
+                }
 
-                false
+                true
             }
-            Msg::Stop => {
-                self.interval_task = None;
+            Msg::ArchiveFallbackChanged(new_secs) => {
+                let secs = parse_number(&new_secs, 0, u64::MAX, " s");
 
-                if self.is_started == true {
-                    self.link.send_message(Msg::WsSend(WsMessage {
-                        msg_type: WsMessageType::Stop,
-                        text: None,
-                        number: None,
-                    }));
+                if let Some(secs) = self.check_setting("archive-fallback", secs) {
+                    self.archive_fallback_secs = secs;
+                    self.settings_changed();
                 }
 
-                self.is_started = false;
-
-                false
+                true
             }
-            Msg::ResetRequestsPerSecond => {
-                self.requests_per_second = self.requests_per_second_current;
+            Msg::LoadedChanged(new_loaded) => {
+                let loaded = parse_number(&new_loaded, 0, MAX_IMAGES as u64, "");
+
+                if let Some(loaded) = self.check_setting("images", loaded) {
+                    self.concurrent_loaded = loaded as usize;
+                    self.settings_changed();
+                }
+
+                true
+            }
+            Msg::ShowModeSelected(value) => {
+                self.show_from_top = value;
+                self.settings_changed();
+
+                true
+            }
+            Msg::TileSizeSelected(tile_size) => {
+                self.tile_size = tile_size;
+                self.settings_changed();
+
+                true
+            }
+            Msg::ImageClickSelected(image_click) => {
+                self.image_click = image_click;
+                self.settings_changed();
+
+                true
+            }
+            Msg::ColumnsChanged(new_columns) => {
+                let columns = parse_number(&new_columns, 0, u64::MAX, "");
+
+                if let Some(columns) = self.check_setting("columns", columns) {
+                    self.columns = columns as usize;
+                    self.settings_changed();
+                }
+
+                true
+            }
+            Msg::RateLimitChanged(new_rate_limit) => {
+                let rate_limit = parse_number(
+                    &new_rate_limit,
+                    self.runtime_config.min_rate_limit,
+                    u64::MAX,
+                    " s",
+                );
+
+                if let Some(rate_limit) = self.check_setting("delay", rate_limit) {
+                    self.set_rate_limit(rate_limit);
+                    self.settings_changed();
+                }
+
+                true
+            }
+            Msg::Start => {
+                if self.is_bruteforce_enabled() && self.transition(Event::Start) {
+                    self.spawn_probe_interval();
+                    self.session = Some(Session::new(Date::now()));
+                    self.reported_requests = self.total_requests;
+                    self.timers.set(
+                        Timer::ReportRate,
+                        Box::new(self.interval_service.spawn(
+                            RATE_REPORT_INTERVAL,
+                            self.link.callback(|_| Msg::ReportRate),
+                        )),
+                    );
+
+                    self.link
+                        .send_message(Msg::WsSend(WsMessage::new(WsMessageType::Start)));
+                }
+
+                false
+            }
+            Msg::Stop => {
+                if !self.transition(Event::Stop) {
+                    return false;
+                }
+
+                self.timers.cancel(&Timer::Probe);
+                self.timers.cancel(&Timer::ReportRate);
+                self.link
+                    .send_message(Msg::WsSend(WsMessage::new(WsMessageType::Stop)));
+                self.save_achievements();
+                self.save_lifetime_stats();
+
+                if let Some(session) = self.session.take() {
+                    self.session_summary = Some(session.summary(Date::now()));
+
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::ResetRequestsPerSecond => {
+                self.requests_per_second = self.requests_per_second_current;
                 self.requests_per_second_current = 0;
+                self.update_quiet_hours();
+                self.check_archive_fallback();
+
+                true
+            }
+            Msg::ResetRateLimit => {
+                self.pacing.reset_rate_limit();
+
+                false
+            }
+            Msg::ImageFailed(id) => {
+                if let Some(image) = self.image(&id) {
+                    if image.retries < IMAGE_MAX_RETRIES {
+                        let retry_id = id.clone();
+
+                        self.image_retry_timers.set(
+                            id,
+                            Box::new(
+                                self.timeout_service.spawn(
+                                    Duration::from_secs(1 << image.retries),
+                                    self.link
+                                        .callback(move |_| Msg::ImageRetry(retry_id.clone())),
+                                ),
+                            ),
+                        );
+
+                        false
+                    } else {
+                        self.image_retry_timers.cancel(&id);
+                        self.remove_image(&id);
+                        self.images_failed += 1;
+                        self.lifetime_stats.images_failed += 1;
+
+                        true
+                    }
+                } else {
+                    false
+                }
+            }
+            Msg::ImageRetry(id) => {
+                self.image_retry_timers.cancel(&id);
+
+                if let Some(image) = self.image_mut(&id) {
+                    image.retries += 1;
+
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::ViewportResized => {
+                let viewport_width = window().inner_width();
+
+                if viewport_width != self.viewport_width {
+                    self.viewport_width = viewport_width;
+
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::ToggleSettings => {
+                self.settings_open = !self.settings_open;
+
+                true
+            }
+            Msg::SettingsTabSelected(tab) => {
+                self.settings_tab = tab;
+
+                true
+            }
+            Msg::ResetSettings => {
+                self.settings_errors.clear();
+                self.apply_settings(Settings::default());
+                self.settings_changed();
+
+                true
+            }
+            Msg::ApplyPreset(name) => {
+                if let Some(preset) = presets::find(&self.user_presets, &name) {
+                    let previous = self.settings();
+
+                    self.settings_errors.clear();
+                    self.apply_settings(preset.settings);
+                    self.settings_changed();
+                    self.push_undo(
+                        format!("{} settings applied.", preset.name),
+                        Undoable::Settings(previous),
+                    );
+                }
+
+                true
+            }
+            Msg::PresetNameChanged(name) => {
+                self.preset_name = name;
+
+                false
+            }
+            Msg::SavePreset => {
+                match self.user_presets.save(&self.preset_name, self.settings()) {
+                    Ok(()) => {
+                        self.save_user_presets();
+                        self.show_toast(format!("Preset {} saved.", self.preset_name.trim()));
+                    }
+                    Err(err) => self.show_toast(err),
+                }
+
+                true
+            }
+            Msg::DeletePreset => {
+                if self.user_presets.remove(&self.preset_name) {
+                    self.save_user_presets();
+                    self.show_toast(format!("Preset {} deleted.", self.preset_name.trim()));
+                } else {
+                    self.show_toast(format!(
+                        "No saved preset is called {}.",
+                        self.preset_name.trim()
+                    ));
+                }
+
+                true
+            }
+            Msg::ExportPresets => {
+                if let Ok(json) = serde_json::to_string_pretty(&self.user_presets) {
+                    download_file("random-imgur-wall-presets.json", "application/json", &json);
+                }
+
+                false
+            }
+            Msg::PresetImportChanged(json) => {
+                self.preset_import = json;
+
+                false
+            }
+            Msg::ImportPresets => {
+                match serde_json::from_str::<UserPresets>(&self.preset_import) {
+                    Ok(imported) => {
+                        let count = self.user_presets.import(imported);
+                        self.save_user_presets();
+                        self.preset_import.clear();
+                        self.show_toast(format!("{} presets imported.", count));
+                    }
+                    Err(err) => self.show_toast(format!("Could not import presets: {}", err)),
+                }
+
+                true
+            }
+            Msg::BlurImagesToggled => {
+                self.blur_images = !self.blur_images;
+                self.settings_changed();
+
+                true
+            }
+            Msg::WatchOnlyToggled => {
+                self.watch_only = !self.watch_only;
+                self.settings_changed();
+
+                true
+            }
+            Msg::TextOnlyToggled => {
+                self.text_only = !self.text_only;
+                self.settings_changed();
+
+                true
+            }
+            Msg::ImageMeasured(id, width, height) => {
+                self.prefetcher.done();
+                self.prefetch();
+
+                match self.measuring.remove(&id) {
+                    Some(mut image) => {
+                        // Failed loads get their retries on the wall.
+                        image.size = Some((width, height))
+                            .filter(|&(width, height)| width > 0 && height > 0);
+                        self.insert_image(image);
+
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Msg::LayoutSelected(layout) => {
+                self.set_layout(layout);
+                self.settings_changed();
+
+                true
+            }
+            Msg::FeedFilterSelected(feed_filter) => {
+                self.feed_filter = feed_filter;
+                self.settings_changed();
+
+                true
+            }
+            Msg::SourceToggled(source) => {
+                match self
+                    .hidden_sources
+                    .iter()
+                    .position(|&hidden| hidden == source)
+                {
+                    Some(index) => {
+                        self.hidden_sources.remove(index);
+                    }
+                    None => self.hidden_sources.push(source),
+                }
+                self.settings_changed();
+
+                true
+            }
+            Msg::RevealImage(id) => match self.image_mut(&id) {
+                Some(image) => {
+                    image.revealed = true;
+                    true
+                }
+                None => false,
+            },
+            Msg::OpenLightbox(id) => {
+                self.lightbox = Some(id);
+
+                true
+            }
+            Msg::CloseLightbox => {
+                self.lightbox = None;
+
+                true
+            }
+            Msg::LightboxNext => self.move_lightbox(1),
+            Msg::LightboxPrevious => self.move_lightbox(-1),
+            Msg::TouchStart(x, y) => {
+                self.touch_start = Some((x, y));
+
+                false
+            }
+            Msg::TouchEnd(x, y) => {
+                if let Some((start_x, start_y)) = self.touch_start.take() {
+                    let (dx, dy) = (x - start_x, y - start_y);
+
+                    if self.lightbox.is_some() && dx.abs() > dy.abs() {
+                        if dx >= SWIPE_MIN_DISTANCE {
+                            self.link.send_message(Msg::LightboxPrevious);
+                        } else if dx <= -SWIPE_MIN_DISTANCE {
+                            self.link.send_message(Msg::LightboxNext);
+                        }
+                    } else if self.lightbox.is_none()
+                        && dy >= PULL_TO_REFRESH_DISTANCE
+                        && page_y_offset() <= 0.0
+                    {
+                        self.link.send_message(Msg::WsReconnect);
+                    }
+                }
+
+                false
+            }
+            Msg::BatteryChanged(battery_low) => {
+                let was_data_saver = self.is_data_saver();
+                self.battery_low = battery_low;
+                self.data_saver_changed(was_data_saver)
+            }
+            Msg::DataSaverModeSelected(mode) => {
+                let was_data_saver = self.is_data_saver();
+                self.data_saver_mode = mode;
+                self.settings_changed();
+                self.data_saver_changed(was_data_saver)
+            }
+            Msg::CacheBustingToggled => {
+                self.cache_busting = !self.cache_busting;
+                self.settings_changed();
+
+                true
+            }
+            Msg::NoStoreToggled => {
+                self.no_store = !self.no_store;
+                self.settings_changed();
+
+                true
+            }
+            Msg::ProbeReferrerSelected(probe_referrer) => {
+                self.probe_referrer = probe_referrer;
+                self.settings_changed();
+
+                true
+            }
+            Msg::CloseSessionSummary => {
+                self.session_summary = None;
+
+                true
+            }
+            Msg::ExportSessionSummary => {
+                if let Some(summary) = &self.session_summary {
+                    if let Ok(json) = serde_json::to_string_pretty(summary) {
+                        download_file("random-imgur-wall-session.json", "application/json", &json);
+                    }
+                }
+
+                false
+            }
+            Msg::ShareSessionSummary => {
+                if let Some(summary) = &self.session_summary {
+                    share_text(&summary.share_text());
+                }
+
+                false
+            }
+            Msg::DismissMilestone => {
+                self.milestone = None;
+                self.timers.cancel(&Timer::Milestone);
+
+                true
+            }
+            Msg::RegistrationNameChanged(name) => {
+                self.registration_name = name;
+
+                false
+            }
+            Msg::RegistrationAvatarChanged(avatar) => {
+                self.registration_avatar = avatar;
+
+                false
+            }
+            Msg::Register => {
+                let avatar = self.registration_avatar.trim();
+
+                self.link.send_message(Msg::WsSend(WsMessage {
+                    text: Some(self.registration_name.trim().to_owned()),
+                    url: if avatar.is_empty() {
+                        None
+                    } else {
+                        Some(avatar.to_owned())
+                    },
+                    ..WsMessage::new(WsMessageType::Register)
+                }));
+
+                false
+            }
+            Msg::AuthProvidersFetched(providers) => {
+                self.auth_providers = providers;
+
+                true
+            }
+            Msg::IotdFetched(history) => {
+                self.iotd_history = history;
+                self.iotd_index = 0;
+
+                true
+            }
+            Msg::IotdSelected(index) => {
+                self.iotd_index = index;
+
+                true
+            }
+            Msg::PlaybackDateChanged(date) => {
+                self.playback_date = date;
+
+                false
+            }
+            Msg::PlaybackLoad => {
+                let url = self.config.as_ref().map(|config| {
+                    server_url(
+                        &config.ws_url,
+                        &self.with_session(format!("/api/archive?date={}", self.playback_date)),
+                    )
+                });
+
+                if let Some(request) = url.and_then(|url| self.get(url)) {
+                    self.archive_fetch_task = Some(self.fetch_service.fetch(
+                        request,
+                        self.link.callback(
+                            |response: Response<Json<Result<DayArchive, Error>>>| {
+                                let (meta, Json(archive)) = response.into_parts();
+                                if meta.status.is_success() {
+                                    Msg::PlaybackLoaded(archive)
+                                } else {
+                                    Msg::PlaybackLoaded(Err(anyhow!(
+                                        "{}: no archive for this day",
+                                        meta.status
+                                    )))
+                                }
+                            },
+                        ),
+                    ));
+                }
+
+                false
+            }
+            Msg::PlaybackLoaded(Ok(archive)) => {
+                self.archive_fetch_task = None;
+                self.playback = Some(Playback::new(archive, self.playback_speed));
+                self.timers.set(
+                    Timer::Playback,
+                    Box::new(
+                        self.interval_service
+                            .spawn(PLAYBACK_TICK, self.link.callback(|_| Msg::PlaybackTick)),
+                    ),
+                );
+
+                true
+            }
+            Msg::RandomFromArchive => {
+                self.fetch_random_images(RANDOM_IMAGES, false);
+
+                false
+            }
+            Msg::RandomFromArchiveLoaded(Ok(finds), is_fallback) => {
+                self.random_fetch_task = None;
+
+                if finds.is_empty() && !is_fallback {
+                    self.show_toast("The archive is empty for now.".to_owned());
+                }
+
+                for find in finds {
+                    self.show_image(
+                        find.id,
+                        true,
+                        find.at,
+                        Some(FindSource::ArchiveReplay),
+                        Vec::new(),
+                    );
+                }
+
+                true
+            }
+            Msg::RandomFromArchiveLoaded(Err(err), is_fallback) => {
+                self.random_fetch_task = None;
+
+                if is_fallback {
+                    false
+                } else {
+                    self.show_toast(format!("Could not load images from the archive: {}", err));
+
+                    true
+                }
+            }
+            Msg::PlaybackLoaded(Err(err)) => {
+                self.archive_fetch_task = None;
+                self.show_toast(format!("Could not load the time-lapse: {}", err));
+
+                true
+            }
+            Msg::PlaybackToggle => {
+                if let Some(playback) = &mut self.playback {
+                    if playback.is_finished() {
+                        let start = playback.archive.start as f64;
+                        playback.seek(start);
+                    }
+
+                    playback.is_playing = !playback.is_playing;
+                }
+
+                true
+            }
+            Msg::PlaybackTick => match &mut self.playback {
+                Some(playback) if playback.is_playing => {
+                    playback.advance(PLAYBACK_TICK.as_secs_f64());
+
+                    for find in playback.upcoming(PLAYBACK_PREFETCH) {
+                        self.prefetcher.push(find.id.clone(), Priority::Low);
+                    }
+
+                    self.prefetch();
+
+                    true
+                }
+                _ => false,
+            },
+            Msg::PlaybackSeek(position) => {
+                if let (Some(playback), Ok(position)) = (&mut self.playback, position.parse()) {
+                    playback.seek(position);
+
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::PlaybackSpeedSelected(speed) => {
+                self.playback_speed = speed;
+
+                if let Some(playback) = &mut self.playback {
+                    playback.speed = speed;
+                }
+
+                false
+            }
+            Msg::PlaybackExit => {
+                self.playback = None;
+                self.timers.cancel(&Timer::Playback);
+
+                true
+            }
+            Msg::ResendPendingFinds => {
+                self.resend_pending_finds(ACK_TIMEOUT.as_millis() as f64);
+
+                false
+            }
+            Msg::ReportRate => {
+                let requests = self.total_requests - self.reported_requests;
+                self.reported_requests = self.total_requests;
+
+                self.link.send_message(Msg::WsSend(WsMessage {
+                    number: Some(requests / RATE_REPORT_INTERVAL.as_secs()),
+                    ..WsMessage::new(WsMessageType::Rate)
+                }));
+
+                if self.throttled_probes > 0 {
+                    self.link.send_message(Msg::WsSend(WsMessage {
+                        number: Some(mem::take(&mut self.throttled_probes)),
+                        ..WsMessage::new(WsMessageType::Throttled)
+                    }));
+                }
+
+                false
+            }
+            Msg::ProbeThrottled(id) => {
+                if self.find_fetch_tasks.remove(&id).is_none() && self.manual_fetch_task.is_some() {
+                    self.manual_fetch_task = None;
+                    self.manual_error =
+                        Some("imgur is rate limiting, try again in a minute.".to_owned());
+                }
+
+                self.requests_per_second_current += 1;
+                self.total_requests += 1;
+                self.throttled_probes += 1;
+
+                true
+            }
+            Msg::SwarmBackoffOver => {
+                self.swarm_backoff = None;
+
+                if self.state.is_bruteforcing() {
+                    self.spawn_probe_interval();
+                }
+
+                true
+            }
+            Msg::Logout => {
+                self.storage.remove(SESSION_TOKEN_KEY);
+                self.session_token = None;
+                self.account_name = None;
+                self.registered_name = None;
+                self.link.send_message(Msg::WsReconnect);
+
+                true
+            }
+            Msg::DismissToast(id) => {
+                self.toast_timers.cancel(&id);
+                self.toasts.retain(|toast| toast.id != id);
+
+                true
+            }
+            Msg::OpenContextMenu(id, x, y) => {
+                self.context_menu = Some(ContextMenu { id, x, y });
+
+                true
+            }
+            Msg::CloseContextMenu => {
+                self.context_menu = None;
+
+                true
+            }
+            Msg::CopyImageLink(id) => {
+                self.context_menu = None;
+
+                if copy_to_clipboard(&original_url(&id)) {
+                    self.show_toast("Link copied.".to_owned());
+                } else {
+                    self.show_toast("Your browser does not allow copying the link.".to_owned());
+                }
+
+                true
+            }
+            Msg::CopyLinks { session } => {
+                let ids = if session {
+                    self.session_finds
+                        .iter()
+                        .map(|(id, _)| id)
+                        .collect::<Vec<_>>()
+                } else {
+                    self.images
+                        .iter()
+                        .chain(&self.own_images)
+                        .map(|image| &image.id)
+                        .collect()
+                };
+                let links = ids
+                    .iter()
+                    .map(|id| original_url(id))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                if copy_to_clipboard(&links) {
+                    self.show_toast(format!("{} links copied.", ids.len()));
+                } else {
+                    self.show_toast("Your browser does not allow copying the links.".to_owned());
+                }
+
+                true
+            }
+            Msg::ExportFinds(format) => {
+                let file = format.render(
+                    self.session_finds
+                        .iter()
+                        .map(|(id, found_at)| (id.as_str(), *found_at)),
+                );
+                download_file(format.file_name(), format.mime(), &file);
+
+                false
+            }
+            Msg::ToggleFavorite(id) => {
+                self.context_menu = None;
+
+                match self.favorites.remove(&id) {
+                    Some(position) => self.push_undo(
+                        "Removed from favorites.".to_owned(),
+                        Undoable::Unfavorite(position, id),
+                    ),
+                    None => {
+                        self.favorites.add(id);
+                        self.show_toast("Added to favorites.".to_owned());
+                    }
+                }
+
+                self.save_favorites();
+
+                true
+            }
+            Msg::ReportImage(id) => {
+                // Imgur's report form opens in a new tab, the image leaves the
+                // wall meanwhile.
+                self.update(Msg::HideImage(id))
+            }
+            Msg::HideImage(id) => {
+                self.context_menu = None;
+
+                if let Some((position, image)) = self.remove_image(&id) {
+                    self.image_retry_timers.cancel(&id);
+
+                    if self.lightbox.as_ref() == Some(&id) {
+                        self.lightbox = None;
+                    }
+
+                    self.push_undo("Image hidden.".to_owned(), Undoable::Hide(position, image));
+
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::ClearWall => {
+                if self.wall_len() == 0
+                    || !confirm("Remove every image from the wall? New finds keep showing up.")
+                {
+                    return false;
+                }
+
+                self.image_retry_timers.cancel_all();
+                self.lightbox = None;
+
+                let mut images = mem::take(&mut self.images);
+                images.append(&mut self.own_images);
+                self.push_undo("Wall cleared.".to_owned(), Undoable::ClearWall(images));
+
+                true
+            }
+            Msg::ClearStats => {
+                if !confirm("Reset the statistics of this session to zero?") {
+                    return false;
+                }
+
+                let stats = self.take_session_stats();
+                self.push_undo("Statistics reset.".to_owned(), Undoable::ClearStats(stats));
+
+                true
+            }
+            Msg::Undo(toast_id) => {
+                self.toast_timers.cancel(&toast_id);
+                self.toasts.retain(|toast| toast.id != toast_id);
+
+                if let Some(action) = self.undo_stack.take(toast_id) {
+                    self.undo(action);
+                }
+
+                true
+            }
+            Msg::WsReconnect => {
+                self.ws_task = None;
+                self.timers.cancel(&Timer::Reconnect);
+                self.link.send_message(Msg::WsConnect);
+
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <body class=self.config.as_ref().map(|config| config.theme).unwrap_or_default().class()
+                  ontouchstart=self.link.callback(|e: TouchStart| touch_message(&e.changed_touches(), Msg::TouchStart))
+                  ontouchend=self.link.callback(|e: TouchEnd| touch_message(&e.changed_touches(), Msg::TouchEnd))>
+                <header>
+                    <h1>{ "Random Imgur Wall" }</h1>
+                </header>
+                { self.view_config_error() }
+                { self.view_milestone() }
+                { self.view_ticker() }
+                { self.view_swarm_backoff() }
+                <main>
+                    <div id="container">
+                        <section id="info">
+                            <h2>{ "NSFL Warning" }</h2>
+                            <p>{ "Images show up randomly and you may see terrible things staying on this site, watch with care." }</p>
+                            <p>
+                                <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href=REPORT_URL>
+                                    { "Report abusive content" }
+                                </a>
+                            </p>
+                            <p>
+                                <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href={ "https://github.com/leo-lb/random-imgur-wall" }>
+                                    { "Source code" }
+                                </a>
+                            </p>
+                            <p>
+                                { "Thanks to " }
+                                <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href={ "https://www.reddit.com/user/quickscoperdoge" }>
+                                    { "u/quickscoperdoge" }
+                                </a>
+                                { " for the revamped design!" }
+                            </p>
+                        </section>
+                        <section id="settings" class=self.settings_class()>
+                            <h2 onclick=self.link.callback(|_| Msg::ToggleSettings)>{ "Settings" }</h2>
+                            <div class="settings-tabs">
+                                <button type="button" class=self.settings_tab_class(SettingsTab::Basic) onclick=self.link.callback(|_| Msg::SettingsTabSelected(SettingsTab::Basic))>{ "Basic" }</button>
+                                <button type="button" class=self.settings_tab_class(SettingsTab::Advanced) onclick=self.link.callback(|_| Msg::SettingsTabSelected(SettingsTab::Advanced))>{ "Advanced" }</button>
+                            </div>
+                            { match self.settings_tab {
+                                SettingsTab::Basic => self.view_basic_settings(),
+                                SettingsTab::Advanced => self.view_advanced_settings(),
+                            } }
+                            <p style="overflow: auto;">
+                                <button type="button" style="margin: auto; width: 50%;" disabled=!self.is_bruteforce_enabled() onclick=self.link.callback(|_| Msg::Start)>{ "Start" }</button> //<!-- modify this -->
+                                <button type="button" style="margin: auto; width: 50%;" onclick=self.link.callback(|_| Msg::Stop)>{ "Stop" }</button> //<!-- modify this -->
+                            </p>
+                            { self.view_watch_only() }
+                            { self.view_quiet_hours() }
+                        </section>
+
+                        <section id="stats">
+                            <h2>{ "Statistics" }</h2>
+                            <table>
+                                <tr>
+                                    <th></th>
+                                    <th>{ "This session" }</th>
+                                    <th>{ "All time" }</th>
+                                </tr>
+                                <tr>
+                                    <td>{ "Total number of requests" }</td>
+                                    <td>{ self.total_requests }</td>
+                                    <td>{ self.lifetime_stats.requests }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Requests completed per second" }</td>
+                                    <td colspan=2>{ self.requests_per_second }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Images you found" }</td>
+                                    <td>{ self.images_found_self }</td>
+                                    <td>{ self.lifetime_stats.images_found }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Images everyone found" }</td>
+                                    <td colspan=2>{ self.images_found }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Of which yours / others'" }</td>
+                                    <td colspan=2>{ format!("{} / {}", self.own_finds_broadcast, self.others_finds_broadcast) }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Images on the wall" }</td>
+                                    <td colspan=2>{ self.wall_len() }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Images removed to make room for new ones" }</td>
+                                    <td colspan=2>{ self.images_evicted }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Requests skipped, too many still pending" }</td>
+                                    <td colspan=2>{ self.probes_skipped }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Images that failed to load" }</td>
+                                    <td>{ self.images_failed }</td>
+                                    <td>{ self.lifetime_stats.images_failed }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Users watching" }</td>
+                                    <td colspan=2>{ self.users_watching }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Users bruteforcing" }</td>
+                                    <td colspan=2>{ self.users_bruteforcing }</td>
+                                </tr>
+                                { self.view_region_counts() }
+                                <tr>
+                                    <td>{ "Median delay between a find and its display" }</td>
+                                    <td colspan=2>{ self.median_display_latency() }</td>
+                                </tr>
+                                {
+                                    match self.upstreams.current() {
+                                        Some(upstream) if self.upstreams.has_mirrors() => html! {
+                                            <tr>
+                                                <td>{ if self.upstreams.is_connected() { "Connected to" } else { "Connecting to" } }</td>
+                                                <td colspan=2>{ upstream }</td>
+                                            </tr>
+                                        },
+                                        _ => html! {},
+                                    }
+                                }
+                                <tr>
+                                    <td>{ "Data sent to the server" }</td>
+                                    <td colspan=2>{ format_bytes(self.bandwidth.sent) }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Data received from the server" }</td>
+                                    <td colspan=2>{ format_bytes(self.bandwidth.received) }</td>
+                                </tr>
+                                <tr>
+                                    <td>{ "Protocol overhead per image everyone found" }</td>
+                                    <td colspan=2>{ self.protocol_overhead() }</td>
+                                </tr>
+                            </table>
+                            <p>
+                                <button type="button" onclick=self.link.callback(|_| Msg::ClearStats)>{ "Reset session statistics" }</button>
+                            </p>
+                        </section>
+                        { self.view_trophies() }
+                        { self.view_playback() }
+                        { self.view_profile() }
+                    </div>
+                    <section id="images">
+                        <h2 style="text-align: center;">{ "Images" }</h2>
+                        <p style="text-align: center;">
+                            <button type="button" disabled=self.playback.is_some() onclick=self.link.callback(|_| Msg::RandomFromArchive)>{ "Random from archive" }</button>
+                            <button type="button" disabled=self.playback.is_some() || self.wall_len() == 0 onclick=self.link.callback(|_| Msg::ClearWall)>{ "Clear wall" }</button>
+                            <button type="button" disabled=self.wall_len() == 0 onclick=self.link.callback(|_| Msg::CopyLinks { session: false })>{ "Copy links on the wall" }</button>
+                            <button type="button" disabled=self.session_finds.is_empty() onclick=self.link.callback(|_| Msg::CopyLinks { session: true })>{ "Copy every link this session" }</button>
+                            <button type="button" onclick=self.link.callback(|_| Msg::TextOnlyToggled)>{ if self.text_only { "Show images" } else { "Text only" } }</button>
+                        </p>
+                        { self.view_export() }
+                        { self.view_feed_controls() }
+                        { self.view_manual_submission() }
+                        { self.view_iotd() }
+                        {
+                            match &self.playback {
+                                Some(playback) => html! {
+                                    <div id="gallery" class=self.gallery_class()>
+                                        { for playback
+                                            .shown(self.wall_capacity())
+                                            .map(|find| self.view_image(&Image::new(find.id.clone()))) }
+                                    </div>
+                                },
+                                None if self.layout == WallLayout::Split => html! {
+                                    <div id="panes">
+                                        <div class="pane">
+                                            <h3>{ "Your finds" }</h3>
+                                            { self.view_gallery("own-gallery", &self.own_images) }
+                                        </div>
+                                        <div class="pane">
+                                            <h3>{ "Community finds" }</h3>
+                                            { self.view_gallery("gallery", &self.images) }
+                                        </div>
+                                    </div>
+                                },
+                                None => self.view_gallery("gallery", &self.images),
+                            }
+                        }
+                    </section>
+                </main>
+                { self.view_lightbox() }
+                { self.view_context_menu() }
+                { self.view_session_summary() }
+                <div id="toasts">
+                    { for self.toasts.iter().map(|toast| {
+                        let id = toast.id;
+
+                        html! {
+                            <div class="toast" onclick=self.link.callback(move |_| Msg::DismissToast(id))>
+                                { &toast.text }
+                                { if toast.undo {
+                                    html! { <button type="button" class="undo" onclick=self.link.callback(move |_| Msg::Undo(id))>{ "Undo" }</button> }
+                                } else {
+                                    html! {}
+                                } }
+                            </div>
+                        }
+                    }) }
+                </div>
+                <footer>
+                </footer>
+            </body>
+        }
+    }
+}
+
+impl Model {
+    fn settings(&self) -> Settings {
+        Settings {
+            interval_ms: self.pacing.interval().as_millis() as u64,
+            concurrent_loaded: self.concurrent_loaded,
+            show_from_top: self.show_from_top,
+            rate_limit: self.pacing.rate_limit(),
+            tile_size: self.tile_size,
+            columns: self.columns,
+            data_saver_mode: self.data_saver_mode,
+            quiet_hours: self.quiet_hours,
+            archive_fallback_secs: self.archive_fallback_secs,
+            cache_busting: self.cache_busting,
+            no_store: self.no_store,
+            probe_referrer: self.probe_referrer,
+            image_click: self.image_click,
+            blur_images: self.blur_images,
+            watch_only: self.watch_only,
+            text_only: self.text_only,
+            hidden_sources: self.hidden_sources.clone(),
+            feed_filter: self.feed_filter,
+            layout: self.layout,
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings) {
+        self.pacing
+            .set_interval(Duration::from_millis(settings.interval_ms));
+        self.concurrent_loaded = settings.concurrent_loaded;
+        self.show_from_top = settings.show_from_top;
+        self.tile_size = settings.tile_size;
+        self.columns = settings.columns;
+        self.data_saver_mode = settings.data_saver_mode;
+        self.quiet_hours = settings.quiet_hours;
+        self.archive_fallback_secs = settings.archive_fallback_secs;
+        self.cache_busting = settings.cache_busting;
+        self.no_store = settings.no_store;
+        self.probe_referrer = settings.probe_referrer;
+        self.image_click = settings.image_click;
+        self.blur_images = settings.blur_images;
+        self.text_only = settings.text_only;
+        self.hidden_sources = settings.hidden_sources;
+        self.feed_filter = settings.feed_filter;
+        self.set_layout(settings.layout);
+        let was_watch_only = mem::replace(&mut self.watch_only, settings.watch_only);
+        self.update_quiet_hours();
+
+        if self.state.is_bruteforcing() {
+            if self.watch_only && !was_watch_only {
+                self.link.send_message(Msg::Stop);
+            } else {
+                self.spawn_probe_interval();
+            }
+        }
+
+        self.set_rate_limit(settings.rate_limit);
+    }
+
+    fn settings_changed(&mut self) {
+        if self.account_name.is_some() {
+            if let Ok(settings) = serde_json::to_string(&self.settings()) {
+                self.link.send_message(Msg::WsSend(WsMessage {
+                    text: Some(settings),
+                    ..WsMessage::new(WsMessageType::SyncSettings)
+                }));
+            }
+        }
+    }
+
+    /// Drops broadcasts already seen and, on a gap in sequence numbers,
+    /// asks the server to replay from the first one missed.
+    fn check_seq(&mut self, seq: Option<u64>) -> bool {
+        match self.seq.check(seq) {
+            SeqCheck::Fresh => true,
+            SeqCheck::Duplicate => false,
+            SeqCheck::Gap(from_seq) => {
+                if !self.is_resyncing {
+                    self.request_resync(from_seq);
+                }
+                false
+            }
+        }
+    }
+
+    /// A `GET` request for `url`, or `None` after reporting why there can
+    /// be none.
+    fn get(&mut self, url: String) -> Option<Request<Nothing>> {
+        match Request::get(&url).body(Nothing) {
+            Ok(request) => Some(request),
+            Err(source) => {
+                self.report(ClientError::Request { url, source });
+                None
+            }
+        }
+    }
+
+    /// Logs `err` to the console, and shows it unless it is routine, like a
+    /// message dropped while reconnecting.
+    fn report(&mut self, err: ClientError) {
+        self.console_service.error(&err.to_string());
+
+        if !matches!(err, ClientError::NotConnected) {
+            self.show_toast(err.to_string());
+        }
+    }
+
+    /// Fetches the login providers and the image of the day history.
+    fn fetch_server_data(&mut self, config: &Config) {
+        if let Some(request) = self.get(server_url(&config.ws_url, "/api/auth")) {
+            self.fetch_task = Some(
+                self.fetch_service.fetch(
+                    request,
+                    self.link
+                        .callback(|response: Response<Json<Result<AuthProviders, Error>>>| {
+                            let (_, Json(auth)) = response.into_parts();
+                            Msg::AuthProvidersFetched(
+                                auth.map(|auth| auth.providers).unwrap_or_default(),
+                            )
+                        }),
+                ),
+            );
+        }
+
+        if let Some(request) = self.get(server_url(&config.ws_url, "/api/iotd/history")) {
+            self.iotd_fetch_task = Some(self.fetch_service.fetch(
+                request,
+                self.link.callback(
+                    |response: Response<Json<Result<Vec<ImageOfTheDay>, Error>>>| {
+                        let (_, Json(history)) = response.into_parts();
+                        Msg::IotdFetched(history.unwrap_or_default())
+                    },
+                ),
+            ));
+        }
+    }
+
+    /// Restarts the generator, and the mock server which draws from it,
+    /// from `seed`.
+    fn reseed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.generator = Generator::seeded(seed);
+
+        if self.mock.is_some() {
+            self.mock = Some(MockServer::new(self.generator.fork()));
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.ws_task.is_some() || self.timers.is_set(&Timer::Mock)
+    }
+
+    fn request_resync(&mut self, from_seq: u64) {
+        self.is_resyncing = true;
+        self.link.send_message(Msg::WsSend(WsMessage {
+            number: Some(from_seq),
+            ..WsMessage::new(WsMessageType::Resync)
+        }));
+    }
+
+    /// Sends a queued find with a fresh proof of work, until it gets acked or
+    /// rejected or runs out of attempts.
+    fn send_find(&mut self, client_msg_id: u64) {
+        if !self.is_connected() {
+            return;
+        }
+
+        let pending = match self.pending_finds.get_mut(&client_msg_id) {
+            Some(pending) if pending.attempts < MAX_SUBMIT_ATTEMPTS => pending,
+            _ => {
+                self.pending_finds.remove(&client_msg_id);
+                return;
+            }
+        };
+
+        pending.attempts += 1;
+        pending.sent_at = Date::now();
+
+        let id = pending.id.clone();
+
+        self.link.send_message(Msg::WsSend(find_message(
+            self.challenge.as_ref(),
+            id,
+            client_msg_id,
+            FindSource::Web,
+        )));
+    }
+
+    /// Resends the finds left unacked for at least `min_age_ms`.
+    fn resend_pending_finds(&mut self, min_age_ms: f64) {
+        let now = Date::now();
+        let stale = self
+            .pending_finds
+            .iter()
+            .filter(|(_, pending)| now - pending.sent_at >= min_age_ms)
+            .map(|(&client_msg_id, _)| client_msg_id)
+            .collect::<Vec<_>>();
+
+        for client_msg_id in stale {
+            self.send_find(client_msg_id);
+        }
+    }
+
+    fn spawn_rate_interval(&mut self) {
+        self.pacing.schedule_rate_limit(&mut WebSchedule {
+            link: &self.link,
+            timers: &mut self.timers,
+            interval_service: &mut self.interval_service,
+        });
+    }
+
+    fn set_rate_limit(&mut self, rate_limit: u64) {
+        self.pacing.set_rate_limit(
+            &mut WebSchedule {
+                link: &self.link,
+                timers: &mut self.timers,
+                interval_service: &mut self.interval_service,
+            },
+            rate_limit,
+        );
+    }
+
+    fn record_probe(&mut self, found: bool) {
+        let (session_secs, streak) = match &mut self.session {
+            Some(session) => {
+                session.record(found);
+                (session.elapsed_secs(Date::now()), session.streak())
+            }
+            None => (std::f64::INFINITY, 0),
+        };
+
+        let unlocked = self.achievements.record(found, session_secs, streak);
+
+        for achievement in &unlocked {
+            self.show_toast(format!(
+                "Achievement unlocked: {} ({})",
+                achievement.title(),
+                achievement.description()
+            ));
+        }
+
+        if found || !unlocked.is_empty() {
+            self.save_achievements();
+        }
+
+        if self.lifetime_stats.record_probe(found) {
+            self.save_lifetime_stats();
+        }
+    }
+
+    /// Requests image `id` from imgur with the probe options of the
+    /// settings, answering `found` or `not_found` with the response
+    /// metadata and the ID.
+    fn probe(
+        &mut self,
+        id: String,
+        found: fn((String, String)) -> Msg,
+        not_found: fn((String, String)) -> Msg,
+    ) -> Option<FetchTask> {
+        let url = if self.cache_busting {
+            format!("{}?{}", original_url(&id), self.generator.alphanumeric(8))
+        } else {
+            original_url(&id)
+        };
+
+        let request = self.get(url)?;
+
+        Some(self.fetch_service.fetch_binary_with_options(
+            request,
+            FetchOptions {
+                cache: if self.no_store {
+                    Some(Cache::NoStore)
+                } else {
+                    None
+                },
+                credentials: None,
+                redirect: Some(Redirect::Error),
+                mode: None,
+                referrer: None,
+                referrer_policy: match self.probe_referrer {
+                    ProbeReferrer::NoReferrer => Some(ReferrerPolicy::NoReferrer),
+                    ProbeReferrer::Origin => Some(ReferrerPolicy::Origin),
+                    ProbeReferrer::BrowserDefault => None,
+                },
+                integrity: None,
+            },
+            self.link.callback(move |response: Response<Nothing>| {
+                let (meta, _) = response.into_parts();
+
+                let message = format!("{:#?}", meta);
+
+                if meta.status.as_u16() == 429 {
+                    Msg::ProbeThrottled(id.clone())
+                } else if meta.status.as_u16() != 408 {
+                    found((message, id.clone()))
+                } else {
+                    not_found((message, id.clone()))
+                }
+            }),
+        ))
+    }
+
+    /// Sends an image that was found to the server, resending it until
+    /// acknowledged.
+    fn submit_find(&mut self, id: String) {
+        self.own_finds.insert(id.clone());
+
+        let client_msg_id = self.next_client_msg_id;
+        self.next_client_msg_id += 1;
+        self.pending_finds.insert(
+            client_msg_id,
+            PendingFind {
+                id,
+                attempts: 0,
+                sent_at: 0.0,
+            },
+        );
+        self.send_find(client_msg_id);
+    }
+
+    fn save_favorites(&mut self) {
+        self.storage
+            .store(favorites::STORAGE_KEY, Json(&self.favorites));
+    }
+
+    fn save_user_presets(&mut self) {
+        self.storage
+            .store(presets::STORAGE_KEY, Json(&self.user_presets));
+    }
+
+    fn save_achievements(&mut self) {
+        self.storage
+            .store(achievements::STORAGE_KEY, Json(&self.achievements));
+    }
+
+    /// Adds the session token to an API path with a query, for the endpoints
+    /// an operator may restrict to logged in users.
+    fn with_session(&self, path: String) -> String {
+        match &self.session_token {
+            Some(token) => format!("{}&session={}", path, token),
+            None => path,
+        }
+    }
+
+    fn fetch_random_images(&mut self, n: usize, is_fallback: bool) {
+        let url = self.config.as_ref().map(|config| {
+            server_url(
+                &config.ws_url,
+                &self.with_session(format!("/api/images/random?n={}", n)),
+            )
+        });
+
+        if let Some(request) = url.and_then(|url| self.get(url)) {
+            self.random_fetch_task = Some(self.fetch_service.fetch(
+                request,
+                self.link.callback(
+                    move |response: Response<Json<Result<Vec<ArchivedFind>, Error>>>| {
+                        let (_, Json(finds)) = response.into_parts();
+                        Msg::RandomFromArchiveLoaded(finds, is_fallback)
+                    },
+                ),
+            ));
+        }
+    }
+
+    /// Mixes a few archived finds into the wall once no new image arrived
+    /// for as long as the user allows.
+    fn check_archive_fallback(&mut self) {
+        if self.archive_fallback_secs == 0
+            || self.playback.is_some()
+            || self.random_fetch_task.is_some()
+        {
+            return;
+        }
+
+        let now = Date::now();
+
+        if now - self.last_new_at >= self.archive_fallback_secs as f64 * 1000.0 {
+            self.last_new_at = now;
+            self.fetch_random_images(FALLBACK_IMAGES, true);
+        }
+    }
+
+    /// Estimates how far the server clock is ahead of ours from a `Time`
+    /// round trip, assuming the reply took half of it. The sample with the
+    /// shortest round trip is the most accurate, so it is the one kept.
+    fn record_clock_sample(&mut self, sent_at: f64, server_ms: f64) {
+        let now = Date::now();
+        let rtt = now - sent_at;
+
+        if self.clock_sample_rtt.map_or(true, |best| rtt <= best) {
+            self.clock_offset = server_ms + rtt / 2.0 - now;
+            self.clock_sample_rtt = Some(rtt);
+        }
+    }
+
+    /// The current time on the server clock, in milliseconds since the unix
+    /// epoch.
+    fn server_now(&self) -> f64 {
+        Date::now() + self.clock_offset
+    }
+
+    /// Keeps the time between the server receiving the last finds and
+    /// their display, in milliseconds.
+    fn record_display_latency(&mut self, latency_ms: f64) {
+        self.display_latencies.push_back(latency_ms.max(0.0));
+
+        if self.display_latencies.len() > LATENCY_SAMPLES {
+            self.display_latencies.pop_front();
+        }
+    }
+
+    fn median_display_latency(&self) -> String {
+        let mut latencies = self.display_latencies.iter().copied().collect::<Vec<_>>();
+
+        if latencies.is_empty() {
+            return "-".to_owned();
+        }
+
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        format!("{:.0} ms", latencies[latencies.len() / 2])
+    }
+
+    /// Watchers by region, biggest first and `other` last, if the server
+    /// sends them.
+    fn view_region_counts(&self) -> Html {
+        if self.region_counts.is_empty() {
+            return html! {};
+        }
+
+        let mut region_counts = self.region_counts.iter().collect::<Vec<_>>();
+        region_counts
+            .sort_by_key(|&(region, &count)| (region == "other", std::cmp::Reverse(count)));
+
+        let breakdown = region_counts
+            .iter()
+            .map(|(region, count)| format!("{} {}", region, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        html! {
+            <tr>
+                <td>{ "Users watching by region" }</td>
+                <td colspan=2>{ breakdown }</td>
+            </tr>
+        }
+    }
+
+    /// Websocket traffic, both ways, per find broadcast since the page
+    /// loaded, which is about what watching costs.
+    fn protocol_overhead(&self) -> String {
+        if self.images_found == 0 {
+            "-".to_owned()
+        } else {
+            format_bytes((self.bandwidth.sent + self.bandwidth.received) / self.images_found)
+        }
+    }
+
+    /// How many images the wall holds, never more than `MAX_IMAGES` even
+    /// when the user asks for no limit.
+    fn wall_capacity(&self) -> usize {
+        if self.concurrent_loaded == 0 {
+            MAX_IMAGES
+        } else {
+            self.concurrent_loaded.min(MAX_IMAGES)
+        }
+    }
+
+    fn wall_len(&self) -> usize {
+        self.images.len() + self.own_images.len()
+    }
+
+    /// The pane images go to: this client's own in the split layout, the
+    /// wall otherwise.
+    fn pane_mut(&mut self, is_own: bool) -> &mut VecDeque<Image> {
+        if is_own && self.layout == WallLayout::Split {
+            &mut self.own_images
+        } else {
+            &mut self.images
+        }
+    }
+
+    fn image(&self, id: &str) -> Option<&Image> {
+        self.images
+            .iter()
+            .chain(&self.own_images)
+            .find(|image| image.id == id)
+    }
+
+    fn image_mut(&mut self, id: &str) -> Option<&mut Image> {
+        self.images
+            .iter_mut()
+            .chain(&mut self.own_images)
+            .find(|image| image.id == id)
+    }
+
+    /// Takes image `id` off the wall, with where it was in its pane.
+    fn remove_image(&mut self, id: &str) -> Option<(usize, Image)> {
+        for pane in [&mut self.images, &mut self.own_images].iter_mut() {
+            if let Some(position) = pane.iter().position(|image| image.id == id) {
+                return pane.remove(position).map(|image| (position, image));
+            }
+        }
+
+        None
+    }
+
+    /// Switches layouts, moving this client's finds between the panes.
+    fn set_layout(&mut self, layout: WallLayout) {
+        if layout == self.layout {
+            return;
+        }
+
+        self.layout = layout;
+
+        let (own, others) = mem::take(&mut self.images)
+            .into_iter()
+            .chain(mem::take(&mut self.own_images))
+            .partition::<VecDeque<_>, _>(|image| image.is_own && layout == WallLayout::Split);
+
+        self.images = others;
+        self.own_images = own;
+    }
+
+    /// Adds an image to the wall once its size is known.
+    fn show_image(
+        &mut self,
+        id: String,
+        from_archive: bool,
+        found_at: u64,
+        source: Option<FindSource>,
+        labels: Vec<String>,
+    ) {
+        push_evicting(
+            &mut self.session_finds,
+            (id.clone(), found_at),
+            MAX_SESSION_LINKS,
+            true,
+        );
+
+        let image = Image {
+            from_archive,
+            source,
+            labels,
+            is_own: !from_archive && self.own_finds.contains(&id),
+            ..Image::new(id)
+        };
+        self.prefetcher.push(image.id.clone(), Priority::High);
+        self.measuring.insert(image.id.clone(), image);
+        self.prefetch();
+    }
+
+    /// Starts loading queued images while fewer than the limit are loading.
+    /// Finds waiting to be inserted are measured, others are only cached.
+    fn prefetch(&mut self) {
+        let limit = if self.is_data_saver() {
+            DATA_SAVER_MAX_PREFETCHES
+        } else {
+            MAX_PREFETCHES
+        }
+        .min(self.wall_capacity());
+
+        while let Some(id) = self.prefetcher.next(limit) {
+            let src = match self.measuring.get(&id) {
+                Some(image) => self.image_src(image, self.is_data_saver()),
+                None => self.image_src(&Image::new(id.clone()), self.is_data_saver()),
+            };
+            let measured = self.link.callback(move |(width, height): (u32, u32)| {
+                Msg::ImageMeasured(id.clone(), width, height)
+            });
+
+            measure::measure(&src, move |width, height| measured.emit((width, height)));
+        }
+    }
+
+    /// Puts a measured image on the wall, making room for it if the wall is
+    /// full.
+    fn insert_image(&mut self, image: Image) {
+        let capacity = self.wall_capacity();
+        let show_from_top = self.show_from_top;
+        let pane = self.pane_mut(image.is_own);
+
+        self.images_evicted += push_evicting(pane, image, capacity, show_from_top);
+    }
+
+    fn save_lifetime_stats(&mut self) {
+        self.storage
+            .store(lifetime::STORAGE_KEY, Json(&self.lifetime_stats));
+    }
+
+    fn show_toast(&mut self, text: String) {
+        self.push_toast(text, false);
+    }
+
+    fn push_toast(&mut self, text: String, undo: bool) -> u32 {
+        let id = self.next_toast_id;
+        self.next_toast_id = self.next_toast_id.wrapping_add(1);
+
+        self.toasts.push(Toast { id, text, undo });
+        self.toast_timers.set(
+            id,
+            Box::new(self.timeout_service.spawn(
+                TOAST_DURATION,
+                self.link.callback(move |_| Msg::DismissToast(id)),
+            )),
+        );
+
+        id
+    }
+
+    /// Shows a toast offering to revert `action` while it is visible.
+    fn push_undo(&mut self, text: String, action: Undoable) {
+        let toast_id = self.push_toast(text, true);
+        self.undo_stack.push(toast_id, action);
+    }
+
+    fn undo(&mut self, action: Undoable) {
+        match action {
+            Undoable::Hide(position, image) => {
+                let pane = self.pane_mut(image.is_own);
+                let position = position.min(pane.len());
+                pane.insert(position, image);
+            }
+            Undoable::Unfavorite(position, id) => {
+                self.favorites.insert(position, id);
+                self.save_favorites();
+            }
+            Undoable::ClearWall(images) => {
+                let show_from_top = self.show_from_top;
+                let (own, others) = images
+                    .into_iter()
+                    .partition::<VecDeque<_>, _>(|image| image.is_own);
+
+                for (is_own, mut cleared) in vec![(false, others), (true, own)] {
+                    let pane = self.pane_mut(is_own);
+
+                    // Finds shown since the wall was cleared are newer than
+                    // every image it held.
+                    if show_from_top {
+                        cleared.append(pane);
+                        *pane = cleared;
+                    } else {
+                        pane.append(&mut cleared);
+                    }
+                }
+            }
+            Undoable::ClearStats(stats) => self.restore_session_stats(stats),
+            Undoable::Settings(settings) => {
+                self.apply_settings(settings);
+                self.settings_changed();
+            }
+        }
+    }
+
+    fn take_session_stats(&mut self) -> SessionStats {
+        // The requests made since the last rate report are dropped along
+        // with the total, which would otherwise fall below it.
+        self.reported_requests = 0;
+        self.requests_per_second = 0;
+        self.requests_per_second_current = 0;
+
+        SessionStats {
+            total_requests: mem::take(&mut self.total_requests),
+            images_found_self: mem::take(&mut self.images_found_self),
+            images_found: mem::take(&mut self.images_found),
+            images_failed: mem::take(&mut self.images_failed),
+            images_evicted: mem::take(&mut self.images_evicted),
+            probes_skipped: mem::take(&mut self.probes_skipped),
+            bandwidth: mem::take(&mut self.bandwidth),
+        }
+    }
+
+    /// Adds back statistics taken by `take_session_stats` to whatever was
+    /// counted since.
+    fn restore_session_stats(&mut self, stats: SessionStats) {
+        self.total_requests += stats.total_requests;
+        self.reported_requests += stats.total_requests;
+        self.images_found_self += stats.images_found_self;
+        self.images_found += stats.images_found;
+        self.images_failed += stats.images_failed;
+        self.images_evicted += stats.images_evicted;
+        self.probes_skipped += stats.probes_skipped;
+        self.bandwidth.sent += stats.bandwidth.sent;
+        self.bandwidth.received += stats.bandwidth.received;
+    }
+
+    fn view_milestone(&self) -> Html {
+        match &self.milestone {
+            Some(milestone) => html! {
+                <div id="milestone" onclick=self.link.callback(|_| Msg::DismissMilestone)>
+                    { "🎉 " }{ milestone }{ " 🎉" }
+                </div>
+            },
+            None => html! {},
+        }
+    }
+
+    /// The latest community events, scrolling by.
+    fn view_ticker(&self) -> Html {
+        if self.activity.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <div id="ticker">
+                <span>{ self.activity.iter().cloned().collect::<Vec<_>>().join(" · ") }</span>
+            </div>
+        }
+    }
+
+    fn view_profile(&self) -> Html {
+        html! {
+            <section id="profile">
+                <h2>{ "Contributor profile" }</h2>
+                { self.view_login() }
+                {
+                    match (&self.registered_name, &self.config) {
+                        (Some(name), Some(config)) => html! {
+                            <p>
+                                { "Your finds are credited to " }
+                                <a target="_blank" rel="noopener" href=server_url(&config.ws_url, &format!("/u/{}", name))>{ name }</a>
+                                { format!(" ({} so far).", self.registered_finds) }
+                            </p>
+                        },
+                        _ => html! {
+                            <p>{ "Optional: pick a name to get a public page listing the images you found." }</p>
+                        },
+                    }
+                }
+                <table>
+                    <tr>
+                        <td><label for="profile-name">{ "Name" }</label></td>
+                        <td><input id="profile-name" type="text" maxlength="20" value=&self.registration_name oninput=self.link.callback(|e: yew::events::InputData| Msg::RegistrationNameChanged(e.value)) /></td>
+                    </tr>
+                    <tr>
+                        <td><label for="profile-avatar">{ "Avatar URL (optional)" }</label></td>
+                        <td><input id="profile-avatar" type="url" value=&self.registration_avatar oninput=self.link.callback(|e: yew::events::InputData| Msg::RegistrationAvatarChanged(e.value)) /></td>
+                    </tr>
+                </table>
+                <button type="button" onclick=self.link.callback(|_| Msg::Register)>
+                    { if self.registered_name.is_some() { "Update profile" } else { "Register" } }
+                </button>
+            </section>
+        }
+    }
+
+    fn view_login(&self) -> Html {
+        match (&self.account_name, &self.config) {
+            (Some(name), _) => html! {
+                <p>
+                    { format!("Logged in as {}, your settings and profile follow you across devices. ", name) }
+                    <button type="button" onclick=self.link.callback(|_| Msg::Logout)>{ "Log out" }</button>
+                </p>
+            },
+            (None, Some(config)) if !self.auth_providers.is_empty() => html! {
+                <p>
+                    { "Log in to keep your settings and profile across devices: " }
+                    { for self.auth_providers.iter().map(|provider| html! {
+                        <a class="login" href=server_url(&config.ws_url, &format!("/auth/{}/login?return_to={}", provider, encode_uri_component(&self.page_url)))>
+                            { format!("Log in with {}", provider) }
+                        </a>
+                    }) }
+                </p>
+            },
+            _ => html! {},
+        }
+    }
+
+    fn view_iotd(&self) -> Html {
+        match self.iotd_history.get(self.iotd_index) {
+            Some(iotd) => html! {
+                <div id="iotd">
+                    <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href=format!("https://i.imgur.com/{}.png", iotd.id)>
+                        <img class="imgur-image" decoding="async" referrerpolicy="no-referrer" src=format!("https://i.imgur.com/{}m.jpg", iotd.id) />
+                    </a>
+                    <p>
+                        <label for="iotd-date">{ "Image of the day " }</label>
+                        <select id="iotd-date" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                            match event {
+                                                                ChangeData::Select(elem) => Msg::IotdSelected(elem.selected_index().unwrap_or(0) as usize),
+                                                                _ => Msg::NoOp,
+                                                            })>
+                            { for self.iotd_history.iter().enumerate().map(|(index, iotd)| html! {
+                                <option selected=index == self.iotd_index>{ &iotd.date }</option>
+                            }) }
+                        </select>
+                    </p>
+                </div>
+            },
+            None => html! {},
+        }
+    }
+
+    fn view_playback(&self) -> Html {
+        html! {
+            <section id="playback">
+                <h2>{ "Time-lapse" }</h2>
+                <p>
+                    <label for="playback-date">{ "Replay the finds of " }</label>
+                    <input id="playback-date" type="date" value=&self.playback_date oninput=self.link.callback(|e: yew::events::InputData| Msg::PlaybackDateChanged(e.value)) />
+                    <button type="button" onclick=self.link.callback(|_| Msg::PlaybackLoad)>{ "Load" }</button>
+                </p>
+                <p>
+                    <label for="playback-speed">{ "Speed " }</label>
+                    <select id="playback-speed" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                        match event {
+                                                            ChangeData::Select(elem) => {
+                                                                let index = elem.selected_index().unwrap_or(1) as usize;
+                                                                Msg::PlaybackSpeedSelected(PLAYBACK_SPEEDS[index.min(PLAYBACK_SPEEDS.len() - 1)].0)
+                                                            }
+                                                            _ => Msg::NoOp,
+                                                        })>
+                        { for PLAYBACK_SPEEDS.iter().map(|&(speed, label)| html! {
+                            <option selected=speed == self.playback_speed>{ label }</option>
+                        }) }
+                    </select>
+                </p>
+                {
+                    match &self.playback {
+                        Some(playback) => html! {
+                            <div>
+                                <input id="playback-scrubber" type="range" min=playback.archive.start max=playback.end() step="60" value=playback.position
+                                       oninput=self.link.callback(|e: yew::events::InputData| Msg::PlaybackSeek(e.value)) />
+                                <p>
+                                    <b>{ format!("{} UTC", playback.clock()) }</b>
+                                    { format!(" on {}, {} finds that day ", playback.archive.date, playback.archive.finds.len()) }
+                                    <button type="button" onclick=self.link.callback(|_| Msg::PlaybackToggle)>
+                                        { if playback.is_playing { "Pause" } else { "Play" } }
+                                    </button>
+                                    <button type="button" onclick=self.link.callback(|_| Msg::PlaybackExit)>{ "Back to live" }</button>
+                                </p>
+                            </div>
+                        },
+                        None => html! {},
+                    }
+                }
+            </section>
+        }
+    }
+
+    fn view_trophies(&self) -> Html {
+        html! {
+            <section id="trophies">
+                <h2>{ "Trophies" }</h2>
+                <table>
+                    { for Achievement::ALL.iter().map(|&achievement| html! {
+                        <tr class=if self.achievements.is_unlocked(achievement) { "trophy unlocked" } else { "trophy" }>
+                            <td><b>{ achievement.title() }</b></td>
+                            <td>{ achievement.description() }</td>
+                        </tr>
+                    }) }
+                </table>
+            </section>
+        }
+    }
+
+    fn image_src(&self, image: &Image, thumbnail: bool) -> String {
+        if self.mock.is_some() {
+            mock::placeholder_src(&image.id)
+        } else {
+            image.src(thumbnail)
+        }
+    }
+
+    fn is_data_saver(&self) -> bool {
+        match self.data_saver_mode {
+            DataSaverMode::Auto => self.save_data || self.battery_low,
+            DataSaverMode::On => true,
+            DataSaverMode::Off => false,
+        }
+    }
+
+    fn data_saver_changed(&mut self, was_data_saver: bool) -> ShouldRender {
+        if self.is_data_saver() != was_data_saver && self.state.is_bruteforcing() {
+            self.spawn_probe_interval();
+        }
+
+        true
+    }
+
+    /// Moves to the state `event` leads to, if any, and returns whether it
+    /// did.
+    fn transition(&mut self, event: Event) -> bool {
+        match self.state.next(event) {
+            Some(state) => {
+                self.state = state;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn spawn_probe_interval(&mut self) {
+        if self.quiet_secs_left.is_some() {
+            self.timers.cancel(&Timer::Probe);
+            self.transition(Event::Pause);
+            return;
+        }
+
+        self.transition(Event::Resume);
+
+        let is_data_saver = self.is_data_saver();
+
+        self.pacing.schedule_probes(
+            &mut WebSchedule {
+                link: &self.link,
+                timers: &mut self.timers,
+                interval_service: &mut self.interval_service,
+            },
+            is_data_saver,
+            self.swarm_backoff.is_some(),
+        );
+    }
+
+    fn is_bruteforce_enabled(&self) -> bool {
+        self.config
+            .as_ref()
+            .map_or(false, |config| config.bruteforce_enabled)
+            && !self.runtime_config.bruteforce_disabled
+    }
+
+    /// Seconds until bruteforcing resumes if the user's quiet hours, in local
+    /// time, or the operator's, in UTC, are in effect.
+    fn quiet_secs_left(&self) -> Option<u32> {
+        let local_secs: u32 = js!(
+            var now = new Date();
+            return now.getHours() * 3600 + now.getMinutes() * 60 + now.getSeconds();
+        )
+        .try_into()
+        .unwrap_or(0);
+        let utc_secs = ((self.server_now() / 1000.0) as u64 % 86_400) as u32;
+
+        let windows = [
+            (self.quiet_hours, local_secs),
+            (self.runtime_config.quiet_hours, utc_secs),
+        ];
+
+        windows
+            .iter()
+            .filter_map(|&(quiet_hours, secs)| {
+                quiet_hours
+                    .filter(|quiet_hours| quiet_hours.contains(secs))
+                    .map(|quiet_hours| quiet_hours.secs_until_change(secs))
+            })
+            .max()
+    }
+
+    /// Pauses the probes when quiet hours start and resumes them once they
+    /// are over.
+    fn update_quiet_hours(&mut self) {
+        let was_quiet = self.quiet_secs_left.is_some();
+        self.quiet_secs_left = self.quiet_secs_left();
+
+        if self.state.is_bruteforcing() && self.quiet_secs_left.is_some() != was_quiet {
+            self.spawn_probe_interval();
+        }
+    }
 
-                true
-            }
-            Msg::ResetRateLimit => {
-                self.is_rate_limited = false;
+    /// The lowest settings allowed by both the deployment and the operator.
+    fn minimums(&self) -> Minimums {
+        let config_min_ms = self
+            .config
+            .as_ref()
+            .map_or(0, |config| config.min_interval_ms);
 
-                false
-            }
-            _ => false,
+        Minimums {
+            interval_ms: config_min_ms.max(self.runtime_config.min_interval_ms),
+            rate_limit: self.runtime_config.min_rate_limit,
         }
     }
 
-    fn view(&self) -> Html {
+    fn view_basic_settings(&self) -> Html {
+        let built_in = presets::built_in();
+
         html! {
-            <body>
-                <header>
-                    <h1>{ "Random Imgur Wall" }</h1>
-                </header>
-                <main>
-                    <div id="container">
-                        <section id="info">
-                            <h2>{ "NSFL Warning" }</h2>
-                            <p>{ "Images show up randomly and you may see terrible things staying on this site, watch with care." }</p>
-                            <p>
-                                <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href="https://help.imgur.com/hc/en-us/articles/208582296-Reporting-Content">
-                                    { "Report abusive content" }
-                                </a>
-                            </p>
-                            <p>
-                                <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href={ "https://github.com/leo-lb/random-imgur-wall" }>
-                                    { "Source code" }
-                                </a>
-                            </p>
-                            <p>
-                                { "Thanks to " }
-                                <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href={ "https://www.reddit.com/user/quickscoperdoge" }>
-                                    { "u/quickscoperdoge" }
-                                </a>
-                                { " for the revamped design!" }
-                            </p>
-                        </section>
-                        <section id="settings">
-                            <h2>{ "Settings" }</h2>
-                            <table>
-                                <tr>
-                                    <td><label for="interval">{ "Interval at which bruteforce requests are sent (in ms)" }</label><b>{" Want to see images faster? Decrease this and press Start."}</b></td>
-                                    <td><input id="interval" type="number" value=self.interval.as_millis() oninput=self.link.callback(|e: yew::events::InputData| Msg::IntervalChanged(e.value)) /></td> // <!-- modify this -->
-                                </tr>
-                                <tr>
-                                    <td><label for="images">{ "Number of images to keep loaded at a time (0 for unlimited)" }</label></td>
-                                    <td><input id="images" type="number" value=self.concurrent_loaded oninput=self.link.callback(|e: yew::events::InputData| Msg::LoadedChanged(e.value)) /></td> // <!-- modify this -->
-                                </tr>
-                                <tr>
-                                    <td><label for="mode">{ "Show mode" }</label></td>
-                                    <td><select id="mode" name="Show mode" onchange=self.link.callback(|event: yew::events::ChangeData|
-                                                                                match event {
-                                                                                    ChangeData::Select(elem) => {
-                                                                                        if let Some(idx) = elem.selected_index() {
-                                                                                            Msg::ShowModeSelected(idx != 1)
-                                                                                        } else {
-                                                                                            unreachable!();
-                                                                                        }
-                                                                                    }
-                                                                                    _ => {
-                                                                                        unreachable!();
-                                                                                    }
-                                                                                })> // <!-- modify this -->
-                                        <option>{ "Show from bottom" }</option>
-                                        <option>{ "Show from top" }</option>
-                                    </select></td>
-                                </tr>
-                                <tr>
-                                    <td><label for="delay">{ "Delay to wait before a new image shows up (in seconds, 0 for none)" }</label><b>{" Want to see images faster? Decrease or set this to 0."}</b></td>
-                                    <td><input id="delay" type="number" value=self.rate_limit oninput=self.link.callback(|e: yew::events::InputData| Msg::RateLimitChanged(e.value)) /></td> //<!-- modify this -->
-                                </tr>
-                            </table>
-                            <p style="overflow: auto;">
-                                <button type="button" style="margin: auto; width: 50%;" onclick=self.link.callback(|_| Msg::Start)>{ "Start" }</button> //<!-- modify this -->
-                                <button type="button" style="margin: auto; width: 50%;" onclick=self.link.callback(|_| Msg::Stop)>{ "Stop" }</button> //<!-- modify this -->
-                            </p>
-                        </section>
+            <table>
+                <tr>
+                    <td>{ "Strict mode: blurred thumbnails showing up slowly, and no bruteforcing" }</td>
+                    <td><button type="button" onclick=self.link.callback(|_| Msg::ApplyPreset(presets::STRICT.to_owned()))>{ "Apply" }</button></td>
+                </tr>
+                <tr>
+                    <td><label for="preset">{ "Presets" }</label></td>
+                    <td><select id="preset" name="Presets" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                match event {
+                                                                    ChangeData::Select(elem) => match elem.value() {
+                                                                        Some(name) if !name.is_empty() => Msg::ApplyPreset(name),
+                                                                        _ => Msg::NoOp,
+                                                                    },
+                                                                    _ => {
+                                                                        unreachable!();
+                                                                    }
+                                                                })>
+                        <option value="" selected=true>{ "Choose one to apply" }</option>
+                        { for built_in.iter().chain(self.user_presets.iter()).map(|preset| html! {
+                            <option value=&preset.name>{ &preset.name }</option>
+                        }) }
+                    </select></td>
+                </tr>
+                <tr>
+                    <td><label for="preset-name">{ "Save the current settings as a preset, or delete one" }</label></td>
+                    <td>
+                        <input id="preset-name" type="text" placeholder="Name" value=&self.preset_name oninput=self.link.callback(|e: yew::events::InputData| Msg::PresetNameChanged(e.value)) />
+                        <button type="button" onclick=self.link.callback(|_| Msg::SavePreset)>{ "Save" }</button>
+                        <button type="button" onclick=self.link.callback(|_| Msg::DeletePreset)>{ "Delete" }</button>
+                    </td>
+                </tr>
+                <tr>
+                    <td><label for="preset-import">{ "Share your saved presets as JSON" }</label></td>
+                    <td>
+                        <button type="button" onclick=self.link.callback(|_| Msg::ExportPresets)>{ "Export" }</button>
+                        <textarea id="preset-import" placeholder="Paste exported presets" value=&self.preset_import oninput=self.link.callback(|e: yew::events::InputData| Msg::PresetImportChanged(e.value))></textarea>
+                        <button type="button" onclick=self.link.callback(|_| Msg::ImportPresets)>{ "Import" }</button>
+                    </td>
+                </tr>
+                <tr>
+                    <td><label for="blur-images">{ "Blur images until clicked" }</label></td>
+                    <td><input id="blur-images" type="checkbox" checked=self.blur_images onclick=self.link.callback(|_| Msg::BlurImagesToggled) /></td>
+                </tr>
+                <tr>
+                    <td><label for="text-only">{ "Text only: list links instead of loading images" }</label></td>
+                    <td><input id="text-only" type="checkbox" checked=self.text_only onclick=self.link.callback(|_| Msg::TextOnlyToggled) /></td>
+                </tr>
+                <tr>
+                    <td><label for="watch-only">{ "Only start bruteforcing with the Start button" }</label></td>
+                    <td><input id="watch-only" type="checkbox" checked=self.watch_only onclick=self.link.callback(|_| Msg::WatchOnlyToggled) /></td>
+                </tr>
+                <tr>
+                    <td><label for="interval">{ "Interval at which bruteforce requests are sent (in ms)" }</label><b>{" Want to see images faster? Decrease this and press Start."}</b></td>
+                    <td>
+                        <input id="interval" type="number" min=self.pacing.min_interval().as_millis() disabled=!self.is_bruteforce_enabled() value=self.pacing.interval().as_millis() oninput=self.link.callback(|e: yew::events::InputData| Msg::IntervalChanged(e.value)) /> // <!-- modify this -->
+                        { self.view_locked_hint(self.pacing.min_interval() > Duration::from_millis(0), &format!("Server minimum: {} ms", self.pacing.min_interval().as_millis())) }
+                        { self.view_interval_clamped() }
+                        { self.view_settings_error("interval") }
+                    </td>
+                </tr>
+                <tr>
+                    <td><label for="images">{ "Number of images to keep loaded at a time (0 for the maximum of 1000)" }</label></td>
+                    <td>
+                        <input id="images" type="number" value=self.concurrent_loaded oninput=self.link.callback(|e: yew::events::InputData| Msg::LoadedChanged(e.value)) /> // <!-- modify this -->
+                        { self.view_settings_error("images") }
+                    </td>
+                </tr>
+                <tr>
+                    <td><label for="mode">{ "Show mode" }</label></td>
+                    <td><select id="mode" name="Show mode" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                match event {
+                                                                    ChangeData::Select(elem) => {
+                                                                        if let Some(idx) = elem.selected_index() {
+                                                                            Msg::ShowModeSelected(idx != 1)
+                                                                        } else {
+                                                                            unreachable!();
+                                                                        }
+                                                                    }
+                                                                    _ => {
+                                                                        unreachable!();
+                                                                    }
+                                                                })> // <!-- modify this -->
+                        <option selected=!self.show_from_top>{ "Show from bottom" }</option>
+                        <option selected=self.show_from_top>{ "Show from top" }</option>
+                    </select></td>
+                </tr>
+                <tr>
+                    <td><label for="tile-size">{ "Image size" }</label></td>
+                    <td><select id="tile-size" name="Image size" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                match event {
+                                                                    ChangeData::Select(elem) => {
+                                                                        match elem.selected_index() {
+                                                                            Some(0) => Msg::TileSizeSelected(TileSize::Small),
+                                                                            Some(2) => Msg::TileSizeSelected(TileSize::Large),
+                                                                            _ => Msg::TileSizeSelected(TileSize::Medium),
+                                                                        }
+                                                                    }
+                                                                    _ => {
+                                                                        unreachable!();
+                                                                    }
+                                                                })>
+                        <option selected=self.tile_size == TileSize::Small>{ "Small" }</option>
+                        <option selected=self.tile_size == TileSize::Medium>{ "Medium" }</option>
+                        <option selected=self.tile_size == TileSize::Large>{ "Large" }</option>
+                    </select></td>
+                </tr>
+                <tr>
+                    <td><label for="image-click">{ "Clicking an image" }</label></td>
+                    <td><select id="image-click" name="Clicking an image" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                match event {
+                                                                    ChangeData::Select(elem) => {
+                                                                        match elem.selected_index() {
+                                                                            Some(1) => Msg::ImageClickSelected(ImageClick::Lightbox),
+                                                                            Some(2) => Msg::ImageClickSelected(ImageClick::File),
+                                                                            Some(3) => Msg::ImageClickSelected(ImageClick::ImgurPage),
+                                                                            Some(4) => Msg::ImageClickSelected(ImageClick::CopyLink),
+                                                                            _ => Msg::ImageClickSelected(ImageClick::Auto),
+                                                                        }
+                                                                    }
+                                                                    _ => {
+                                                                        unreachable!();
+                                                                    }
+                                                                })>
+                        <option selected=self.image_click == ImageClick::Auto>{ "Automatic (enlarges it on mobile, opens the file elsewhere)" }</option>
+                        <option selected=self.image_click == ImageClick::Lightbox>{ "Enlarges it" }</option>
+                        <option selected=self.image_click == ImageClick::File>{ "Opens the image file" }</option>
+                        <option selected=self.image_click == ImageClick::ImgurPage>{ "Opens its imgur page" }</option>
+                        <option selected=self.image_click == ImageClick::CopyLink>{ "Copies its link" }</option>
+                    </select></td>
+                </tr>
+                <tr>
+                    <td><label for="layout">{ "Wall layout" }</label></td>
+                    <td><select id="layout" name="Wall layout" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                match event {
+                                                                    ChangeData::Select(elem) => {
+                                                                        match elem.selected_index() {
+                                                                            Some(1) => Msg::LayoutSelected(WallLayout::Split),
+                                                                            _ => Msg::LayoutSelected(WallLayout::Single),
+                                                                        }
+                                                                    }
+                                                                    _ => {
+                                                                        unreachable!();
+                                                                    }
+                                                                })>
+                        <option selected=self.layout == WallLayout::Single>{ "Every find on one wall" }</option>
+                        <option selected=self.layout == WallLayout::Split>{ "Your finds and the community's side by side" }</option>
+                    </select></td>
+                </tr>
+                <tr>
+                    <td><label for="columns">{ "Number of columns (0 for automatic)" }</label></td>
+                    <td>
+                        <input id="columns" type="number" min="0" value=self.columns oninput=self.link.callback(|e: yew::events::InputData| Msg::ColumnsChanged(e.value)) />
+                        { self.view_settings_error("columns") }
+                    </td>
+                </tr>
+                <tr>
+                    <td><label for="delay">{ "Delay to wait before a new image shows up (in seconds, 0 for none)" }</label><b>{" Want to see images faster? Decrease or set this to 0."}</b></td>
+                    <td>
+                        <input id="delay" type="number" min=self.runtime_config.min_rate_limit value=self.pacing.rate_limit() oninput=self.link.callback(|e: yew::events::InputData| Msg::RateLimitChanged(e.value)) /> //<!-- modify this -->
+                        { self.view_locked_hint(self.runtime_config.min_rate_limit != 0, &format!("Server minimum: {} s", self.runtime_config.min_rate_limit)) }
+                        { self.view_settings_error("delay") }
+                    </td>
+                </tr>
+            </table>
+        }
+    }
 
-                        <section id="stats">
-                            <h2>{ "Statistics" }</h2>
-                            <table>
-                                <tr>
-                                    <td>{ "Total number of requests" }</td>
-                                    <td>{ self.total_requests }</td>
-                                </tr>
-                                <tr>
-                                    <td>{ "Requests completed per second" }</td>
-                                    <td>{ self.requests_per_second }</td>
-                                </tr>
-                                <tr>
-                                    <td>{ "Images you found" }</td>
-                                    <td>{ self.images_found_self }</td>
-                                </tr>
-                                <tr>
-                                    <td>{ "Images everyone found" }</td>
-                                    <td>{ self.images_found }</td>
-                                </tr>
-                                <tr>
-                                    <td>{ "Users watching" }</td>
-                                    <td>{ self.users_watching }</td>
-                                </tr>
+    fn view_advanced_settings(&self) -> Html {
+        html! {
+            <>
+                <table>
+                    <tr>
+                        <td><label for="quiet-start">{ "Quiet hours, when bruteforcing pauses (local time)" }</label></td>
+                        <td>
+                            <input id="quiet-start" type="time" value=self.quiet_hours.map_or(String::new(), |quiet_hours| format_time(quiet_hours.start)) oninput=self.link.callback(|e: yew::events::InputData| Msg::QuietStartChanged(e.value)) />
+                            { " to " }
+                            <input id="quiet-end" type="time" value=self.quiet_hours.map_or(String::new(), |quiet_hours| format_time(quiet_hours.end)) oninput=self.link.callback(|e: yew::events::InputData| Msg::QuietEndChanged(e.value)) />
+                            { self.view_locked_hint(self.runtime_config.quiet_hours.is_some(), &self.runtime_config.quiet_hours.map_or(String::new(), |quiet_hours| format!("Server quiet hours: {} to {} UTC", format_time(quiet_hours.start), format_time(quiet_hours.end)))) }
+                            { self.view_settings_error("quiet-hours") }
+                        </td>
+                    </tr>
+                    <tr>
+                        <td><label for="archive-fallback">{ "Mix in images from the archive after this many seconds without a new find (0 to disable)" }</label></td>
+                        <td>
+                            <input id="archive-fallback" type="number" min="0" value=self.archive_fallback_secs oninput=self.link.callback(|e: yew::events::InputData| Msg::ArchiveFallbackChanged(e.value)) />
+                            { self.view_settings_error("archive-fallback") }
+                        </td>
+                    </tr>
+                    <tr>
+                        <td>
+                            <label for="data-saver">{ "Data saver (thumbnails only, slower requests)" }</label>
+                            <b>{ if self.is_data_saver() { " Currently on." } else { " Currently off." } }</b>
+                        </td>
+                        <td><select id="data-saver" name="Data saver" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                    match event {
+                                                                        ChangeData::Select(elem) => {
+                                                                            match elem.selected_index() {
+                                                                                Some(1) => Msg::DataSaverModeSelected(DataSaverMode::On),
+                                                                                Some(2) => Msg::DataSaverModeSelected(DataSaverMode::Off),
+                                                                                _ => Msg::DataSaverModeSelected(DataSaverMode::Auto),
+                                                                            }
+                                                                        }
+                                                                        _ => {
+                                                                            unreachable!();
+                                                                        }
+                                                                    })>
+                            <option selected=self.data_saver_mode == DataSaverMode::Auto>{ "Automatic (Save-Data or low battery)" }</option>
+                            <option selected=self.data_saver_mode == DataSaverMode::On>{ "Always on" }</option>
+                            <option selected=self.data_saver_mode == DataSaverMode::Off>{ "Always off" }</option>
+                        </select></td>
+                    </tr>
+                </table>
+                <p>{ "Only change these if requests come back wrong, for instance stale answers from a proxy or cache between you and imgur." }</p>
+                <table>
+                    <tr>
+                        <td><label for="cache-busting">{ "Add a random parameter to each request so no cache can answer it" }</label></td>
+                        <td><input id="cache-busting" type="checkbox" checked=self.cache_busting onclick=self.link.callback(|_| Msg::CacheBustingToggled) /></td>
+                    </tr>
+                    <tr>
+                        <td><label for="no-store">{ "Ask the browser not to cache requests" }</label></td>
+                        <td><input id="no-store" type="checkbox" checked=self.no_store onclick=self.link.callback(|_| Msg::NoStoreToggled) /></td>
+                    </tr>
+                    <tr>
+                        <td><label for="probe-referrer">{ "Referrer sent with requests" }</label></td>
+                        <td><select id="probe-referrer" name="Referrer" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                                    match event {
+                                                                        ChangeData::Select(elem) => {
+                                                                            match elem.selected_index() {
+                                                                                Some(1) => Msg::ProbeReferrerSelected(ProbeReferrer::Origin),
+                                                                                Some(2) => Msg::ProbeReferrerSelected(ProbeReferrer::BrowserDefault),
+                                                                                _ => Msg::ProbeReferrerSelected(ProbeReferrer::NoReferrer),
+                                                                            }
+                                                                        }
+                                                                        _ => {
+                                                                            unreachable!();
+                                                                        }
+                                                                    })>
+                            <option selected=self.probe_referrer == ProbeReferrer::NoReferrer>{ "None" }</option>
+                            <option selected=self.probe_referrer == ProbeReferrer::Origin>{ "This site's address" }</option>
+                            <option selected=self.probe_referrer == ProbeReferrer::BrowserDefault>{ "Browser default" }</option>
+                        </select></td>
+                    </tr>
+                    {
+                        match (&self.config, self.ws_url_from_setup) {
+                            (Some(config), true) => html! {
                                 <tr>
-                                    <td>{ "Users bruteforcing" }</td>
-                                    <td>{ self.users_bruteforcing }</td>
+                                    <td>{ "Server, set in this browser" }</td>
+                                    <td>
+                                        { &config.ws_url }{ " " }
+                                        <button type="button" onclick=self.link.callback(|_| Msg::ForgetWsUrl)>{ "Change" }</button>
+                                    </td>
                                 </tr>
-                            </table>
-                        </section>
-                    </div>
-                    <section id="images">
-                        <h2 style="text-align: center;">{ "Images" }</h2>
-                        <div id="gallery">
-                            {
-                                for self.images.iter().map(|image| html! {
-                                    <a class="imgur-image-container" target="_blank" rel="noopener" referrerpolicy="no-referrer" href=format!("https://i.imgur.com/{}.png", image)>
-                                        <img class="imgur-image" decoding="async" referrerpolicy="no-referrer" src=format!("https://i.imgur.com/{}.png", image) />
-                                    </a>
-                                })
-                            }
-                        </div>
+                            },
+                            _ => html! {},
+                        }
+                    }
+                </table>
+                <p>
+                    <button type="button" onclick=self.link.callback(|_| Msg::ResetSettings)>{ "Reset all settings" }</button>
+                </p>
+            </>
+        }
+    }
+
+    fn settings_tab_class(&self, tab: SettingsTab) -> &'static str {
+        if self.settings_tab == tab {
+            "selected"
+        } else {
+            ""
+        }
+    }
+
+    /// Remembers why the value typed into `field` was refused, or forgets
+    /// it once a valid one replaces it.
+    fn check_setting<T>(&mut self, field: &'static str, result: Result<T, String>) -> Option<T> {
+        self.settings_errors.retain(|error| error.field != field);
+
+        match result {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.settings_errors.push(SettingsError { field, message });
+                None
+            }
+        }
+    }
+
+    fn view_settings_error(&self, field: &str) -> Html {
+        match self
+            .settings_errors
+            .iter()
+            .find(|error| error.field == field)
+        {
+            Some(error) => html! { <div class="settings-error">{ &error.message }</div> },
+            None => html! {},
+        }
+    }
+
+    fn view_watch_only(&self) -> Html {
+        if self.config.is_none() || self.is_bruteforce_enabled() {
+            return html! {};
+        }
+
+        let reason = match &self.runtime_config.bruteforce_disabled_reason {
+            Some(reason) if self.runtime_config.bruteforce_disabled => reason.as_str(),
+            _ if self.runtime_config.bruteforce_disabled => {
+                "Bruteforcing is paused by the operator of this wall."
+            }
+            _ => "This wall is watch-only.",
+        };
+
+        html! {
+            <p id="watch-only">
+                <b>{ reason }</b>
+                { " You can still watch the images others find, browse the image of the day and replay past days." }
+            </p>
+        }
+    }
+
+    fn view_swarm_backoff(&self) -> Html {
+        match &self.swarm_backoff {
+            Some(reason) => html! {
+                <p id="swarm-backoff">
+                    <b>{ reason }</b>
+                    { format!(" Everyone probes {} times slower for a few minutes, fewer new images will show up.", pacing::SWARM_BACKOFF_SLOWDOWN) }
+                </p>
+            },
+            None => html! {},
+        }
+    }
+
+    fn view_quiet_hours(&self) -> Html {
+        match self.quiet_secs_left {
+            Some(secs_left) if self.state.is_bruteforcing() => html! {
+                <p id="quiet-hours">
+                    <b>{ "Quiet hours: " }</b>
+                    { format!("bruteforcing resumes in {}.", format_duration(secs_left as u64)) }
+                </p>
+            },
+            _ => html! {},
+        }
+    }
+
+    fn view_interval_clamped(&self) -> Html {
+        if self.pacing.is_interval_clamped() {
+            html! {
+                <div class="clamped">
+                    { format!("Your interval was raised to the minimum of {} ms allowed on this wall.", self.pacing.min_interval().as_millis()) }
+                </div>
+            }
+        } else {
+            html! {}
+        }
+    }
+
+    fn view_locked_hint(&self, is_locked: bool, hint: &str) -> Html {
+        if is_locked {
+            html! { <div class="locked">{ hint }</div> }
+        } else {
+            html! {}
+        }
+    }
+
+    fn view_config_error(&self) -> Html {
+        match &self.config_error {
+            Some(err) => html! {
+                <div id="config-error">
+                    <b>{ "This wall is misconfigured and cannot start. " }</b>
+                    { err }
+                    { self.view_setup() }
+                </div>
+            },
+            None => html! {},
+        }
+    }
+
+    fn view_setup(&self) -> Html {
+        match &self.setup_ws_url {
+            Some(ws_url) => html! {
+                <form id="setup" onsubmit=self.link.callback(|e: SubmitEvent| {
+                    e.prevent_default();
+                    Msg::SetupSubmitted
+                })>
+                    <label for="setup-ws-url">{ "Or type in the address of the server to use from this browser: " }</label>
+                    <input id="setup-ws-url" type="text" placeholder="wss://example.org/ws" value=ws_url
+                           oninput=self.link.callback(|e: yew::events::InputData| Msg::SetupWsUrlChanged(e.value)) />
+                    <button type="submit">{ "Connect" }</button>
+                    { match &self.setup_error {
+                        Some(error) => html! { <div class="settings-error">{ error }</div> },
+                        None => html! {},
+                    } }
+                </form>
+            },
+            None => html! {},
+        }
+    }
+
+    fn is_mobile(&self) -> bool {
+        self.viewport_width <= MOBILE_MAX_WIDTH
+    }
+
+    fn settings_class(&self) -> &'static str {
+        match (self.is_mobile(), self.settings_open) {
+            (false, _) => "",
+            (true, false) => "bottom-sheet",
+            (true, true) => "bottom-sheet open",
+        }
+    }
+
+    /// Moves through the pane of the image in the lightbox.
+    fn move_lightbox(&mut self, step: isize) -> ShouldRender {
+        let pane = match &self.lightbox {
+            Some(id) if self.own_images.iter().any(|image| &image.id == id) => &self.own_images,
+            _ => &self.images,
+        };
+        let position = match &self.lightbox {
+            Some(id) => pane.iter().position(|image| &image.id == id),
+            None => None,
+        };
+
+        if let Some(position) = position {
+            let next = position as isize + step;
+
+            if next >= 0 && (next as usize) < pane.len() {
+                self.lightbox = Some(pane[next as usize].id.clone());
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn view_image(&self, image: &Image) -> Html {
+        if self.text_only {
+            return self.view_link(image);
+        }
+
+        let id = image.id.clone();
+        let click_id = image.id.clone();
+        let menu_id = image.id.clone();
+        let image_click = match self.image_click {
+            ImageClick::Auto if self.is_mobile() => ImageClick::Lightbox,
+            ImageClick::Auto => ImageClick::File,
+            image_click => image_click,
+        };
+        let href = match image_click {
+            ImageClick::ImgurPage => page_url(&image.id),
+            _ => original_url(&image.id),
+        };
+        let is_blurred = self.blur_images && !image.revealed;
+        let mut class = "imgur-image-container".to_owned();
+
+        if image.from_archive {
+            class.push_str(" from-archive");
+        }
+
+        if is_blurred {
+            class.push_str(" blurred");
+        }
+
+        html! {
+            <a class=class style=self.tile_style() target="_blank" rel="noopener" referrerpolicy="no-referrer" href=href
+               onclick=self.link.callback(move |e: ClickEvent| match image_click {
+                   _ if is_blurred => {
+                       e.prevent_default();
+                       Msg::RevealImage(click_id.clone())
+                   }
+                   ImageClick::Lightbox => {
+                       e.prevent_default();
+                       Msg::OpenLightbox(click_id.clone())
+                   }
+                   ImageClick::CopyLink => {
+                       e.prevent_default();
+                       Msg::CopyImageLink(click_id.clone())
+                   }
+                   _ => Msg::NoOp,
+               })
+               oncontextmenu=self.link.callback(move |e: ContextMenuEvent| {
+                   e.prevent_default();
+                   Msg::OpenContextMenu(menu_id.clone(), e.client_x(), e.client_y())
+               })>
+                <img class="imgur-image" loading="lazy" decoding="async" referrerpolicy="no-referrer" style=aspect_ratio_style(image) src=self.image_src(image, self.is_data_saver()) onerror=self.link.callback(move |_| Msg::ImageFailed(id.clone())) />
+                { if image.from_archive { html! { <span class="archive-badge">{ "from archive" }</span> } } else { html! {} } }
+                { if self.favorites.contains(&image.id) { html! { <span class="favorite-badge">{ "★" }</span> } } else { html! {} } }
+                { match image.source { Some(source) => html! { <span class="source-badge">{ source.label() }</span> }, None => html! {} } }
+                { if image.labels.is_empty() { html! {} } else { html! { <span class="label-badge">{ image.labels.join(", ") }</span> } } }
+            </a>
+        }
+    }
+
+    /// Downloads of this session's finds for download tools.
+    fn view_export(&self) -> Html {
+        if self.session_finds.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <p style="text-align: center;">
+                { "Download this session's finds as " }
+                <button type="button" onclick=self.link.callback(|_| Msg::ExportFinds(ExportFormat::UrlList))>{ "a list of links" }</button>
+                <button type="button" onclick=self.link.callback(|_| Msg::ExportFinds(ExportFormat::GalleryDl))>{ "a gallery-dl input file" }</button>
+                <button type="button" onclick=self.link.callback(|_| Msg::ExportFinds(ExportFormat::Script))>{ "a shell script" }</button>
+            </p>
+        }
+    }
+
+    /// Whose finds to show, and checkboxes showing or hiding the finds of
+    /// each kind of client.
+    fn view_feed_controls(&self) -> Html {
+        html! {
+            <p id="feed-controls" style="text-align: center;">
+                <label for="feed-filter">{ "Show " }</label>
+                <select id="feed-filter" onchange=self.link.callback(|event: yew::events::ChangeData|
+                                                    match event {
+                                                        ChangeData::Select(elem) => {
+                                                            match elem.selected_index() {
+                                                                Some(1) => Msg::FeedFilterSelected(FeedFilter::Mine),
+                                                                Some(2) => Msg::FeedFilterSelected(FeedFilter::Others),
+                                                                _ => Msg::FeedFilterSelected(FeedFilter::All),
+                                                            }
+                                                        }
+                                                        _ => {
+                                                            unreachable!();
+                                                        }
+                                                    })>
+                    <option selected=self.feed_filter == FeedFilter::All>{ "everyone's finds" }</option>
+                    <option selected=self.feed_filter == FeedFilter::Mine>{ "only my finds" }</option>
+                    <option selected=self.feed_filter == FeedFilter::Others>{ "only others' finds" }</option>
+                </select>
+                { " from " }
+                { for FindSource::ALL.iter().map(|&source| html! {
+                    <label>
+                        <input type="checkbox" checked=!self.hidden_sources.contains(&source) onclick=self.link.callback(move |_| Msg::SourceToggled(source)) />
+                        { source.label() }
+                    </label>
+                }) }
+            </p>
+        }
+    }
+
+    /// Whether `image` passes the source filter. Finds from clients that do
+    /// not tell their source are always shown.
+    fn is_shown(&self, image: &Image) -> bool {
+        image
+            .source
+            .map_or(true, |source| !self.hidden_sources.contains(&source))
+    }
+
+    fn view_gallery(&self, id: &str, images: &VecDeque<Image>) -> Html {
+        html! {
+            <div id=id class=self.gallery_class()>
+                { for images.iter().filter(|image| self.is_shown(image)).map(|image| self.view_image(image)) }
+            </div>
+        }
+    }
+
+    fn gallery_class(&self) -> &'static str {
+        if self.text_only {
+            "text-only"
+        } else {
+            self.tile_size.class()
+        }
+    }
+
+    /// An image as its link, for text only mode, which loads no image.
+    fn view_link(&self, image: &Image) -> Html {
+        let menu_id = image.id.clone();
+        let url = original_url(&image.id);
+
+        html! {
+            <a class="image-link" target="_blank" rel="noopener" referrerpolicy="no-referrer" href=&url
+               oncontextmenu=self.link.callback(move |e: ContextMenuEvent| {
+                   e.prevent_default();
+                   Msg::OpenContextMenu(menu_id.clone(), e.client_x(), e.client_y())
+               })>
+                { &url }
+            </a>
+        }
+    }
+
+    fn view_session_summary(&self) -> Html {
+        match &self.session_summary {
+            Some(summary) => html! {
+                <div class="modal">
+                    <section class="modal-content">
+                        <h2>{ "Session summary" }</h2>
+                        <table>
+                            <tr>
+                                <td>{ "Duration" }</td>
+                                <td>{ format_duration(summary.duration_secs) }</td>
+                            </tr>
+                            <tr>
+                                <td>{ "Requests sent" }</td>
+                                <td>{ summary.attempts }</td>
+                            </tr>
+                            <tr>
+                                <td>{ "Images found" }</td>
+                                <td>{ summary.finds }</td>
+                            </tr>
+                            <tr>
+                                <td>{ "Hit rate" }</td>
+                                <td>{ format!("{:.3}%", summary.hit_rate * 100.0) }</td>
+                            </tr>
+                            <tr>
+                                <td>{ "Estimated bandwidth used" }</td>
+                                <td>{ format_bytes(summary.bandwidth_bytes) }</td>
+                            </tr>
+                            <tr>
+                                <td>{ "Best streak of consecutive finds" }</td>
+                                <td>{ summary.best_streak }</td>
+                            </tr>
+                        </table>
+                        <p>
+                            <button type="button" onclick=self.link.callback(|_| Msg::ExportSessionSummary)>{ "Export" }</button>
+                            <button type="button" onclick=self.link.callback(|_| Msg::ShareSessionSummary)>{ "Share" }</button>
+                            <button type="button" onclick=self.link.callback(|_| Msg::CloseSessionSummary)>{ "Close" }</button>
+                        </p>
                     </section>
-                </main>
-                <footer>
-                </footer>
-            </body>
+                </div>
+            },
+            None => html! {},
+        }
+    }
+
+    fn view_manual_submission(&self) -> Html {
+        if !self.is_bruteforce_enabled() {
+            return html! {};
+        }
+
+        html! {
+            <form id="manual-submission" onsubmit=self.link.callback(|e: SubmitEvent| {
+                e.prevent_default();
+                Msg::SubmitManualId
+            })>
+                <label for="manual-id">{ "Found an image elsewhere? Paste its link or ID: " }</label>
+                <input id="manual-id" type="text" placeholder="https://i.imgur.com/abc1234.jpg" value=&self.manual_id
+                       oninput=self.link.callback(|e: yew::events::InputData| Msg::ManualIdChanged(e.value)) />
+                <button type="submit" disabled=self.manual_fetch_task.is_some()>{ "Submit" }</button>
+                { match &self.manual_error {
+                    Some(error) => html! { <div class="settings-error">{ error }</div> },
+                    None => html! {},
+                } }
+            </form>
+        }
+    }
+
+    fn view_context_menu(&self) -> Html {
+        let menu = match &self.context_menu {
+            Some(menu) => menu,
+            None => return html! {},
+        };
+
+        let copy_id = menu.id.clone();
+        let favorite_id = menu.id.clone();
+        let hide_id = menu.id.clone();
+        let report_id = menu.id.clone();
+
+        html! {
+            <div id="context-menu-backdrop"
+                 onclick=self.link.callback(|_| Msg::CloseContextMenu)
+                 oncontextmenu=self.link.callback(|e: ContextMenuEvent| {
+                     e.prevent_default();
+                     Msg::CloseContextMenu
+                 })>
+                <div id="context-menu" style=format!("left: {}px; top: {}px;", menu.x, menu.y)>
+                    <button type="button" onclick=self.link.callback(move |_| Msg::CopyImageLink(copy_id.clone()))>{ "Copy link" }</button>
+                    <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href=original_url(&menu.id)>{ "Open original" }</a>
+                    <button type="button" onclick=self.link.callback(move |_| Msg::ToggleFavorite(favorite_id.clone()))>
+                        { if self.favorites.contains(&menu.id) { "Remove from favorites" } else { "Add to favorites" } }
+                    </button>
+                    <button type="button" onclick=self.link.callback(move |_| Msg::HideImage(hide_id.clone()))>{ "Hide" }</button>
+                    <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href=report_url(&menu.id)
+                       onclick=self.link.callback(move |_| Msg::ReportImage(report_id.clone()))>{ "Report" }</a>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_lightbox(&self) -> Html {
+        match self.lightbox.as_ref().and_then(|id| self.image(id)) {
+            Some(image) => {
+                let report_id = image.id.clone();
+
+                html! {
+                    <div id="lightbox">
+                        <img class="lightbox-image" referrerpolicy="no-referrer" src=self.image_src(image, false) />
+                        <p class="lightbox-controls">
+                            <button type="button" onclick=self.link.callback(|_| Msg::LightboxPrevious)>{ "Previous" }</button>
+                            <button type="button" onclick=self.link.callback(|_| Msg::CloseLightbox)>{ "Close" }</button>
+                            <button type="button" onclick=self.link.callback(|_| Msg::LightboxNext)>{ "Next" }</button>
+                            <a target="_blank" rel="noopener" referrerpolicy="no-referrer" href=report_url(&image.id)
+                               onclick=self.link.callback(move |_| Msg::ReportImage(report_id.clone()))>{ "Report" }</a>
+                        </p>
+                    </div>
+                }
+            }
+            None => html! {},
+        }
+    }
+
+    fn tile_style(&self) -> String {
+        if self.columns == 0 {
+            String::new()
+        } else {
+            format!("width: calc(100% * (1/{}) - 2*0.2em - 5px);", self.columns)
+        }
+    }
+}
+
+/// Reserves the space of an image before it is decoded, so the wall does not
+/// reflow when it shows up.
+/// Runs the pacing timers of client-core as the app's interval tasks.
+struct WebSchedule<'a> {
+    link: &'a ComponentLink<Model>,
+    timers: &'a mut Timers<Timer>,
+    interval_service: &'a mut IntervalService,
+}
+
+impl Schedule for WebSchedule<'_> {
+    fn every(&mut self, tick: Tick, every: Duration) {
+        let callback = match tick {
+            Tick::Probe => self.link.callback(|_| Msg::TryFind),
+            Tick::ResetRateLimit => self.link.callback(|_| Msg::ResetRateLimit),
+        };
+
+        self.timers.set(
+            tick_timer(tick),
+            Box::new(self.interval_service.spawn(every, callback)),
+        );
+    }
+
+    fn cancel(&mut self, tick: Tick) {
+        self.timers.cancel(&tick_timer(tick));
+    }
+}
+
+fn tick_timer(tick: Tick) -> Timer {
+    match tick {
+        Tick::Probe => Timer::Probe,
+        Tick::ResetRateLimit => Timer::ResetRateLimit,
+    }
+}
+
+/// Carries out what client-core's lifecycle asks of the connection.
+struct WebLink<'a> {
+    link: &'a ComponentLink<Model>,
+    timers: &'a mut Timers<Timer>,
+    timeout_service: &'a mut TimeoutService,
+}
+
+impl Link for WebLink<'_> {
+    fn send(&mut self, message: WsMessage) {
+        self.link.send_message(Msg::WsSend(message));
+    }
+
+    fn reconnect_after(&mut self, delay: Duration) {
+        self.timers.set(
+            Timer::Reconnect,
+            Box::new(
+                self.timeout_service
+                    .spawn(delay, self.link.callback(|_| Msg::WsConnect)),
+            ),
+        );
+    }
+
+    fn start(&mut self) {
+        self.link.send_message(Msg::Start);
+    }
+}
+
+fn aspect_ratio_style(image: &Image) -> String {
+    match image.size {
+        Some((width, height)) => format!("aspect-ratio: {} / {};", width, height),
+        None => String::new(),
+    }
+}
+
+fn touch_message(touches: &[stdweb::web::Touch], msg: fn(f64, f64) -> Msg) -> Msg {
+    match touches.first() {
+        Some(touch) => msg(touch.client_x(), touch.client_y()),
+        None => Msg::NoOp,
+    }
+}
+
+/// The websocket server of this page's origin, `/ws` on the same host.
+fn location_ws_url() -> String {
+    let protocol: String = js!(return location.protocol;)
+        .try_into()
+        .unwrap_or_default();
+    let host: String = js!(return location.host;).try_into().unwrap_or_default();
+
+    same_origin_ws_url(&protocol, &host)
+}
+
+/// Maps a path on the websocket server to its plain HTTP URL,
+/// `wss://example.org/ws` becoming `https://example.org{path}`.
+fn server_url(ws_url: &str, path: &str) -> String {
+    let base = if ws_url.starts_with("wss://") {
+        format!("https://{}", &ws_url["wss://".len()..])
+    } else if ws_url.starts_with("ws://") {
+        format!("http://{}", &ws_url["ws://".len()..])
+    } else {
+        ws_url.to_owned()
+    };
+
+    let base = base.trim_end_matches("/ws");
+
+    format!("{}{}", base, path)
+}
+
+fn encode_uri_component(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn download_file(name: &str, mime: &str, contents: &str) {
+    js! {
+        var blob = new Blob([@{contents}], { type: @{mime} });
+        var link = document.createElement("a");
+        link.href = URL.createObjectURL(blob);
+        link.download = @{name};
+        document.body.appendChild(link);
+        link.click();
+        document.body.removeChild(link);
+        URL.revokeObjectURL(link.href);
+    }
+}
+
+fn share_text(text: &str) {
+    js! {
+        var text = @{text};
+        if (navigator.share) {
+            navigator.share({ title: "Random Imgur Wall", text: text, url: location.href });
+        } else if (navigator.clipboard) {
+            navigator.clipboard.writeText(text + " " + location.href);
         }
     }
 }
 
+/// Extracts the image ID from whatever was pasted, like
+/// `https://i.imgur.com/abc1234.jpg` or just `abc1234`.
+fn parse_image_link(text: &str) -> Option<String> {
+    let text = text.trim().trim_end_matches('/');
+
+    normalize_id(text.rsplit('/').next().unwrap_or(text))
+}
+
+fn copy_to_clipboard(text: &str) -> bool {
+    // Older browsers and pages not served over HTTPS only have the
+    // deprecated `execCommand`.
+    js!(
+        var text = @{text};
+        if (navigator.clipboard && navigator.clipboard.writeText) {
+            navigator.clipboard.writeText(text);
+            return true;
+        }
+        var textarea = document.createElement("textarea");
+        textarea.value = text;
+        textarea.style.position = "fixed";
+        textarea.style.opacity = "0";
+        document.body.appendChild(textarea);
+        textarea.select();
+        var copied = false;
+        try {
+            copied = document.execCommand("copy");
+        } catch (err) {}
+        document.body.removeChild(textarea);
+        return copied;
+    )
+    .try_into()
+    .unwrap_or(false)
+}
+
+fn confirm(question: &str) -> bool {
+    js!(return confirm(@{question});)
+        .try_into()
+        .unwrap_or(false)
+}
+
+fn page_y_offset() -> f64 {
+    js!(return window.pageYOffset;).try_into().unwrap_or(0.0)
+}
+
 fn main() {
+    crash::set_panic_hook();
     yew::start_app::<Model>();
 }
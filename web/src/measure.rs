@@ -0,0 +1,21 @@
+use stdweb::Once;
+
+/// Loads `src` into an image outside the document and calls `on_measured`
+/// with its natural width and height, both 0 if it failed to load. The
+/// browser caches it, so the wall then shows it at once.
+pub fn measure(src: &str, on_measured: impl FnOnce(u32, u32) + 'static) {
+    let on_measured = Once(on_measured);
+
+    js! { @(no_return)
+        var on_measured = @{on_measured};
+        var image = new Image();
+        image.referrerPolicy = "no-referrer";
+        image.onload = function () {
+            on_measured(image.naturalWidth, image.naturalHeight);
+        };
+        image.onerror = function () {
+            on_measured(0, 0);
+        };
+        image.src = @{src};
+    }
+}
@@ -0,0 +1,131 @@
+//! A stand-in for the websocket server, enabled with `?mock=1`, so the
+//! frontend can be worked on without running the server or probing imgur.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+use client_core::generator::Generator;
+use protocol::{Counts, WsMessage, WsMessageType};
+
+/// How often other users seem to find something or come and go.
+pub const TICK: Duration = Duration::from_millis(500);
+
+/// Odds that a probe of ours finds an image.
+pub const FIND_PROBABILITY: f64 = 0.02;
+
+const OTHERS_FIND_PROBABILITY: f64 = 0.3;
+const COUNTS_CHANGE_PROBABILITY: f64 = 0.1;
+
+pub struct MockServer {
+    generator: Generator,
+    next_seq: u64,
+    others: Counts,
+    is_bruteforcing: bool,
+}
+
+impl MockServer {
+    pub fn new(generator: Generator) -> Self {
+        MockServer {
+            generator,
+            next_seq: 0,
+            others: Counts {
+                watching: 12,
+                bruteforcing: 5,
+            },
+            is_bruteforcing: false,
+        }
+    }
+
+    /// What the server would answer `msg` with.
+    pub fn reply(&mut self, msg: &WsMessage, now_ms: u64) -> Vec<WsMessage> {
+        match msg.msg_type {
+            WsMessageType::New => match &msg.text {
+                Some(id) => vec![
+                    WsMessage {
+                        text: Some(id.clone()),
+                        client_msg_id: msg.client_msg_id,
+                        ..WsMessage::new(WsMessageType::Ack)
+                    },
+                    self.find(id.clone(), now_ms),
+                ],
+                None => Vec::new(),
+            },
+            WsMessageType::Time => vec![WsMessage {
+                number: msg.number,
+                at: Some(now_ms),
+                ..WsMessage::new(WsMessageType::Time)
+            }],
+            WsMessageType::Start => {
+                self.is_bruteforcing = true;
+                vec![self.counts()]
+            }
+            WsMessageType::Stop => {
+                self.is_bruteforcing = false;
+                vec![self.counts()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// What the server would have sent since the last tick: finds of other
+    /// users now and then, and users coming and going.
+    pub fn tick(&mut self, now_ms: u64) -> Vec<WsMessage> {
+        let mut messages = Vec::new();
+
+        if self.generator.rng().gen_bool(OTHERS_FIND_PROBABILITY) {
+            let id = self.generator.id();
+            messages.push(self.find(id, now_ms));
+        }
+
+        let rng = self.generator.rng();
+
+        if rng.gen_bool(COUNTS_CHANGE_PROBABILITY) {
+            self.others.watching = (self.others.watching + rng.gen_range(0, 3)).saturating_sub(1);
+            self.others.bruteforcing = rng.gen_range(0, self.others.watching + 1);
+            messages.push(self.counts());
+        }
+
+        messages
+    }
+
+    fn find(&mut self, id: String, now_ms: u64) -> WsMessage {
+        self.next_seq += 1;
+
+        WsMessage {
+            text: Some(id),
+            at: Some(now_ms),
+            seq: Some(self.next_seq - 1),
+            ..WsMessage::new(WsMessageType::New)
+        }
+    }
+
+    /// The counts with us included.
+    fn counts(&self) -> WsMessage {
+        let counts = Counts {
+            watching: self.others.watching + 1,
+            bruteforcing: self.others.bruteforcing + self.is_bruteforcing as u64,
+        };
+
+        WsMessage {
+            text: serde_json::to_string(&counts).ok(),
+            ..WsMessage::new(WsMessageType::Counts)
+        }
+    }
+}
+
+/// A colored tile with the ID on it, shown instead of the imgur image.
+pub fn placeholder_src(id: &str) -> String {
+    let hue = id
+        .bytes()
+        .fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32))
+        % 360;
+
+    format!(
+        "data:image/svg+xml,<svg xmlns='http://www.w3.org/2000/svg' width='320' height='320'>\
+         <rect width='320' height='320' fill='hsl({},60%25,55%25)'/>\
+         <text x='160' y='170' font-size='40' font-family='monospace' text-anchor='middle' fill='white'>{}</text>\
+         </svg>",
+        hue, id
+    )
+}
@@ -0,0 +1,82 @@
+use serde::Deserialize;
+
+const DAY_SECS: f64 = 86_400.0;
+
+#[derive(Deserialize)]
+pub struct ArchivedFind {
+    pub at: u64,
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+pub struct DayArchive {
+    pub date: String,
+    pub start: u64,
+    pub finds: Vec<ArchivedFind>,
+}
+
+/// Replays a day of finds, `speed` seconds of the day passing for every
+/// real second.
+pub struct Playback {
+    pub archive: DayArchive,
+    pub position: f64,
+    pub speed: f64,
+    pub is_playing: bool,
+    reached: usize,
+}
+
+impl Playback {
+    pub fn new(mut archive: DayArchive, speed: f64) -> Self {
+        archive.finds.sort_by_key(|find| find.at);
+
+        Playback {
+            position: archive.start as f64,
+            archive,
+            speed,
+            is_playing: true,
+            reached: 0,
+        }
+    }
+
+    pub fn end(&self) -> f64 {
+        self.archive.start as f64 + DAY_SECS
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.end()
+    }
+
+    pub fn advance(&mut self, real_secs: f64) {
+        if self.is_playing {
+            self.seek(self.position + real_secs * self.speed);
+            self.is_playing = !self.is_finished();
+        }
+    }
+
+    pub fn seek(&mut self, position: f64) {
+        self.position = position.max(self.archive.start as f64).min(self.end());
+        self.reached = self
+            .archive
+            .finds
+            .iter()
+            .take_while(|find| find.at as f64 <= self.position)
+            .count();
+    }
+
+    /// The `limit` latest finds reached so far, newest first.
+    pub fn shown(&self, limit: usize) -> impl Iterator<Item = &ArchivedFind> {
+        self.archive.finds[..self.reached].iter().rev().take(limit)
+    }
+
+    /// The `limit` finds coming up next, soonest first.
+    pub fn upcoming(&self, limit: usize) -> impl Iterator<Item = &ArchivedFind> {
+        self.archive.finds[self.reached..].iter().take(limit)
+    }
+
+    /// The time of day reached, as `HH:MM` UTC.
+    pub fn clock(&self) -> String {
+        let secs = (self.position - self.archive.start as f64).max(0.0) as u64;
+
+        format!("{:02}:{:02}", secs / 3600, secs % 3600 / 60)
+    }
+}
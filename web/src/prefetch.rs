@@ -0,0 +1,57 @@
+use std::collections::VecDeque;
+
+/// Soon-visible images beyond this wait their turn are dropped, oldest
+/// first, they would have scrolled by anyway.
+const MAX_LOW_PRIORITY: usize = 50;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Priority {
+    /// Finds about to be inserted, waiting on their size.
+    High,
+    /// Images about to come into view.
+    Low,
+}
+
+/// Images to load ahead of the wall, a bounded number at a time: the newest
+/// finds first, then the images about to come into view in order.
+#[derive(Default)]
+pub struct Prefetcher {
+    high: VecDeque<String>,
+    low: VecDeque<String>,
+    in_flight: usize,
+}
+
+impl Prefetcher {
+    pub fn push(&mut self, id: String, priority: Priority) {
+        match priority {
+            Priority::High => self.high.push_back(id),
+            Priority::Low => {
+                if self.low.contains(&id) {
+                    return;
+                }
+
+                if self.low.len() == MAX_LOW_PRIORITY {
+                    self.low.pop_front();
+                }
+
+                self.low.push_back(id);
+            }
+        }
+    }
+
+    /// The next image to load, if fewer than `limit` are loading.
+    pub fn next(&mut self, limit: usize) -> Option<String> {
+        if self.in_flight >= limit {
+            return None;
+        }
+
+        let id = self.high.pop_back().or_else(|| self.low.pop_front())?;
+        self.in_flight += 1;
+
+        Some(id)
+    }
+
+    pub fn done(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{DataSaverMode, Settings};
+
+pub const STORAGE_KEY: &str = "random-imgur-wall.presets";
+pub const STRICT: &str = "Strict";
+
+/// Settings applied together in one click.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub settings: Settings,
+}
+
+/// Conservative settings for sharing a screen or letting children watch:
+/// blurred thumbnails showing up slowly and few at a time, and no
+/// bruteforcing. Nothing tells apart NSFW images, blurring covers for it.
+pub fn strict() -> Preset {
+    Preset {
+        name: STRICT.to_owned(),
+        settings: Settings {
+            concurrent_loaded: 20,
+            rate_limit: 10,
+            data_saver_mode: DataSaverMode::On,
+            blur_images: true,
+            watch_only: true,
+            ..Settings::default()
+        },
+    }
+}
+
+pub fn built_in() -> Vec<Preset> {
+    vec![strict()]
+}
+
+/// Presets the user saved, in the order they were first saved. Exported
+/// and imported as is.
+#[derive(Default, Serialize, Deserialize)]
+pub struct UserPresets {
+    presets: Vec<Preset>,
+}
+
+impl UserPresets {
+    /// Saves `settings` under `name`, replacing the preset already called
+    /// that.
+    pub fn save(&mut self, name: &str, settings: Settings) -> Result<(), String> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err("A preset needs a name.".to_owned());
+        }
+
+        if built_in().iter().any(|preset| preset.name == name) {
+            return Err(format!("{} is a built-in preset.", name));
+        }
+
+        let preset = Preset {
+            name: name.to_owned(),
+            settings,
+        };
+
+        match self.presets.iter_mut().find(|saved| saved.name == name) {
+            Some(saved) => *saved = preset,
+            None => self.presets.push(preset),
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.presets.len();
+        self.presets.retain(|preset| preset.name != name.trim());
+
+        self.presets.len() != len
+    }
+
+    /// Adds the presets of an export, replacing those with the same name,
+    /// and returns how many it took. Built-in names are skipped.
+    pub fn import(&mut self, imported: UserPresets) -> usize {
+        imported
+            .presets
+            .into_iter()
+            .filter(|preset| self.save(&preset.name, preset.settings.clone()).is_ok())
+            .count()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.presets.iter()
+    }
+}
+
+/// The built-in or saved preset called `name`.
+pub fn find(user_presets: &UserPresets, name: &str) -> Option<Preset> {
+    built_in()
+        .into_iter()
+        .chain(user_presets.iter().cloned())
+        .find(|preset| preset.name == name)
+}
@@ -0,0 +1,215 @@
+use protocol::{FindSource, QuietHours};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TileSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl TileSize {
+    pub fn class(self) -> &'static str {
+        match self {
+            TileSize::Small => "tiles-small",
+            TileSize::Medium => "tiles-medium",
+            TileSize::Large => "tiles-large",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataSaverMode {
+    Auto,
+    On,
+    Off,
+}
+
+/// Referrer policy of probe requests. Imgur does not need a referrer, the
+/// others only help with proxies that refuse requests without one.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ProbeReferrer {
+    NoReferrer,
+    Origin,
+    BrowserDefault,
+}
+
+impl Default for ProbeReferrer {
+    fn default() -> Self {
+        ProbeReferrer::NoReferrer
+    }
+}
+
+/// What clicking an image on the wall does.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImageClick {
+    /// The lightbox on mobile, the file in a new tab elsewhere.
+    Auto,
+    Lightbox,
+    File,
+    ImgurPage,
+    CopyLink,
+}
+
+impl Default for ImageClick {
+    fn default() -> Self {
+        ImageClick::Auto
+    }
+}
+
+/// Whose finds the wall shows as they are broadcast.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FeedFilter {
+    All,
+    Mine,
+    Others,
+}
+
+impl FeedFilter {
+    pub fn accepts(self, is_own: bool) -> bool {
+        match self {
+            FeedFilter::All => true,
+            FeedFilter::Mine => is_own,
+            FeedFilter::Others => !is_own,
+        }
+    }
+}
+
+impl Default for FeedFilter {
+    fn default() -> Self {
+        FeedFilter::All
+    }
+}
+
+/// How finds are laid out on the wall.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WallLayout {
+    Single,
+    /// Your finds and the community's side by side, each pane evicting and
+    /// rate limited on its own.
+    Split,
+}
+
+impl Default for WallLayout {
+    fn default() -> Self {
+        WallLayout::Single
+    }
+}
+
+/// User-tunable knobs, as synced to the server for logged in users.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub interval_ms: u64,
+    pub concurrent_loaded: usize,
+    pub show_from_top: bool,
+    pub rate_limit: u64,
+    pub tile_size: TileSize,
+    pub columns: usize,
+    pub data_saver_mode: DataSaverMode,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub archive_fallback_secs: u64,
+    #[serde(default)]
+    pub cache_busting: bool,
+    #[serde(default)]
+    pub no_store: bool,
+    #[serde(default)]
+    pub probe_referrer: ProbeReferrer,
+    #[serde(default)]
+    pub image_click: ImageClick,
+    /// Blurs images until they are clicked.
+    #[serde(default)]
+    pub blur_images: bool,
+    /// Keeps bruteforcing off until started by hand.
+    #[serde(default)]
+    pub watch_only: bool,
+    /// Lists the links of finds instead of loading the images.
+    #[serde(default)]
+    pub text_only: bool,
+    /// Kinds of clients whose finds are kept off the wall.
+    #[serde(default)]
+    pub hidden_sources: Vec<FindSource>,
+    #[serde(default)]
+    pub feed_filter: FeedFilter,
+    #[serde(default)]
+    pub layout: WallLayout,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            interval_ms: 100,
+            concurrent_loaded: 100,
+            show_from_top: false,
+            rate_limit: 2,
+            tile_size: TileSize::Medium,
+            columns: 0,
+            data_saver_mode: DataSaverMode::Auto,
+            quiet_hours: None,
+            archive_fallback_secs: 0,
+            cache_busting: false,
+            no_store: false,
+            probe_referrer: ProbeReferrer::default(),
+            image_click: ImageClick::default(),
+            blur_images: false,
+            watch_only: false,
+            text_only: false,
+            hidden_sources: Vec::new(),
+            feed_filter: FeedFilter::default(),
+            layout: WallLayout::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SettingsTab {
+    Basic,
+    Advanced,
+}
+
+/// Why the value typed into the input `field` was not applied. Shown next
+/// to the input until a valid value replaces it.
+pub struct SettingsError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// Parses the value of a number input, refusing anything outside
+/// `min..=max`.
+pub fn parse_number(value: &str, min: u64, max: u64, unit: &str) -> Result<u64, String> {
+    match value.trim().parse::<u64>() {
+        Ok(number) if number < min => Err(format!("must be at least {}{}", min, unit)),
+        Ok(number) if number > max => Err(format!("must be at most {}{}", max, unit)),
+        Ok(number) => Ok(number),
+        Err(_) => Err("must be a whole number".to_owned()),
+    }
+}
+
+/// Parses the `HH:MM` value of a time input into minutes since midnight.
+pub fn parse_time(value: &str) -> Option<u32> {
+    let mut parts = value.splitn(2, ':').map(|part| part.parse::<u32>().ok());
+    let (hours, minutes) = (parts.next()??, parts.next()??);
+
+    if hours < 24 && minutes < 60 {
+        Some(hours * 60 + minutes)
+    } else {
+        None
+    }
+}
+
+/// Like [`parse_time`], but an empty input clears the time instead of
+/// being refused.
+pub fn parse_optional_time(value: &str) -> Result<Option<u32>, String> {
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        parse_time(value)
+            .map(Some)
+            .ok_or_else(|| "must be a time like 22:30".to_owned())
+    }
+}
+
+pub fn format_time(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use yew::services::Task;
+
+/// The app's single-instance interval and timeout tasks.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Timer {
+    Probe,
+    ResetRequestsPerSecond,
+    ResetRateLimit,
+    ResendPendingFinds,
+    ReportRate,
+    Reconnect,
+    Mock,
+    Milestone,
+    Playback,
+    SwarmBackoff,
+}
+
+/// Owns running interval and timeout tasks by key. Yew tasks cancel
+/// themselves when dropped, so setting a key cancels the task it replaces and
+/// nothing keeps ticking once it has been cancelled here.
+pub struct Timers<K, T = Box<dyn Task>> {
+    tasks: HashMap<K, T>,
+}
+
+impl<K: Eq + Hash, T> Default for Timers<K, T> {
+    fn default() -> Self {
+        Timers {
+            tasks: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, T> Timers<K, T> {
+    pub fn set(&mut self, key: K, task: T) {
+        self.tasks.insert(key, task);
+    }
+
+    pub fn cancel(&mut self, key: &K) -> bool {
+        self.tasks.remove(key).is_some()
+    }
+
+    pub fn cancel_all(&mut self) {
+        self.tasks.clear();
+    }
+
+    pub fn is_set(&self, key: &K) -> bool {
+        self.tasks.contains_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Counts how many times it was dropped, which cancels a yew task.
+    struct FakeTask(Rc<Cell<u32>>);
+
+    impl Drop for FakeTask {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    fn timers() -> (Timers<Timer, FakeTask>, Rc<Cell<u32>>) {
+        (Timers::default(), Rc::default())
+    }
+
+    #[test]
+    fn set_timers_are_set_until_cancelled() {
+        let (mut timers, cancelled) = timers();
+
+        assert!(!timers.is_set(&Timer::Probe));
+
+        timers.set(Timer::Probe, FakeTask(cancelled.clone()));
+        assert!(timers.is_set(&Timer::Probe));
+        assert!(!timers.is_set(&Timer::Reconnect));
+        assert_eq!(cancelled.get(), 0);
+
+        assert!(timers.cancel(&Timer::Probe));
+        assert!(!timers.is_set(&Timer::Probe));
+        assert_eq!(cancelled.get(), 1);
+
+        assert!(!timers.cancel(&Timer::Probe));
+        assert_eq!(cancelled.get(), 1);
+    }
+
+    #[test]
+    fn setting_a_timer_again_cancels_the_one_it_replaces() {
+        let (mut timers, cancelled) = timers();
+
+        timers.set(Timer::Probe, FakeTask(cancelled.clone()));
+        timers.set(Timer::Probe, FakeTask(cancelled.clone()));
+
+        assert!(timers.is_set(&Timer::Probe));
+        assert_eq!(cancelled.get(), 1);
+    }
+
+    #[test]
+    fn cancel_all_cancels_every_timer() {
+        let (mut timers, cancelled) = timers();
+
+        timers.set(Timer::Probe, FakeTask(cancelled.clone()));
+        timers.set(Timer::Reconnect, FakeTask(cancelled.clone()));
+        timers.cancel_all();
+
+        assert!(!timers.is_set(&Timer::Probe));
+        assert!(!timers.is_set(&Timer::Reconnect));
+        assert_eq!(cancelled.get(), 2);
+    }
+}
@@ -0,0 +1,34 @@
+use std::collections::VecDeque;
+
+/// How many actions can be undone at most.
+const UNDO_LEN: usize = 10;
+
+/// The last few destructive actions, each with the ID of the toast offering
+/// to undo it.
+pub struct UndoStack<T> {
+    actions: VecDeque<(u32, T)>,
+}
+
+impl<T> Default for UndoStack<T> {
+    fn default() -> Self {
+        UndoStack {
+            actions: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> UndoStack<T> {
+    pub fn push(&mut self, toast_id: u32, action: T) {
+        if self.actions.len() == UNDO_LEN {
+            self.actions.pop_front();
+        }
+
+        self.actions.push_back((toast_id, action));
+    }
+
+    pub fn take(&mut self, toast_id: u32) -> Option<T> {
+        let position = self.actions.iter().position(|(id, _)| *id == toast_id)?;
+
+        self.actions.remove(position).map(|(_, action)| action)
+    }
+}
@@ -0,0 +1,76 @@
+/// A connection dropped after this long was healthy, the server is tried
+/// again first.
+const STABLE_AFTER_MS: f64 = 60_000.0;
+
+struct Upstream {
+    url: String,
+    /// Connections that failed or dropped early in a row.
+    failures: u32,
+}
+
+/// The servers listed in the config, in order of preference, and the one
+/// connected to or tried next.
+#[derive(Default)]
+pub struct Upstreams {
+    upstreams: Vec<Upstream>,
+    current: usize,
+    connected_at: Option<f64>,
+}
+
+impl Upstreams {
+    pub fn new(urls: impl IntoIterator<Item = String>) -> Upstreams {
+        Upstreams {
+            upstreams: urls
+                .into_iter()
+                .map(|url| Upstream { url, failures: 0 })
+                .collect(),
+            current: 0,
+            connected_at: None,
+        }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.upstreams
+            .get(self.current)
+            .map(|upstream| upstream.url.as_str())
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected_at.is_some()
+    }
+
+    pub fn has_mirrors(&self) -> bool {
+        self.upstreams.len() > 1
+    }
+
+    pub fn connected(&mut self, now: f64) {
+        self.connected_at = Some(now);
+    }
+
+    /// Counts a failed or dropped connection against the current server
+    /// and moves on to the healthiest one, the first listed among equals.
+    pub fn lost(&mut self, now: f64) {
+        let was_stable = self
+            .connected_at
+            .take()
+            .map_or(false, |at| now - at >= STABLE_AFTER_MS);
+
+        if let Some(upstream) = self.upstreams.get_mut(self.current) {
+            if was_stable {
+                upstream.failures = 0;
+                return;
+            }
+
+            upstream.failures += 1;
+        }
+
+        if let Some((index, _)) = self
+            .upstreams
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, upstream)| upstream.failures)
+        {
+            self.current = index;
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use protocol::{WsMessage, WsMessageType};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Build outputs renamed with a content hash so they can be cached forever.
+const FINGERPRINTED: [&str; 2] = ["web.wasm", "web.js"];
+
+/// JSON Schema and TypeScript types of every optional `WsMessage` field.
+/// Checked against the serialized struct so a new field cannot be missed.
+const FIELDS: [(&str, &str, &str); 12] = [
+    ("text", "string", "string"),
+    ("number", "integer", "number"),
+    ("url", "string", "string"),
+    ("reason", "string", "string"),
+    ("seq", "integer", "number"),
+    ("client_msg_id", "integer", "number"),
+    ("token", "string", "string"),
+    ("at", "integer", "number"),
+    (
+        "source",
+        "string",
+        r#""web" | "cli" | "desktop" | "extension" | "server-bot" | "archive-replay""#,
+    ),
+    ("origin", "string", "string"),
+    ("signature", "string", "string"),
+    ("labels", "array", "string[]"),
+];
+
+/// Configuration installed next to the binary by `cargo xtask release`.
+const DEFAULT_ENV: &str = "\
+WS_LISTEN_ADDR=0.0.0.0:8080
+DATA_DIR=/var/lib/random-imgur-wall
+";
+
+const SYSTEMD_UNIT: &str = "\
+[Unit]
+Description=Random Imgur Wall
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+EnvironmentFile=/etc/random-imgur-wall.env
+ExecStart=/usr/local/bin/random-imgur-wall
+StateDirectory=random-imgur-wall
+DynamicUser=yes
+Restart=on-failure
+
+[Install]
+WantedBy=multi-user.target
+";
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let result = match args.next().as_deref() {
+        Some("schema") => schema(
+            &args
+                .next()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| project_root().join("protocol/schema")),
+        ),
+        Some("dist") => dist(&project_root()),
+        Some("release") => release(&project_root(), args.next().as_deref()),
+        _ => Err(
+            "usage: cargo xtask schema [OUT_DIR] | cargo xtask dist | cargo xtask release [TARGET]"
+                .to_owned(),
+        ),
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn project_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Writes `ws-message.schema.json` and `ws-message.d.ts` to `out_dir`.
+fn schema(out_dir: &Path) -> Result<(), String> {
+    check_fields()?;
+
+    let msg_types = WsMessageType::ALL
+        .iter()
+        .map(|msg_type| serde_json::to_value(msg_type).map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    fs::create_dir_all(out_dir).map_err(|err| err.to_string())?;
+
+    write(
+        &out_dir.join("ws-message.schema.json"),
+        serde_json::to_string_pretty(&json_schema(&msg_types)).map_err(|err| err.to_string())?
+            + "\n",
+    )?;
+    write(&out_dir.join("ws-message.d.ts"), typescript(&msg_types))
+}
+
+fn check_fields() -> Result<(), String> {
+    let message =
+        serde_json::to_value(WsMessage::new(WsMessageType::New)).map_err(|err| err.to_string())?;
+    let fields = message
+        .as_object()
+        .map(|object| {
+            object
+                .keys()
+                .filter(|key| *key != "msg_type")
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    for field in &fields {
+        if !FIELDS.iter().any(|(name, _, _)| name == field) {
+            return Err(format!(
+                "WsMessage field `{}` is missing from FIELDS in xtask",
+                field
+            ));
+        }
+    }
+
+    for (name, _, _) in &FIELDS {
+        if !fields.iter().any(|field| field == name) {
+            return Err(format!(
+                "FIELDS in xtask lists `{}` which WsMessage lacks",
+                name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn json_schema(msg_types: &[Value]) -> Value {
+    let mut properties = Map::new();
+
+    properties.insert("msg_type".to_owned(), json!({ "enum": msg_types }));
+
+    for (name, json_type, _) in &FIELDS {
+        let mut property = json!({ "type": [json_type, "null"] });
+
+        if *json_type == "integer" {
+            property["minimum"] = json!(0);
+        } else if *json_type == "array" {
+            property["items"] = json!({ "type": "string" });
+        }
+
+        properties.insert((*name).to_owned(), property);
+    }
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "WsMessage",
+        "description": "A message exchanged over the Random Imgur Wall websocket. Generated by `cargo xtask schema`, do not edit.",
+        "type": "object",
+        "required": ["msg_type"],
+        "properties": properties,
+    })
+}
+
+fn typescript(msg_types: &[Value]) -> String {
+    let mut ts = String::from("// Generated by `cargo xtask schema`, do not edit.\n\n");
+
+    ts += "export type WsMessageType =\n";
+    for msg_type in msg_types {
+        ts += &format!("  | {}\n", msg_type);
+    }
+    ts += ";\n\nexport interface WsMessage {\n  msg_type: WsMessageType;\n";
+    for (name, _, ts_type) in &FIELDS {
+        ts += &format!("  {}?: {} | null;\n", name, ts_type);
+    }
+    ts += "}\n";
+
+    ts
+}
+
+/// Builds the frontend and the server in release mode into `dist/`:
+/// `dist/www` is the static site and `dist/server` the websocket server.
+fn dist(root: &Path) -> Result<(), String> {
+    let dist = root.join("dist");
+    let www = dist.join("www");
+
+    if dist.exists() {
+        fs::remove_dir_all(&dist).map_err(|err| err.to_string())?;
+    }
+
+    build_web(root, &www)?;
+    run(Command::new("cargo").current_dir(root).args([
+        "build",
+        "--release",
+        "--package",
+        "server",
+    ]))?;
+
+    fs::copy(
+        root.join("target/release")
+            .join(format!("server{}", env::consts::EXE_SUFFIX)),
+        dist.join(format!("server{}", env::consts::EXE_SUFFIX)),
+    )
+    .map_err(|err| format!("could not copy the server binary: {}", err))?;
+
+    Ok(())
+}
+
+/// Builds the frontend into `www`, with `config.json` pointing to `WS_URL`
+/// if set, and fingerprints it.
+fn build_web(root: &Path, www: &Path) -> Result<(), String> {
+    run(Command::new("cargo")
+        .current_dir(root.join("web"))
+        .args(["web", "deploy", "--release", "--output"])
+        .arg(www))?;
+
+    if let Ok(ws_url) = env::var("WS_URL") {
+        write(
+            &www.join("config.json"),
+            serde_json::to_string_pretty(&json!({ "ws_url": ws_url }))
+                .map_err(|err| err.to_string())?
+                + "\n",
+        )?;
+    }
+
+    fingerprint(www)
+}
+
+/// Packages a server serving the frontend itself, for `target` or the host,
+/// with a default configuration and a systemd unit, as
+/// `dist/random-imgur-wall-{target}.tar.gz`.
+fn release(root: &Path, target: Option<&str>) -> Result<(), String> {
+    let dist = root.join("dist");
+    let www = dist.join("www");
+    let target_name = target
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("{}-{}", env::consts::ARCH, env::consts::OS));
+    let name = format!("random-imgur-wall-{}", target_name);
+    let bundle = dist.join(&name);
+
+    if dist.exists() {
+        fs::remove_dir_all(&dist).map_err(|err| err.to_string())?;
+    }
+
+    build_web(root, &www)?;
+
+    let mut build = Command::new("cargo");
+    build.current_dir(root).env("WEB_ASSETS_DIR", &www).args([
+        "build",
+        "--release",
+        "--package",
+        "server",
+    ]);
+
+    if let Some(target) = target {
+        build.args(["--target", target]);
+    }
+
+    run(&mut build)?;
+
+    let exe_suffix = if target_name.contains("windows") {
+        ".exe"
+    } else {
+        ""
+    };
+    let release_dir = match target {
+        Some(target) => root.join("target").join(target).join("release"),
+        None => root.join("target/release"),
+    };
+
+    fs::create_dir_all(&bundle).map_err(|err| err.to_string())?;
+    fs::copy(
+        release_dir.join(format!("server{}", exe_suffix)),
+        bundle.join(format!("random-imgur-wall{}", exe_suffix)),
+    )
+    .map_err(|err| format!("could not copy the server binary: {}", err))?;
+
+    write(
+        &bundle.join("random-imgur-wall.env"),
+        DEFAULT_ENV.to_owned(),
+    )?;
+    write(
+        &bundle.join("random-imgur-wall.service"),
+        SYSTEMD_UNIT.to_owned(),
+    )?;
+
+    run(Command::new("tar")
+        .current_dir(&dist)
+        .arg("czf")
+        .arg(format!("{}.tar.gz", name))
+        .arg(&name))
+}
+
+/// Renames `FINGERPRINTED` files to `{stem}.{hash}.{ext}` and rewrites the
+/// references to them, the wasm file first since `web.js` loads it.
+fn fingerprint(www: &Path) -> Result<(), String> {
+    let mut renames = Vec::new();
+
+    for name in &FINGERPRINTED {
+        let path = www.join(name);
+        let mut contents =
+            fs::read(&path).map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+
+        if !renames.is_empty() {
+            let text = String::from_utf8(contents)
+                .map_err(|_| format!("{} references fingerprinted files but is not text", name))?;
+            contents = rewrite_references(text, &renames).into_bytes();
+        }
+
+        let hash = Sha256::digest(&contents)
+            .iter()
+            .take(8)
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let (stem, extension) = name.split_at(name.rfind('.').unwrap_or(name.len()));
+        let fingerprinted = format!("{}.{}{}", stem, hash, extension);
+
+        fs::write(www.join(&fingerprinted), contents).map_err(|err| err.to_string())?;
+        fs::remove_file(&path).map_err(|err| err.to_string())?;
+
+        renames.push((name.to_string(), fingerprinted));
+    }
+
+    let index = www.join("index.html");
+    let html = fs::read_to_string(&index).map_err(|err| err.to_string())?;
+
+    write(&index, rewrite_references(html, &renames))
+}
+
+fn rewrite_references(text: String, renames: &[(String, String)]) -> String {
+    renames.iter().fold(text, |text, (from, to)| {
+        text.replace(&format!("\"{}\"", from), &format!("\"{}\"", to))
+    })
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let status = command
+        .status()
+        .map_err(|err| format!("could not run {:?}: {}", command, err))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{:?} failed with {}", command, status))
+    }
+}
+
+fn write(path: &Path, contents: String) -> Result<(), String> {
+    fs::write(path, contents).map_err(|err| format!("could not write {}: {}", path.display(), err))
+}